@@ -1,10 +1,17 @@
 use clap::{Parser, Subcommand};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use brine_kiwi_compiler::{compile_schema, compile_schema_to_rust, decode_binary_schema};
+use brine_kiwi_compiler::{
+    check_compatibility, compile_schema_files, compile_schema_to_rust_with, decode_binary_schema, diff_schemas,
+    explain_binary_schema, schema_to_kiwi_text, GenOptions, SchemaChange,
+};
 use brine_kiwi_compiler::error::KiwiError;
-use brine_kiwi::decode_to_json;
+use brine_kiwi_compiler::parser::parse_schema;
+use brine_kiwi_compiler::tokenizer::tokenize_schema;
+use brine_kiwi_compiler::types::Schema;
+use brine_kiwi::{decode_message_to_json, decode_schema_to_json};
 
 #[derive(Parser)]
 #[command(name = "brine-kiwi-cli")]
@@ -27,6 +34,40 @@ enum Commands {
         output: Option<PathBuf>,
     },
 
+    /// Check a `.kiwi` IDL file for errors without writing any output
+    Validate {
+        /// Input `.kiwi` file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+
+    /// Reformat a `.kiwi` IDL file canonically (comments are not kept)
+    #[command(alias = "format")]
+    Fmt {
+        /// Input `.kiwi` file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Write the formatted schema here instead of printing to stdout
+        #[arg(short, long, conflicts_with = "write")]
+        output: Option<PathBuf>,
+
+        /// Overwrite the input file instead of printing to stdout
+        #[arg(long, alias = "in-place")]
+        write: bool,
+    },
+
+    /// List `// TODO` / `// FIXME` comments in a `.kiwi` IDL file
+    Todos {
+        /// Input `.kiwi` file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Comment prefix to report instead of TODO and FIXME (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+
     /// Decode a `.kiwi.bin` file to JSON (printed to stdout)
     Decode {
         /// Input `.kiwi.bin` file
@@ -34,6 +75,37 @@ enum Commands {
         input: PathBuf,
     },
 
+    /// Decode a data file holding one value of a schema type to JSON (printed to stdout)
+    DecodeData {
+        /// The `.kiwi` or `.kiwi.bin` schema the data was encoded with
+        #[arg(short, long)]
+        schema: PathBuf,
+
+        /// Input data file
+        #[arg(short, long)]
+        data: PathBuf,
+
+        /// Name of the type the data holds, e.g. a root message
+        #[arg(short = 't', long = "type")]
+        type_name: String,
+    },
+
+    /// Compare two `.kiwi` or `.kiwi.bin` files; exits non-zero on breaking changes
+    Diff {
+        /// The previous `.kiwi` or `.kiwi.bin` file
+        old: PathBuf,
+
+        /// The new `.kiwi` or `.kiwi.bin` file
+        new: PathBuf,
+    },
+
+    /// Describe the byte layout of a `.kiwi.bin` file, value by value
+    Explain {
+        /// Input `.kiwi.bin` file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+
     /// Generate Rust code from a `.kiwi` schema, by calling `compile_schema_to_rust`
     GenRust {
         /// Input `.kiwi` schema file
@@ -43,18 +115,160 @@ enum Commands {
         /// Output `.rs` file (if omitted, prints to stdout)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Do not derive `serde::Serialize` on the generated types
+        #[arg(long)]
+        no_serde: bool,
+
+        /// Append a `#[cfg(test)]` module with round-trip tests for every type
+        #[arg(long)]
+        emit_tests: bool,
+
+        /// Omit empty struct arrays from serialized output
+        #[arg(long)]
+        skip_empty_vecs: bool,
+
+        /// Generate `set_field(name, value)` on messages
+        #[arg(long)]
+        dynamic_setters: bool,
+
+        /// Append a `prelude` module re-exporting every generated type
+        #[arg(long)]
+        emit_prelude: bool,
+
+        /// Generate `byte[]` fields as `bytes::Bytes` instead of `Vec<u8>`
+        #[arg(long)]
+        use_bytes_crate: bool,
+
+        /// Generate string fields as `Cow<'a, str>` that can borrow from a `Value`
+        #[arg(long)]
+        zero_copy_strings: bool,
+
+        /// Emit code that uses `core`/`alloc` instead of `std`
+        #[arg(long)]
+        no_std: bool,
+
+        /// Map enum values missing from the schema to an `Unknown(String)` variant
+        #[arg(long)]
+        tolerant_enums: bool,
+
+        /// Mark generated enums `#[non_exhaustive]`
+        #[arg(long)]
+        non_exhaustive_enums: bool,
+
+        /// Extra trait to derive on every generated type (repeatable)
+        #[arg(long = "derive")]
+        derives: Vec<String>,
+
+        /// Path the generated module imports the runtime from
+        #[arg(long, default_value = "brine_kiwi")]
+        module_path: String,
     },
 }
 
+/// Render a rustc-style diagnostic for a parse error:
+///
+/// ```text
+/// error: Expected ";" but found "}"
+///  --> schema.kiwi:3:1
+///   |
+/// 3 | }
+///   | ^
+/// ```
+fn format_parse_error(path: &Path, text: &str, msg: &str, line: usize, column: usize) -> String {
+    let gutter = " ".repeat(line.to_string().len());
+    let mut out = format!("error: {}\n{}--> {}:{}:{}\n", msg, gutter, path.display(), line, column);
+
+    if let Some(source_line) = text.lines().nth(line.saturating_sub(1)) {
+        // Columns are byte offsets; keep tabs so the caret lines up visually.
+        let padding: String = source_line
+            .char_indices()
+            .take_while(|(i, _)| *i + 1 < column)
+            .map(|(_, c)| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        out.push_str(&format!("{} |\n", gutter));
+        out.push_str(&format!("{} | {}\n", line, source_line));
+        out.push_str(&format!("{} | {}^\n", gutter, padding));
+    }
+    out
+}
+
+/// Render `diff_schemas` output grouped by definition, in the order the
+/// definitions first appear:
+///
+/// ```text
+/// Example
+///   field "name" added (string, id 4)
+///   [breaking] field "type" type changed from Type to uint
+/// ```
+fn format_changes(changes: &[SchemaChange]) -> String {
+    if changes.is_empty() {
+        return "No changes\n".to_string();
+    }
+
+    let mut groups: Vec<(&str, Vec<&SchemaChange>)> = Vec::new();
+    for change in changes {
+        match groups.iter_mut().find(|(name, _)| *name == change.definition) {
+            Some((_, group)) => group.push(change),
+            None => groups.push((&change.definition, vec![change])),
+        }
+    }
+
+    let mut out = String::new();
+    for (name, group) in groups {
+        out.push_str(&format!("{}\n", name));
+        for change in group {
+            let marker = if change.breaking { "[breaking] " } else { "" };
+            out.push_str(&format!("  {}{}\n", marker, change.description));
+        }
+    }
+    out
+}
+
+/// Compile the schema file at `path` and its imports, printing a diagnostic
+/// pointing into the offending file and exiting on parse errors. Other
+/// errors are returned to the caller unchanged.
+fn compile_or_report(path: &Path) -> Result<(Schema, Vec<u8>), KiwiError> {
+    compile_schema_files(path).inspect_err(|err| {
+        if print_parse_error(path, err) {
+            process::exit(1);
+        }
+    })
+}
+
+/// If `err` is a parse error in `root` or a file it imports, prints it with
+/// the offending source line and returns true.
+fn print_parse_error(root: &Path, err: &KiwiError) -> bool {
+    let (file, inner) = match *err {
+        KiwiError::Import { ref path, ref source } => (path.as_path(), &**source),
+        ref other => (root, other),
+    };
+    if let KiwiError::ParseError { ref msg, line, column } = *inner {
+        if let Ok(text) = fs::read_to_string(file) {
+            eprint!("{}", format_parse_error(file, &text, msg, line, column));
+            return true;
+        }
+    }
+    false
+}
+
+/// Load a schema from a `.kiwi` text file or, for any other extension, a
+/// binary `.kiwi.bin` file.
+fn load_schema(path: &Path) -> Result<Schema, KiwiError> {
+    if path.extension().is_some_and(|ext| ext == "kiwi") {
+        Ok(compile_or_report(path)?.0)
+    } else {
+        decode_binary_schema(&fs::read(path).map_err(KiwiError::Io)?)
+    }
+}
+
 fn main() -> Result<(), KiwiError> {
     let cli = Cli::parse();
 
     match &cli.command {
         Commands::Compile { input, output } => {
-            // Read .kiwi text
-            let text = fs::read_to_string(input).map_err(KiwiError::Io)?;
-            // compile_schema → (Schema, Vec<u8>)
-            let (_schema, bin) = compile_schema(&text)?;
+            // compile_schema_files → (Schema, Vec<u8>), imports included
+            let (_schema, bin) = compile_or_report(input)?;
             // Determine output path
             let out_path = if let Some(o) = output {
                 o.clone()
@@ -69,24 +283,131 @@ fn main() -> Result<(), KiwiError> {
             Ok(())
         }
 
+        Commands::Validate { input } => {
+            match compile_schema_files(input) {
+                Ok(_) => {
+                    println!("OK");
+                    Ok(())
+                }
+                Err(err) => {
+                    if !print_parse_error(input, &err) {
+                        eprintln!("error: {}", err);
+                    }
+                    process::exit(1);
+                }
+            }
+        }
+
+        Commands::Fmt { input, output, write } => {
+            let text = fs::read_to_string(input).map_err(KiwiError::Io)?;
+            let schema = match tokenize_schema(&text).and_then(|tokens| parse_schema(&tokens)) {
+                Ok(schema) => schema,
+                Err(KiwiError::ParseError { msg, line, column }) => {
+                    eprint!("{}", format_parse_error(input, &text, &msg, line, column));
+                    process::exit(1);
+                }
+                Err(err) => return Err(err),
+            };
+            let formatted = schema_to_kiwi_text(&schema);
+            if *write {
+                fs::write(input, &formatted).map_err(KiwiError::Io)?;
+            } else if let Some(out_path) = output {
+                fs::write(out_path, &formatted).map_err(KiwiError::Io)?;
+            } else {
+                print!("{}", formatted);
+            }
+            Ok(())
+        }
+
+        Commands::Todos { input, tags } => {
+            let (schema, _bin) = compile_or_report(input)?;
+            let found = if tags.is_empty() {
+                schema.comment_tags()
+            } else {
+                let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+                schema.comment_tags_matching(&tags)
+            };
+            for (line, comment) in found {
+                println!("{}:{}: {}", input.display(), line, comment);
+            }
+            Ok(())
+        }
+
         Commands::Decode { input } => {
             // Read binary
             let data = fs::read(input).map_err(KiwiError::Io)?;
             // Decode to Schema (and ignore it here)
             let _schema = decode_binary_schema(&data)?;
             // Pretty-print JSON
-            let json = decode_to_json(&data)?;
+            let json = decode_schema_to_json(&data)?;
             println!("{}", json);
             Ok(())
         }
 
-        Commands::GenRust { input, output } => {
-            // Read .kiwi text
-            let text = fs::read_to_string(input).map_err(KiwiError::Io)?;
-            // Run compile_schema so parsing, verification, etc. all occur
-            let (schema, _bin) = compile_schema(&text)?;
+        Commands::DecodeData { schema, data, type_name } => {
+            let runtime = brine_kiwi::Schema::try_from(&load_schema(schema)?)?;
+            let Some(&type_id) = runtime.def_name_to_index.get(type_name) else {
+                eprintln!("error: No type named \"{}\" in {}", type_name, schema.display());
+                process::exit(1);
+            };
+            let bytes = fs::read(data).map_err(KiwiError::Io)?;
+            println!("{}", decode_message_to_json(&runtime, type_id as i32, &bytes)?);
+            Ok(())
+        }
+
+        Commands::Diff { old, new } => {
+            let old_schema = load_schema(old)?;
+            let new_schema = load_schema(new)?;
+            print!("{}", format_changes(&diff_schemas(&old_schema, &new_schema)));
+            if check_compatibility(&old_schema, &new_schema).is_err() {
+                process::exit(1);
+            }
+            Ok(())
+        }
+
+        Commands::Explain { input } => {
+            let data = fs::read(input).map_err(KiwiError::Io)?;
+            for line in explain_binary_schema(&data)? {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+
+        Commands::GenRust {
+            input,
+            output,
+            no_serde,
+            emit_tests,
+            skip_empty_vecs,
+            dynamic_setters,
+            emit_prelude,
+            use_bytes_crate,
+            zero_copy_strings,
+            no_std,
+            tolerant_enums,
+            non_exhaustive_enums,
+            derives,
+            module_path,
+        } => {
+            // Compile the schema and its imports so parsing, verification, etc. all occur
+            let (schema, _bin) = compile_or_report(input)?;
             // Generate Rust source
-            let rust_code = compile_schema_to_rust(&schema);
+            let options = GenOptions {
+                derive_serde:         !no_serde,
+                emit_tests:           *emit_tests,
+                skip_empty_vecs:      *skip_empty_vecs,
+                dynamic_setters:      *dynamic_setters,
+                emit_prelude:         *emit_prelude,
+                use_bytes_crate:      *use_bytes_crate,
+                zero_copy_strings:    *zero_copy_strings,
+                no_std:               *no_std,
+                tolerant_enums:       *tolerant_enums,
+                non_exhaustive_enums: *non_exhaustive_enums,
+                extra_derives:        derives.clone(),
+                module_path:          module_path.clone(),
+                ..GenOptions::default()
+            };
+            let rust_code = compile_schema_to_rust_with(&schema, &options);
             if let Some(out_path) = output {
                 fs::write(out_path, &rust_code).map_err(KiwiError::Io)?;
                 println!("Generated Rust code written to {}", out_path.display());
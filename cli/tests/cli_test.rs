@@ -0,0 +1,311 @@
+#![cfg(test)]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+fn example_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../example").join(name)
+}
+
+/// A fresh, empty directory for one test's files, unique to this run.
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bkiwi-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_explain_describes_offsets() {
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("explain")
+        .arg("--input")
+        .arg(example_path("simple.kiwi.bin"))
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("offset 0: definition count = 3"));
+    assert!(stdout.contains("offset 1: definition[0] name = \"Type\""));
+    assert!(stdout.contains("offset 6: definition[0] kind = 0 (Enum)"));
+    assert!(stdout.contains("definition[1] name = \"Color\""));
+    assert!(stdout.contains("definition[2] name = \"Example\""));
+    assert!(stdout.contains("field[0] name = \"clientID\""));
+}
+
+#[test]
+fn test_compile_reports_parse_error_location() {
+    let dir = temp_dir("parse-error");
+    let path = dir.join("bad.kiwi");
+    std::fs::write(&path, "message Example {\n  uint id = -1;\n}\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("compile")
+        .arg("--input")
+        .arg(&path)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("error: Negative id \"-1\" is not allowed"));
+    assert!(stderr.contains(&format!(" --> {}:2:13", path.display())));
+    assert!(stderr.contains("2 |   uint id = -1;\n  |             ^\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_validate_reports_pass_and_fail() {
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("validate")
+        .arg("--input")
+        .arg(example_path("simple.kiwi"))
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OK\n");
+
+    let dir = temp_dir("validate");
+    let path = dir.join("bad.kiwi");
+    std::fs::write(&path, "message Example {\n  Missing thing = 1;\n}\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("validate")
+        .arg("--input")
+        .arg(&path)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("The type \"Missing\" is not defined for field \"thing\""));
+    // Nothing is written next to the input.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diff_groups_changes_and_fails_on_breaking() {
+    let dir = temp_dir("diff");
+    let old = dir.join("old.kiwi.bin");
+    let new = dir.join("new.kiwi.bin");
+    let (_, old_bin) =
+        brine_kiwi_compiler::compile_schema("message Example { uint id = 1; string name = 2; }").unwrap();
+    let (_, new_bin) =
+        brine_kiwi_compiler::compile_schema("message Example { uint id = 1; string note = 3; }").unwrap();
+    std::fs::write(&old, &old_bin).unwrap();
+    std::fs::write(&new, &new_bin).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Example\n  [breaking] field \"name\" removed\n  field \"note\" added (string, id 3)\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("diff")
+        .arg(&old)
+        .arg(&old)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "No changes\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diff_reads_text_schemas() {
+    let dir = temp_dir("diff-text");
+    let old = dir.join("old.kiwi.bin");
+    let new = dir.join("new.kiwi");
+    let (_, old_bin) = brine_kiwi_compiler::compile_schema(
+        "message Example { uint id = 1; string name = 2; }",
+    )
+    .unwrap();
+    std::fs::write(&old, &old_bin).unwrap();
+    std::fs::write(&new, "message Example { uint id = 1; string name = 2; float ratio = 3; }\nenum Kind { A; }\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Example\n  field \"ratio\" added (float, id 3)\nKind\n  enum added\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("diff")
+        .arg(&new)
+        .arg(&old)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_todos_lists_tagged_comments() {
+    let dir = temp_dir("todos");
+    let path = dir.join("tagged.kiwi");
+    std::fs::write(
+        &path,
+        "// TODO: split into two messages\nmessage Example {\n  uint id = 1; // FIXME widen to uint64\n  // NOTE: ids start at 1\n}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("todos")
+        .arg("--input")
+        .arg(&path)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        format!(
+            "{0}:1: TODO: split into two messages\n{0}:3: FIXME widen to uint64\n",
+            path.display()
+        )
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .args(["todos", "--tag", "NOTE", "--input"])
+        .arg(&path)
+        .output()
+        .expect("failed to run bkiwi");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, format!("{}:4: NOTE: ids start at 1\n", path.display()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fmt_prints_or_rewrites() {
+    let dir = temp_dir("fmt");
+    let path = dir.join("messy.kiwi");
+    let messy = "message Example {uint id=1;  string[] names = 2 [deprecated];}\n";
+    let canonical = "message Example {\n  uint id        = 1;\n  string[] names = 2 [deprecated];\n}\n";
+    std::fs::write(&path, messy).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("fmt")
+        .arg("--input")
+        .arg(&path)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), canonical);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), messy);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("fmt")
+        .arg("--input")
+        .arg(&path)
+        .arg("--write")
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), canonical);
+
+    // `format` is the same command; formatting canonical text changes
+    // nothing, and `--output` writes elsewhere.
+    let out = dir.join("out.kiwi");
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("format")
+        .arg("--input")
+        .arg(&path)
+        .arg("--output")
+        .arg(&out)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), canonical);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+        .arg("format")
+        .arg("--input")
+        .arg(&path)
+        .arg("--in-place")
+        .arg("--output")
+        .arg(&out)
+        .output()
+        .expect("failed to run bkiwi");
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compile_follows_imports() {
+    let dir = temp_dir("import");
+    let main = dir.join("main.kiwi");
+    std::fs::write(&main, "import \"types.kiwi\";\nmessage Example { Type t = 1; }\n").unwrap();
+    std::fs::write(dir.join("types.kiwi"), "enum Type { A; }\n").unwrap();
+
+    let validate = || {
+        Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+            .arg("validate")
+            .arg("--input")
+            .arg(&main)
+            .output()
+            .expect("failed to run bkiwi")
+    };
+    let output = validate();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OK\n");
+
+    // Parse errors point into the imported file
+    std::fs::write(dir.join("types.kiwi"), "enum Type {\n  A B\n}\n").unwrap();
+    let output = validate();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(&format!(" --> {}:2:5", dir.join("types.kiwi").display())), "{}", stderr);
+    assert!(stderr.contains("2 |   A B\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_decode_data_prints_json() {
+    let dir = temp_dir("decode-data");
+    let schema = dir.join("example.kiwi.bin");
+    let data = dir.join("example.bin");
+    let (_, bin) =
+        brine_kiwi_compiler::compile_schema("message Example { uint id = 1; string name = 2; }").unwrap();
+    std::fs::write(&schema, &bin).unwrap();
+    std::fs::write(&data, [1, 7, 2, b'h', b'i', 0, 0]).unwrap();
+
+    let decode = |type_name: &str| {
+        Command::new(env!("CARGO_BIN_EXE_bkiwi"))
+            .arg("decode-data")
+            .arg("--schema")
+            .arg(&schema)
+            .arg("--data")
+            .arg(&data)
+            .arg("--type")
+            .arg(type_name)
+            .output()
+            .expect("failed to run bkiwi")
+    };
+    let output = decode("Example");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"id\": 7"), "{}", stdout);
+    assert!(stdout.contains("\"name\": \"hi\""), "{}", stdout);
+
+    let output = decode("Missing");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("No type named \"Missing\""));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,223 @@
+use crate::{
+    types::{Definition, DefinitionKind, Field, Schema},
+    utils::quote,
+};
+
+/// One difference between two versions of a schema, as found by `diff_schemas`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaChange {
+    /// Name of the definition the change belongs to.
+    pub definition:  String,
+    /// Human-readable description, e.g. `field "id" added (uint, id 4)`.
+    pub description: String,
+    /// Whether data written with one schema can no longer be read with the other.
+    pub breaking:    bool,
+}
+
+/// A breaking change found by `check_compatibility`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompatError {
+    /// Name of the definition the change belongs to.
+    pub definition:  String,
+    /// Human-readable description, e.g. `field "id" type changed from uint to int`.
+    pub description: String,
+}
+
+impl std::fmt::Display for CompatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.definition, self.description)
+    }
+}
+
+/// Checks that `new` can replace `old` on the wire, returning every breaking
+/// change `diff_schemas` finds. Adding message fields, enum variants or
+/// definitions is allowed, as is deprecating or then removing a message
+/// field. Binary schemas carry no deprecation flag, so a field removed from
+/// a decoded `.kiwi.bin` baseline always counts as breaking.
+pub fn check_compatibility(old: &Schema, new: &Schema) -> Result<(), Vec<CompatError>> {
+    let errors: Vec<CompatError> = diff_schemas(old, new)
+        .into_iter()
+        .filter(|change| change.breaking)
+        .map(|change| CompatError {
+            definition:  change.definition,
+            description: change.description,
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Compares `old` and `new` definition by definition (matched by name) and
+/// field by field, returning every change in `old`'s definition order
+/// followed by added definitions.
+///
+/// Struct fields are positional, so any change to a struct is breaking. For
+/// messages, adding fields or removing deprecated ones is safe while removing
+/// other fields or changing a field's type, array-ness or id is not. Enums
+/// may gain variants but not lose or renumber them.
+pub fn diff_schemas(old: &Schema, new: &Schema) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+
+    for old_def in &old.definitions {
+        let mut push = |description: String, breaking: bool| {
+            changes.push(SchemaChange {
+                definition: old_def.name.clone(),
+                description,
+                breaking,
+            });
+        };
+
+        let new_def = match new.find_definition(&old_def.name) {
+            Some(def) => def,
+            None => {
+                push(format!("{} removed", kind_name(&old_def.kind)), true);
+                continue;
+            }
+        };
+
+        if new_def.kind != old_def.kind {
+            push(
+                format!(
+                    "changed from {} to {}",
+                    kind_name(&old_def.kind),
+                    kind_name(&new_def.kind)
+                ),
+                true,
+            );
+            continue;
+        }
+
+        for old_field in &old_def.fields {
+            match find_field(new_def, &old_field.name) {
+                Some(new_field) => {
+                    for description in field_differences(&old_def.kind, old_field, new_field) {
+                        push(description, true);
+                    }
+                }
+                // Writers already skip deprecated message fields.
+                None if old_def.kind == DefinitionKind::Message && old_field.is_deprecated => {
+                    push(format!("deprecated field {} removed", quote(&old_field.name)), false)
+                }
+                None => push(format!("{} {} removed", item_name(&old_def.kind), quote(&old_field.name)), true),
+            }
+        }
+
+        for new_field in &new_def.fields {
+            if find_field(old_def, &new_field.name).is_none() {
+                // Only messages can grow without breaking older readers.
+                let breaking = new_def.kind == DefinitionKind::Struct;
+                push(
+                    format!(
+                        "{} {} added ({})",
+                        item_name(&new_def.kind),
+                        quote(&new_field.name),
+                        describe_field(&new_def.kind, new_field)
+                    ),
+                    breaking,
+                );
+            }
+        }
+    }
+
+    for new_def in &new.definitions {
+        if !old.definitions.iter().any(|d| d.name == new_def.name) {
+            changes.push(SchemaChange {
+                definition:  new_def.name.clone(),
+                description: format!("{} added", kind_name(&new_def.kind)),
+                breaking:    false,
+            });
+        }
+    }
+
+    changes
+}
+
+fn find_field<'a>(def: &'a Definition, name: &str) -> Option<&'a Field> {
+    def.fields.iter().find(|f| f.name == name)
+}
+
+/// Lists the incompatible differences between two versions of the same field.
+fn field_differences(kind: &DefinitionKind, old: &Field, new: &Field) -> Vec<String> {
+    let mut out = Vec::new();
+    let name = quote(&old.name);
+
+    if *kind == DefinitionKind::Enum {
+        if old.reserved_index != new.reserved_index {
+            out.push(format!(
+                "variant {} value changed from {} to {}",
+                name, old.reserved_index, new.reserved_index
+            ));
+        }
+        return out;
+    }
+
+    if old.type_ != new.type_ {
+        out.push(format!(
+            "field {} type changed from {} to {}",
+            name,
+            old.type_.as_deref().unwrap_or("?"),
+            new.type_.as_deref().unwrap_or("?")
+        ));
+    }
+    if old.key_type != new.key_type && old.is_map() && new.is_map() {
+        out.push(format!(
+            "field {} key type changed from {} to {}",
+            name,
+            old.key_type.as_deref().unwrap_or("?"),
+            new.key_type.as_deref().unwrap_or("?")
+        ));
+    }
+    if old.array_depth != new.array_depth || old.is_map() != new.is_map() {
+        out.push(format!(
+            "field {} changed from {} to {}",
+            name,
+            describe_shape(old),
+            describe_shape(new)
+        ));
+    }
+    if old.reserved_index != new.reserved_index {
+        let what = if *kind == DefinitionKind::Struct { "position" } else { "id" };
+        out.push(format!(
+            "field {} {} changed from {} to {}",
+            name, what, old.reserved_index, new.reserved_index
+        ));
+    }
+    out
+}
+
+fn describe_field(kind: &DefinitionKind, field: &Field) -> String {
+    match kind {
+        DefinitionKind::Enum => format!("value {}", field.reserved_index),
+        _ => format!("{}, id {}", field.type_text(), field.reserved_index),
+    }
+}
+
+fn describe_shape(field: &Field) -> String {
+    if field.is_map() {
+        return "map".to_string();
+    }
+    match field.array_depth {
+        0 => "single value".to_string(),
+        1 => "array".to_string(),
+        n => format!("{}-dimensional array", n),
+    }
+}
+
+fn kind_name(kind: &DefinitionKind) -> &'static str {
+    match kind {
+        DefinitionKind::Enum    => "enum",
+        DefinitionKind::Struct  => "struct",
+        DefinitionKind::Message => "message",
+    }
+}
+
+fn item_name(kind: &DefinitionKind) -> &'static str {
+    match kind {
+        DefinitionKind::Enum => "variant",
+        _                    => "field",
+    }
+}
@@ -1,29 +1,295 @@
-use brine_kiwi_schema::ByteBuffer;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use brine_kiwi_schema::{ByteBuffer, MAP_FIELD_FLAG};
 use crate::{
-    types::{DefinitionKind, Field, Schema},
+    types::{Definition, DefinitionKind, Field, Schema, Span},
     verifier::{verify_schema, NATIVE_TYPES},
-    tokenizer::tokenize_schema,
+    tokenizer::tokenize_schema_with_comments,
     parser::parse_schema,
     error::KiwiError,
 };
 
 /// Compile a textual schema into `(Schema, Vec<u8>)`.
 /// Returns `Err(KiwiError)` if tokenization/parsing/verification fails.
+/// The schema keeps the source's comments in `comments`.
 pub fn compile_schema(text: &str) -> Result<(Schema, Vec<u8>), KiwiError> {
-    let tokens = tokenize_schema(text)?;
-    let schema = parse_schema(&tokens)?;
+    let schema = parse_schema_text(text)?;
     verify_schema(&schema)?;
     let bin = encode_binary_schema(&schema)?;
     Ok((schema, bin))
 }
 
+/// Tokenizes and parses `text`, keeping its comments.
+fn parse_schema_text(text: &str) -> Result<Schema, KiwiError> {
+    let (tokens, comments) = tokenize_schema_with_comments(text)?;
+    let mut schema = parse_schema(&tokens)?;
+    schema.comments = comments
+        .into_iter()
+        .map(|token| (token.line, token.text.trim_start_matches('/').trim().to_string()))
+        .collect();
+    Ok(schema)
+}
+
+/// Compile the schema file at `root` together with everything it imports.
+/// `import "other.kiwi";` paths are resolved relative to the importing file,
+/// and each file is read once however often it is imported. Imported
+/// definitions come before those of the file importing them, and the result
+/// is verified as one schema, so a name defined in two files is reported as
+/// defined twice. The package and comments are those of `root`.
+///
+/// Errors in an imported file come back as `KiwiError::Import` naming it;
+/// import cycles are a `VerifierError`.
+pub fn compile_schema_files(root: &Path) -> Result<(Schema, Vec<u8>), KiwiError> {
+    compile_files_cached(root, None)
+}
+
+fn compile_files_cached(root: &Path, cache: Option<&mut SchemaCache>) -> Result<(Schema, Vec<u8>), KiwiError> {
+    let key = root.canonicalize()?;
+    let mut loader = ImportLoader::new(FileSource, cache);
+    let schema = loader.parse(&fs::read_to_string(root)?)?;
+    let schema = loader.load_imports(root, Some(key), schema)?;
+    loader.finish(schema)
+}
+
+/// Like `compile_schema_files`, but the root schema is `text` and imports are
+/// loaded by `resolver`, which is given each import exactly as written and
+/// returns that file's source. Useful when schemas live somewhere other than
+/// the filesystem, such as embedded in the binary or fetched over the network.
+///
+/// Imports are identified by their written name, so `"a.kiwi"` and
+/// `"./a.kiwi"` are two different files as far as duplicate and cycle
+/// detection go. Errors follow `compile_schema_files`: a resolver error or a
+/// bad imported file is a `KiwiError::Import`, and a cycle a `VerifierError`.
+pub fn compile_schema_with_resolver<F>(text: &str, resolver: F) -> Result<(Schema, Vec<u8>), KiwiError>
+where
+    F: FnMut(&str) -> Result<String, KiwiError>,
+{
+    compile_with_resolver_cached(text, resolver, None)
+}
+
+fn compile_with_resolver_cached<F>(
+    text: &str,
+    resolver: F,
+    cache: Option<&mut SchemaCache>,
+) -> Result<(Schema, Vec<u8>), KiwiError>
+where
+    F: FnMut(&str) -> Result<String, KiwiError>,
+{
+    let mut loader = ImportLoader::new(ResolverSource(resolver), cache);
+    let schema = loader.parse(text)?;
+    let schema = loader.load_imports(Path::new(""), None, schema)?;
+    loader.finish(schema)
+}
+
+/// Where `ImportLoader` finds imported files.
+trait SchemaSource {
+    /// The name of `import` as written in the file named `from`.
+    fn join(&self, from: &Path, import: &str) -> PathBuf;
+    /// The identity of the file named `name`, used to spot repeats and cycles.
+    fn key(&self, name: &Path) -> Option<PathBuf>;
+    fn read(&mut self, name: &Path) -> Result<String, KiwiError>;
+}
+
+struct FileSource;
+
+impl SchemaSource for FileSource {
+    fn join(&self, from: &Path, import: &str) -> PathBuf {
+        from.parent().unwrap_or(Path::new("")).join(import)
+    }
+
+    fn key(&self, name: &Path) -> Option<PathBuf> {
+        name.canonicalize().ok()
+    }
+
+    fn read(&mut self, name: &Path) -> Result<String, KiwiError> {
+        Ok(fs::read_to_string(name)?)
+    }
+}
+
+struct ResolverSource<F>(F);
+
+impl<F: FnMut(&str) -> Result<String, KiwiError>> SchemaSource for ResolverSource<F> {
+    fn join(&self, _from: &Path, import: &str) -> PathBuf {
+        PathBuf::from(import)
+    }
+
+    fn key(&self, name: &Path) -> Option<PathBuf> {
+        Some(name.to_path_buf())
+    }
+
+    fn read(&mut self, name: &Path) -> Result<String, KiwiError> {
+        (self.0)(&name.to_string_lossy())
+    }
+}
+
+/// State for the depth-first walk over a schema's imports.
+struct ImportLoader<'c, S> {
+    source:      S,
+    /// Parsed files from earlier compilations, if any.
+    cache:       Option<&'c mut SchemaCache>,
+    /// Files being loaded, outermost first, as `(key, as written)`.
+    stack:       Vec<(PathBuf, PathBuf)>,
+    done:        HashSet<PathBuf>,
+    definitions: Vec<Definition>,
+}
+
+impl<'c, S: SchemaSource> ImportLoader<'c, S> {
+    fn new(source: S, cache: Option<&'c mut SchemaCache>) -> Self {
+        ImportLoader { source, cache, stack: Vec::new(), done: HashSet::new(), definitions: Vec::new() }
+    }
+
+    /// Tokenizes and parses `text`, or copies the result from the cache.
+    fn parse(&mut self, text: &str) -> Result<Schema, KiwiError> {
+        let Some(cache) = self.cache.as_mut() else {
+            return parse_schema_text(text);
+        };
+        if let Some(schema) = cache.parsed.get(text) {
+            return Ok(schema.clone());
+        }
+        let schema = parse_schema_text(text)?;
+        cache.parsed.insert(text.to_string(), schema.clone());
+        Ok(schema)
+    }
+
+    /// Loads the imported file `name` and its imports, appending their
+    /// definitions.
+    fn load(&mut self, name: &Path) -> Result<(), KiwiError> {
+        let schema = self
+            .source
+            .read(name)
+            .and_then(|text| self.parse(&text))
+            .map_err(|err| KiwiError::Import { path: name.to_path_buf(), source: Box::new(err) })?;
+        let key = self.source.key(name).unwrap_or_else(|| name.to_path_buf());
+        self.load_imports(name, Some(key), schema)?;
+        Ok(())
+    }
+
+    /// Loads the imports of `schema`, the file `name`, then appends its own
+    /// definitions and returns the schema with them taken out. `key` is
+    /// `None` for a root that isn't a file imports could name.
+    fn load_imports(&mut self, name: &Path, key: Option<PathBuf>, mut schema: Schema) -> Result<Schema, KiwiError> {
+        if let Some(key) = &key {
+            self.stack.push((key.clone(), name.to_path_buf()));
+        }
+        for import in &schema.imports {
+            let import_path = self.source.join(name, import);
+            let import_key = self.source.key(&import_path);
+            if let Some(at) = self.stack.iter().position(|(key, _)| Some(key) == import_key.as_ref()) {
+                let mut chain: Vec<String> = self.stack[at..].iter().map(|(_, p)| p.display().to_string()).collect();
+                chain.push(import_path.display().to_string());
+                return Err(KiwiError::VerifierError(format!("Import cycle: {}", chain.join(" -> "))));
+            }
+            if import_key.as_ref().is_some_and(|key| self.done.contains(key)) {
+                continue;
+            }
+            self.load(&import_path)?;
+        }
+        if let Some(key) = key {
+            self.stack.pop();
+            self.done.insert(key);
+        }
+
+        self.definitions.append(&mut schema.definitions);
+        Ok(schema)
+    }
+
+    /// Gives the root `schema` every loaded definition, then verifies and
+    /// encodes it.
+    fn finish(self, mut schema: Schema) -> Result<(Schema, Vec<u8>), KiwiError> {
+        schema.imports = Vec::new();
+        schema.definitions = self.definitions;
+        verify_schema(&schema)?;
+        let bin = encode_binary_schema(&schema)?;
+        Ok((schema, bin))
+    }
+}
+
+/// Memoizes compilations by source text across calls, for builds that
+/// compile many schema files importing the same shared ones. `compile`
+/// caches whole results; `compile_files` and `compile_with_resolver` cache
+/// each file they parse, so a shared import is only tokenized and parsed
+/// once per build (every root is still verified, since its imports differ).
+/// Failed compilations are not cached.
+#[derive(Debug, Default)]
+pub struct SchemaCache {
+    entries: HashMap<String, (Schema, Vec<u8>)>,
+    parsed:  HashMap<String, Schema>,
+}
+
+impl SchemaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like `compile_schema`, but returns the cached result if `text` has
+    /// been compiled before.
+    pub fn compile(&mut self, text: &str) -> Result<(&Schema, &[u8]), KiwiError> {
+        if !self.entries.contains_key(text) {
+            let compiled = compile_schema(text)?;
+            self.entries.insert(text.to_string(), compiled);
+        }
+        let (schema, bin) = &self.entries[text];
+        Ok((schema, bin))
+    }
+
+    /// Like `compile_schema_files`, parsing each file through the cache.
+    pub fn compile_files(&mut self, root: &Path) -> Result<(Schema, Vec<u8>), KiwiError> {
+        compile_files_cached(root, Some(self))
+    }
+
+    /// Like `compile_schema_with_resolver`, parsing `text` and each import
+    /// through the cache.
+    pub fn compile_with_resolver<F>(&mut self, text: &str, resolver: F) -> Result<(Schema, Vec<u8>), KiwiError>
+    where
+        F: FnMut(&str) -> Result<String, KiwiError>,
+    {
+        compile_with_resolver_cached(text, resolver, Some(self))
+    }
+
+    /// Number of distinct schemas currently cached by `compile`.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Number of distinct files parsed by `compile_files` and
+    /// `compile_with_resolver`.
+    pub fn parsed_len(&self) -> usize {
+        self.parsed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.parsed.is_empty()
+    }
+}
+
 /// Decode a binary schema buffer back into a `Schema`.
 /// Returns `Err(KiwiError)` on any read failure or invalid data.
 pub fn decode_binary_schema(buffer: &[u8]) -> Result<Schema, KiwiError> {
+    decode_binary_schema_traced(buffer, None)
+}
+
+/// Decode a binary schema buffer while describing its layout, one line per
+/// value read (`offset 0: definition count = 3`, …). Meant for learning and
+/// debugging the binary schema format.
+pub fn explain_binary_schema(buffer: &[u8]) -> Result<Vec<String>, KiwiError> {
+    let mut trace = Vec::new();
+    decode_binary_schema_traced(buffer, Some(&mut trace))?;
+    Ok(trace)
+}
+
+/// Shared read loop behind `decode_binary_schema` / `explain_binary_schema`.
+/// When `trace` is set, every value read is recorded with its byte offset.
+fn decode_binary_schema_traced(
+    buffer: &[u8],
+    mut trace: Option<&mut Vec<String>>,
+) -> Result<Schema, KiwiError> {
     struct FieldTemp {
         name:           String,
         type_num:       i32,
-        is_array:       bool,
+        array_depth:    u32,
+        key_type_num:   Option<i32>,
         reserved_index: u32,
     }
 
@@ -33,26 +299,42 @@ pub fn decode_binary_schema(buffer: &[u8]) -> Result<Schema, KiwiError> {
         fields: Vec<FieldTemp>,
     }
 
+    let mut note = |offset: usize, text: String| {
+        if let Some(trace) = trace.as_mut() {
+            trace.push(format!("offset {}: {}", offset, text));
+        }
+    };
+
     let mut bb = ByteBuffer::new(buffer);
 
     // Read definition count
+    let offset = bb.index();
     let definition_count = bb
         .read_var_uint()
         .map_err(|e| KiwiError::DecodeError(format!("Failed to read definition count: {:?}", e)))?;
+    note(offset, format!("definition count = {}", definition_count));
+
+    // Each definition takes at least three bytes (name terminator, kind,
+    // field count), so a count beyond the remaining bytes is corrupt.
+    if definition_count as usize > bb.remaining() {
+        return Err(KiwiError::DecodeError(format!(
+            "Definition count {} exceeds the {} bytes left",
+            definition_count,
+            bb.remaining()
+        )));
+    }
 
     // Collect all definitions (temporarily)
     let mut definitions_temp: Vec<DefinitionTemp> =
         Vec::with_capacity(definition_count as usize);
 
     // Read each definition
-    for _ in 0..definition_count {
-        let definition_name = bb
-            .read_string()
-            .map_err(|e| {
-                KiwiError::DecodeError(format!("Failed to read definition name: {:?}", e))
-            })?
-            .into_owned();
+    for def_index in 0..definition_count {
+        let offset = bb.index();
+        let definition_name = read_name(&mut bb, "definition name")?;
+        note(offset, format!("definition[{}] name = {:?}", def_index, definition_name));
 
+        let offset = bb.index();
         let kind_byte = bb
             .read_byte()
             .map_err(|e| KiwiError::DecodeError(format!("Failed to read kind byte: {:?}", e)))?;
@@ -67,37 +349,70 @@ pub fn decode_binary_schema(buffer: &[u8]) -> Result<Schema, KiwiError> {
                 )))
             }
         };
+        note(offset, format!("definition[{}] kind = {} ({:?})", def_index, kind_byte, kind));
 
+        let offset = bb.index();
         let field_count = bb
             .read_var_uint()
             .map_err(|e| KiwiError::DecodeError(format!("Failed to read field count: {:?}", e)))?;
-
+        note(offset, format!("definition[{}] field count = {}", def_index, field_count));
+
+        if field_count as usize > bb.remaining() {
+            return Err(KiwiError::DecodeError(format!(
+                "Field count {} exceeds the {} bytes left",
+                field_count,
+                bb.remaining()
+            )));
+        }
         let mut fields_temp: Vec<FieldTemp> = Vec::with_capacity(field_count as usize);
-        for _ in 0..field_count {
-            let field_name = bb
-                .read_string()
-                .map_err(|e| {
-                    KiwiError::DecodeError(format!("Failed to read field name: {:?}", e))
-                })?
-                .into_owned();
+        for field_index in 0..field_count {
+            let offset = bb.index();
+            let field_name = read_name(&mut bb, "field name")?;
+            note(offset, format!("  field[{}] name = {:?}", field_index, field_name));
 
+            let offset = bb.index();
             let type_num = bb
                 .read_var_int()
                 .map_err(|e| KiwiError::DecodeError(format!("Failed to read type_num: {:?}", e)))?;
+            note(offset, format!("  field[{}] type = {}", field_index, type_num));
 
-            let is_array_byte = bb
-                .read_byte()
-                .map_err(|e| KiwiError::DecodeError(format!("Failed to read is_array byte: {:?}", e)))?;
-            let is_array = (is_array_byte & 1) != 0;
+            let offset = bb.index();
+            let mut array_depth = bb
+                .read_var_uint()
+                .map_err(|e| KiwiError::DecodeError(format!("Failed to read array_depth: {:?}", e)))?;
+            let key_type_num = if array_depth & MAP_FIELD_FLAG != 0 {
+                array_depth &= !MAP_FIELD_FLAG;
+                note(offset, format!("  field[{}] array_depth = {} (map)", field_index, array_depth));
+                let offset = bb.index();
+                let key_type_num = bb
+                    .read_var_int()
+                    .map_err(|e| KiwiError::DecodeError(format!("Failed to read key type_num: {:?}", e)))?;
+                note(offset, format!("  field[{}] key type = {}", field_index, key_type_num));
+                Some(key_type_num)
+            } else {
+                note(offset, format!("  field[{}] array_depth = {}", field_index, array_depth));
+                None
+            };
+            if array_depth >= MAP_FIELD_FLAG {
+                return Err(KiwiError::DecodeError(format!(
+                    "Field {:?} has an array depth of {}, more than {}",
+                    field_name,
+                    array_depth,
+                    MAP_FIELD_FLAG - 1
+                )));
+            }
 
+            let offset = bb.index();
             let reserved_index = bb
                 .read_var_uint()
                 .map_err(|e| KiwiError::DecodeError(format!("Failed to read reserved_index: {:?}", e)))?;
+            note(offset, format!("  field[{}] reserved_index = {}", field_index, reserved_index));
 
             fields_temp.push(FieldTemp {
                 name:           field_name,
                 type_num,
-                is_array,
+                array_depth,
+                key_type_num,
                 reserved_index,
             });
         }
@@ -109,48 +424,64 @@ pub fn decode_binary_schema(buffer: &[u8]) -> Result<Schema, KiwiError> {
         });
     }
 
+    // An optional package name trails the definitions. Older binaries end
+    // here, and readers that predate it stop before it.
+    let package = if bb.remaining() > 0 {
+        let offset = bb.index();
+        let name = read_name(&mut bb, "package name")?;
+        note(offset, format!("package = {:?}", name));
+        Some(name).filter(|name| !name.is_empty())
+    } else {
+        None
+    };
+
     // Build the final definitions with resolved type names
-    let native_types: Vec<&str> = NATIVE_TYPES.iter().cloned().collect();
+    let native_types: Vec<&str> = NATIVE_TYPES.to_vec();
     let mut definitions: Vec<crate::types::Definition> =
         Vec::with_capacity(definition_count as usize);
 
-    for def_temp in &definitions_temp {
+    for (def_index, def_temp) in definitions_temp.iter().enumerate() {
         let mut fields = Vec::with_capacity(def_temp.fields.len());
 
         for field_temp in &def_temp.fields {
-            // Resolve the type string (None for enums)
-            let type_resolved: Option<String> = if def_temp.kind == DefinitionKind::Enum {
-                None
-            } else {
-                if field_temp.type_num < 0 {
+            // Resolve the type strings (None for enums)
+            let resolve = |type_num: i32| -> Result<String, KiwiError> {
+                if type_num < 0 {
                     // Negative => native type
-                    let index = (!field_temp.type_num) as usize;
+                    let index = (!type_num) as usize;
                     if index >= native_types.len() {
                         return Err(KiwiError::DecodeError(format!(
                             "Invalid native type index {} for field {}",
-                            field_temp.type_num, field_temp.name
+                            type_num, field_temp.name
                         )));
                     }
-                    Some(native_types[index].to_string())
+                    Ok(native_types[index].to_string())
                 } else {
                     // Non‐negative => an index into definitions_temp
-                    let index = field_temp.type_num as usize;
+                    let index = type_num as usize;
                     if index >= definitions_temp.len() {
                         return Err(KiwiError::DecodeError(format!(
                             "Invalid definition index {} for field {}",
-                            field_temp.type_num, field_temp.name
+                            type_num, field_temp.name
                         )));
                     }
-                    Some(definitions_temp[index].name.clone())
+                    Ok(definitions_temp[index].name.clone())
                 }
             };
+            let (type_resolved, key_type) = if def_temp.kind == DefinitionKind::Enum {
+                (None, None)
+            } else {
+                (Some(resolve(field_temp.type_num)?), field_temp.key_type_num.map(resolve).transpose()?)
+            };
 
             fields.push(Field {
                 name:           field_temp.name.clone(),
                 line:           0,
                 column:         0,
+                span:           Span::default(),
                 type_:          type_resolved,
-                is_array:       field_temp.is_array,
+                array_depth:    field_temp.array_depth,
+                key_type,
                 is_deprecated:  false, // no deprecation in binary format
                 reserved_index: field_temp.reserved_index as i32,
             });
@@ -160,22 +491,39 @@ pub fn decode_binary_schema(buffer: &[u8]) -> Result<Schema, KiwiError> {
             name:    def_temp.name.clone(),
             line:    0,
             column:  0,
+            span:    Span::default(),
             kind:    def_temp.kind.clone(),
             fields,
+            reserved_ranges: Vec::new(),
+            binary_index: Some(def_index),
         });
     }
 
-    // Package is never encoded in the binary format
     Ok(Schema {
-        package:    None,
+        package,
+        imports:    Vec::new(),
         definitions,
+        comments:   Vec::new(),
     })
 }
 
+/// Reads the null-terminated `what` from a binary schema. Unlike data
+/// strings, names must be valid UTF-8: replacing bad bytes would only surface
+/// later as a confusing type resolution error.
+fn read_name(bb: &mut ByteBuffer, what: &str) -> Result<String, KiwiError> {
+    match bb.read_str() {
+        Ok(name) => Ok(name.to_string()),
+        // `read_str` leaves the index alone, so see whether the name was
+        // terminated at all.
+        Err(()) if bb.read_string().is_ok() => Err(KiwiError::DecodeError(format!("Invalid UTF-8 in {}", what))),
+        Err(()) => Err(KiwiError::DecodeError(format!("Failed to read {}: ()", what))),
+    }
+}
+
 /// Encode a `Schema` into bytes. Returns `Err(KiwiError::EncodeError)` if any field's type is invalid.
+/// A package name is appended after the definitions, where readers that
+/// don't know about it never look.
 pub fn encode_binary_schema(schema: &Schema) -> Result<Vec<u8>, KiwiError> {
-    use std::collections::HashMap;
-
     struct Writer {
         buffer: Vec<u8>,
     }
@@ -222,13 +570,7 @@ pub fn encode_binary_schema(schema: &Schema) -> Result<Vec<u8>, KiwiError> {
     let definition_count = schema.definitions.len();
     writer.write_var_uint(definition_count as u32);
 
-    // Build a map: name -> index
-    let mut definition_index_map = HashMap::new();
-    for (i, def) in schema.definitions.iter().enumerate() {
-        definition_index_map.insert(def.name.clone(), i);
-    }
-
-    let native_types: Vec<&str> = NATIVE_TYPES.iter().cloned().collect();
+    let definition_index_map = definition_indices(schema);
 
     for def in &schema.definitions {
         // Write name
@@ -250,35 +592,111 @@ pub fn encode_binary_schema(schema: &Schema) -> Result<Vec<u8>, KiwiError> {
             // Field name
             writer.write_string(&field.name);
 
-            // Determine type_num
-            let type_num: i32 = if def.kind == DefinitionKind::Enum {
-                0
-            } else if let Some(ref type_str) = field.type_ {
-                if let Some(native_idx) = native_types.iter().position(|&t| t == type_str.as_str())
-                {
-                    !(native_idx as i32) // negative for native type
-                } else if let Some(&def_idx) = definition_index_map.get(type_str) {
-                    def_idx as i32 // positive for user defs
-                } else {
-                    return Err(KiwiError::EncodeError(format!(
-                        "Type '{}' not found in native types or definitions",
-                        type_str
-                    )));
-                }
-            } else {
-                0
-            };
+            writer.write_var_int(field_type_id(&def.kind, field, &definition_index_map)?);
 
-            writer.write_var_int(type_num);
-
-            // is_array
-            let is_array_byte = if field.is_array { 1 } else { 0 };
-            writer.write_byte(is_array_byte);
+            // array_depth, a var_uint so that 0 and 1 match the old is_array
+            // byte. Map fields flag it and follow it with the key type.
+            match field_key_type_id(field, &definition_index_map)? {
+                Some(key_type_id) => {
+                    writer.write_var_uint(field.array_depth | MAP_FIELD_FLAG);
+                    writer.write_var_int(key_type_id);
+                }
+                None => writer.write_var_uint(field.array_depth),
+            }
 
             // reserved_index
             writer.write_var_uint(field.reserved_index as u32);
         }
     }
 
+    if let Some(ref package) = schema.package {
+        writer.write_string(package);
+    }
+
     Ok(writer.buffer)
 }
+
+/// Maps each definition name to its position, which is its type id.
+fn definition_indices(schema: &Schema) -> HashMap<String, usize> {
+    schema
+        .definitions
+        .iter()
+        .enumerate()
+        .map(|(i, def)| (def.name.clone(), i))
+        .collect()
+}
+
+/// Resolves the type of `field` to a type id: negative for native types,
+/// the definition's position otherwise, and 0 for enum values.
+fn field_type_id(
+    kind: &DefinitionKind,
+    field: &Field,
+    definition_index_map: &HashMap<String, usize>,
+) -> Result<i32, KiwiError> {
+    match field.type_ {
+        Some(ref type_str) if *kind != DefinitionKind::Enum => type_name_id(type_str, definition_index_map),
+        _ => Ok(0),
+    }
+}
+
+/// Resolves the key type of a map `field` to a type id, or `None` for any
+/// other field.
+fn field_key_type_id(
+    field: &Field,
+    definition_index_map: &HashMap<String, usize>,
+) -> Result<Option<i32>, KiwiError> {
+    field
+        .key_type
+        .as_deref()
+        .map(|key_type| type_name_id(key_type, definition_index_map))
+        .transpose()
+}
+
+fn type_name_id(type_str: &str, definition_index_map: &HashMap<String, usize>) -> Result<i32, KiwiError> {
+    if let Some(native_idx) = NATIVE_TYPES.iter().position(|&t| t == type_str) {
+        Ok(!(native_idx as i32)) // negative for native type
+    } else if let Some(&def_idx) = definition_index_map.get(type_str) {
+        Ok(def_idx as i32) // positive for user defs
+    } else {
+        Err(KiwiError::EncodeError(format!(
+            "Type '{}' not found in native types or definitions",
+            type_str
+        )))
+    }
+}
+
+/// Builds the runtime schema `Value` decodes and encodes with, the same one
+/// `brine_kiwi_schema::Schema::decode` would read back from
+/// `encode_binary_schema`. The package is dropped, as the runtime schema has
+/// no place for it.
+///
+/// Fails with the `EncodeError` `encode_binary_schema` would give if a
+/// field's type is not defined, which `verify_schema` (and so
+/// `compile_schema`) rules out.
+impl TryFrom<&Schema> for brine_kiwi_schema::Schema {
+    type Error = KiwiError;
+
+    fn try_from(schema: &Schema) -> Result<Self, KiwiError> {
+        let definition_index_map = definition_indices(schema);
+        let mut defs = Vec::with_capacity(schema.definitions.len());
+        for def in &schema.definitions {
+            let kind = match def.kind {
+                DefinitionKind::Enum    => brine_kiwi_schema::DefKind::Enum,
+                DefinitionKind::Struct  => brine_kiwi_schema::DefKind::Struct,
+                DefinitionKind::Message => brine_kiwi_schema::DefKind::Message,
+            };
+            let mut fields = Vec::with_capacity(def.fields.len());
+            for field in &def.fields {
+                fields.push(brine_kiwi_schema::Field {
+                    name:        field.name.clone(),
+                    type_id:     field_type_id(&def.kind, field, &definition_index_map)?,
+                    array_depth: field.array_depth,
+                    key_type_id: field_key_type_id(field, &definition_index_map)?,
+                    value:       field.reserved_index as u32,
+                });
+            }
+            defs.push(brine_kiwi_schema::Def::new(def.name.clone(), kind, fields));
+        }
+        Ok(brine_kiwi_schema::Schema::new(defs))
+    }
+}
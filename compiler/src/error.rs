@@ -26,4 +26,17 @@ pub enum KiwiError {
 
     #[error("Verifier error: {0}")]
     VerifierError(String),
+
+    /// An error in a file reached through an `import`, tagged with its path.
+    #[error("In {}: {source}", path.display())]
+    Import {
+        path:   std::path::PathBuf,
+        source: Box<KiwiError>,
+    },
+}
+
+impl From<brine_kiwi_schema::EncodeError> for KiwiError {
+    fn from(err: brine_kiwi_schema::EncodeError) -> Self {
+        KiwiError::EncodeError(err.to_string())
+    }
 }
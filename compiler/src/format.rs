@@ -0,0 +1,92 @@
+use std::fmt;
+
+use crate::types::{Definition, DefinitionKind, Field, Schema};
+
+/// Renders `schema` back to canonical `.kiwi` text: the package and imports
+/// first, one definition per block separated by blank lines, two-space
+/// indentation and one field per line with the `=` signs of a block lined
+/// up. Definition and field order, deprecation tags and reserved ids are
+/// kept; reserved ids are merged into `a to b` ranges. Comments and number
+/// formatting (hex, implicit enum values) are not.
+pub fn schema_to_kiwi_text(schema: &Schema) -> String {
+    let mut blocks = Vec::new();
+
+    if let Some(ref package) = schema.package {
+        blocks.push(format!("package {};\n", package));
+    }
+    if !schema.imports.is_empty() {
+        blocks.push(schema.imports.iter().map(|path| format!("import \"{}\";\n", path)).collect());
+    }
+    for def in &schema.definitions {
+        blocks.push(format_definition(def));
+    }
+
+    blocks.join("\n")
+}
+
+/// Prints the schema as [`schema_to_kiwi_text`] does, so a schema decoded
+/// from binary can be shown as `.kiwi` source with `{}`.
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&schema_to_kiwi_text(self))
+    }
+}
+
+fn format_definition(def: &Definition) -> String {
+    let keyword = match def.kind {
+        DefinitionKind::Enum    => "enum",
+        DefinitionKind::Struct  => "struct",
+        DefinitionKind::Message => "message",
+    };
+    let mut out = format!("{} {} {{\n", keyword, def.name);
+
+    if !def.reserved_ranges.is_empty() {
+        out.push_str(&format!("  reserved {};\n", format_reserved(&def.reserved_ranges)));
+    }
+
+    let declarations: Vec<String> = def.fields.iter().map(|f| declaration(&def.kind, f)).collect();
+    let width = declarations.iter().map(|d| d.chars().count()).max().unwrap_or(0);
+    for (field, decl) in def.fields.iter().zip(&declarations) {
+        let line = match def.kind {
+            DefinitionKind::Struct => decl.clone(),
+            _ => format!("{:<width$} = {}", decl, field.reserved_index, width = width),
+        };
+        let deprecated = if field.is_deprecated { " [deprecated]" } else { "" };
+        out.push_str(&format!("  {}{};\n", line, deprecated));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// The part of a field line before any `= value`: `type name`, or just the
+/// variant name for enums.
+fn declaration(kind: &DefinitionKind, field: &Field) -> String {
+    match kind {
+        DefinitionKind::Enum => field.name.clone(),
+        _ => format!("{} {}", field.type_text(), field.name),
+    }
+}
+
+/// Sorts `ids` and merges consecutive runs, e.g. `2, 5 to 7`.
+fn format_reserved(reserved: &[(i32, i32)]) -> String {
+    let mut sorted = reserved.to_vec();
+    sorted.sort_unstable();
+
+    // Merge ranges that overlap or touch.
+    let mut ranges: Vec<(i32, i32)> = Vec::new();
+    for (start, end) in sorted {
+        match ranges.last_mut() {
+            Some((_, last)) if start as i64 <= *last as i64 + 1 => *last = (*last).max(end),
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end { start.to_string() } else { format!("{} to {}", start, end) }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
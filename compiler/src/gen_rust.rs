@@ -1,6 +1,7 @@
-use crate::types::{Definition, DefinitionKind, Schema};
+use crate::compiler::encode_binary_schema;
+use crate::types::{Definition, DefinitionKind, Field, Schema};
 use crate::verifier::NATIVE_TYPES;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Converts a string to PascalCase.
 fn to_pascal_case(s: &str) -> String {
@@ -33,9 +34,17 @@ fn to_pascal_case(s: &str) -> String {
 }
 
 /// Converts a string to snake_case.
+///
+/// An uppercase letter starts a new word unless it continues an acronym run,
+/// and the last capital of a run starts the next word (`HTTPStatus` →
+/// `http_status`). A leading digit gets a `_` prefix so the result is a valid
+/// identifier (`2dPos` → `_2d_pos`).
 fn to_snake_case(s: &str) -> String {
     let chars: Vec<char> = s.chars().collect();
     let mut snake = String::new();
+    if chars.first().is_some_and(|c| c.is_ascii_digit()) {
+        snake.push('_');
+    }
     for i in 0..chars.len() {
         let c = chars[i];
         if c.is_uppercase() {
@@ -53,69 +62,472 @@ fn to_snake_case(s: &str) -> String {
     snake
 }
 
-/// Maps schema types to Rust types.
-fn map_type(type_name: &str, is_message: bool, is_array: bool) -> String {
+/// Maps schema types to Rust types, nested in `array_depth` `Vec`s. `is_boxed`
+/// wraps a non-array type in `Box<…>` to break recursive cycles. With
+/// `cow_strings`, strings become `Cow<'a, str>` and definitions in `lifetimed`
+/// carry the `'a` parameter.
+fn map_type(
+    type_name: &str,
+    is_message: bool,
+    array_depth: u32,
+    is_boxed: bool,
+    cow_strings: bool,
+    lifetimed: &HashSet<String>,
+) -> String {
     let rust_type = match type_name {
         "bool"   => "bool".to_string(),
         "byte"   => "u8".to_string(),
         "int"    => "i32".to_string(),
         "uint"   => "u32".to_string(),
         "float"  => "f32".to_string(),
+        "string" if cow_strings => "Cow<'a, str>".to_string(),
         "string" => "String".to_string(),
         "int64"  => "i64".to_string(),
         "uint64" => "u64".to_string(),
-        other    => to_pascal_case(other),
+        "float64" => "f64".to_string(),
+        other    => type_with_lifetime(other, lifetimed),
+    };
+    let mut rust_type = if is_boxed && array_depth == 0 {
+        format!("Box<{}>", rust_type)
+    } else {
+        rust_type
     };
+    for _ in 0..array_depth {
+        rust_type = format!("Vec<{}>", rust_type);
+    }
 
-    if is_array {
-        if is_message {
-            format!("Option<Vec<{}>>", rust_type)
+    if is_message {
+        format!("Option<{}>", rust_type)
+    } else {
+        rust_type
+    }
+}
+
+/// Returns true if `field` is a `byte[]` that should be generated as
+/// `bytes::Bytes`. Deeper byte arrays (`byte[][]`) stay `Vec`s of `Vec<u8>`.
+fn is_bytes_field(field: &Field, options: &GenOptions) -> bool {
+    options.use_bytes_crate && field.array_depth == 1 && field.type_.as_deref() == Some("byte")
+}
+
+/// Wraps `elem`, an expression converting the `&Value` named `item`, so that
+/// it converts an `item` holding `levels` more arrays around it, failing on
+/// the first element that does not convert.
+fn nested_item_conversion(elem: &str, levels: u32) -> String {
+    if levels == 0 {
+        return elem.to_string();
+    }
+    format!(
+        "item.as_array().iter().map(|item| Ok({})).collect::<Result<Vec<_>, KiwiError>>()?",
+        nested_item_conversion(elem, levels - 1)
+    )
+}
+
+/// Returns the expression converting `source`, a `Value` holding the `byte[]`
+/// field `field_name` nested in `array_depth - 1` more arrays, to `Vec<u8>`s.
+/// The innermost arrays are usually `Value::Bytes`, but values built by hand
+/// may still hold them as a `Value::Array` of `Value::Byte`s, so those are
+/// accepted too.
+fn bytes_from_value(source: &str, field_name: &str, array_depth: u32) -> String {
+    let bytes = |expr: &str| {
+        format!(
+            "match {0}.as_bytes_opt() {{ Some(bytes) => bytes.to_vec(), None => {0}.as_array().iter().map(|byte| byte.as_byte_opt().ok_or_else(|| KiwiError::MissingField(\"{1}\".into()))).collect::<Result<Vec<u8>, KiwiError>>()? }}",
+            expr, field_name
+        )
+    };
+    if array_depth <= 1 {
+        return bytes(source);
+    }
+    format!(
+        "{}.as_array().iter().map(|item| Ok({})).collect::<Result<Vec<_>, KiwiError>>()?",
+        source,
+        nested_item_conversion(&bytes("item"), array_depth - 2)
+    )
+}
+
+/// Returns the `Value::Array` built from `expr`, a reference to a `Vec` nested
+/// `array_depth` deep around values of `type_name`. A `byte[]` is a
+/// `Value::Bytes`.
+fn array_value(type_name: &str, expr: &str, array_depth: u32, cow_strings: bool) -> String {
+    if array_depth == 1 && type_name == "byte" {
+        return format!("Value::Bytes({}.to_vec())", expr);
+    }
+    let item = if array_depth > 1 {
+        array_value(type_name, "item", array_depth - 1, cow_strings)
+    } else {
+        value_constructor(type_name, "item", true, cow_strings)
+    };
+    format!("Value::Array({}.iter().map(|item| {}).collect())", expr, item)
+}
+
+/// Returns the Rust type of a `map<K, V>` field: a `HashMap`, or without
+/// `std` (which `HashMap` needs) a `Vec` of pairs in wire order.
+fn map_field_type(field: &Field, options: &GenOptions, lifetimed: &HashSet<String>) -> String {
+    let key = map_type(field.key_type.as_deref().unwrap_or(""), false, 0, false, options.zero_copy_strings, lifetimed);
+    let value = map_type(field.type_.as_deref().unwrap_or(""), false, 0, false, options.zero_copy_strings, lifetimed);
+    if options.no_std {
+        format!("Vec<({}, {})>", key, value)
+    } else {
+        format!("std::collections::HashMap<{}, {}>", key, value)
+    }
+}
+
+/// Returns the expression converting the `Value::Map` in `source` to the
+/// type [`map_field_type`] gives `field`. `from_kiwi` names the function
+/// nested types are converted with.
+fn map_from_value(
+    field: &Field,
+    source: &str,
+    strings: StringMode,
+    no_std: bool,
+    from_kiwi: &dyn Fn(&str) -> &'static str,
+) -> String {
+    let convert = |type_name: &str, expr: &str| {
+        if NATIVE_TYPES.contains(&type_name) {
+            format!("{}.{}", expr, conversion_method(type_name, &field.name, strings))
         } else {
-            format!("Vec<{}>", rust_type)
+            format!("{}::{}({})?", to_pascal_case(type_name), from_kiwi(type_name), expr)
         }
+    };
+    let key = convert(field.key_type.as_deref().unwrap_or(""), "key");
+    let value = convert(field.type_.as_deref().unwrap_or(""), "item");
+    let (new_map, insert) = if no_std {
+        ("Vec::new()", format!("tmp.push(({}, {}))", key, value))
     } else {
-        if is_message {
-            format!("Option<{}>", rust_type)
-        } else {
-            rust_type
+        ("std::collections::HashMap::new()", format!("tmp.insert({}, {})", key, value))
+    };
+    format!(
+        "{{ let mut tmp = {}; for (key, item) in {}.as_map() {{ {}; }} tmp }}",
+        new_map, source, insert
+    )
+}
+
+/// Returns the PascalCase name of the definition `name`, followed by `<'a>`
+/// if it is in `lifetimed`.
+fn type_with_lifetime(name: &str, lifetimed: &HashSet<String>) -> String {
+    if lifetimed.contains(name) {
+        format!("{}<'a>", to_pascal_case(name))
+    } else {
+        to_pascal_case(name)
+    }
+}
+
+/// Returns the `impl` header for `trait_name` (or an inherent impl when it is
+/// empty) on the definition `name`, declaring `'a` when the type needs it.
+fn impl_header(trait_name: &str, name: &str, lifetimed: &HashSet<String>) -> String {
+    let generics = if lifetimed.contains(name) { "<'a>" } else { "" };
+    let target = type_with_lifetime(name, lifetimed);
+    if trait_name.is_empty() {
+        format!("impl{} {} {{", generics, target)
+    } else {
+        format!("impl{} {} for {} {{", generics, trait_name, target)
+    }
+}
+
+/// How string fields are produced from a `Value`.
+#[derive(Clone, Copy, PartialEq)]
+enum StringMode {
+    /// `String` fields, copied out of the `Value`.
+    Owned,
+    /// `Cow<'a, str>` fields holding a copy (`Cow::Owned`).
+    OwnedCow,
+    /// `Cow<'a, str>` fields borrowing from the `Value` (`Cow::Borrowed`).
+    BorrowedCow,
+}
+
+/// Returns the conversion applied to a `Value` holding the field `field_name`
+/// of native type `type_name`. A value of the wrong kind is reported as a
+/// `MissingField` error rather than silently read as a default.
+fn conversion_method(type_name: &str, field_name: &str, strings: StringMode) -> String {
+    let string_suffix = match strings {
+        StringMode::Owned       => ".to_string()",
+        StringMode::OwnedCow    => ".to_string().into()",
+        StringMode::BorrowedCow => ".into()",
+    };
+    let (method, suffix) = match type_name {
+        "bool"   => ("as_bool_opt()", ""),
+        "byte"   => ("as_byte_opt()", ""),
+        "int"    => ("as_int_opt()", ""),
+        "uint"   => ("as_uint_opt()", ""),
+        "float"  => ("as_float_opt()", ""),
+        "string" => ("as_string_opt()", string_suffix),
+        "int64"  => ("as_int64_opt()", ""),
+        "uint64" => ("as_uint64_opt()", ""),
+        "float64" => ("as_float64_opt()", ""),
+        _        => ("as_string_opt()", ""),
+    };
+    format!(
+        "{}.ok_or_else(|| KiwiError::MissingField(\"{}\".into()))?{}",
+        method, field_name, suffix
+    )
+}
+
+/// Returns true if `from` can reach the definition `target` through
+/// non-array fields, i.e. if embedding `from` inside `target` by value would
+/// make `target` infinitely sized.
+fn reaches_by_value(
+    from: &str,
+    target: &str,
+    definitions_map: &HashMap<String, Definition>,
+    visited: &mut HashSet<String>,
+) -> bool {
+    if from == target {
+        return true;
+    }
+    if !visited.insert(from.to_string()) {
+        return false;
+    }
+    let definition = match definitions_map.get(from) {
+        Some(def) if def.kind != DefinitionKind::Enum => def,
+        _ => return false,
+    };
+    definition.fields.iter().any(|field| {
+        !field.is_array()
+            && !field.is_map()
+            && field
+                .type_
+                .as_deref()
+                .is_some_and(|ty| reaches_by_value(ty, target, definitions_map, visited))
+    })
+}
+
+/// Collects every `(definition, field)` pair whose (non-array) field type
+/// leads back to its own definition. Those fields are generated as
+/// `Box<T>` / `Option<Box<T>>` so recursive messages still compile.
+fn find_recursive_fields(definitions_map: &HashMap<String, Definition>) -> HashSet<(String, String)> {
+    let mut recursive = HashSet::new();
+    for definition in definitions_map.values() {
+        if definition.kind == DefinitionKind::Enum {
+            continue;
+        }
+        for field in &definition.fields {
+            if field.is_array() || field.is_map() {
+                continue;
+            }
+            if let Some(ref ty) = field.type_ {
+                if reaches_by_value(ty, &definition.name, definitions_map, &mut HashSet::new()) {
+                    recursive.insert((definition.name.clone(), field.name.clone()));
+                }
+            }
+        }
+    }
+    recursive
+}
+
+/// Collects the structs and messages that hold a string, directly or through
+/// a nested struct or message. With `zero_copy_strings` those types borrow
+/// their strings and so take a lifetime parameter.
+fn find_lifetimed_definitions(schema: &Schema) -> HashSet<String> {
+    let mut lifetimed = HashSet::new();
+    loop {
+        let mut changed = false;
+        for definition in &schema.definitions {
+            if definition.kind == DefinitionKind::Enum || lifetimed.contains(&definition.name) {
+                continue;
+            }
+            let needs_lifetime = definition.fields.iter().any(|field| {
+                field
+                    .type_
+                    .iter()
+                    .chain(&field.key_type)
+                    .any(|ty| ty == "string" || lifetimed.contains(ty))
+            });
+            if needs_lifetime {
+                lifetimed.insert(definition.name.clone());
+                changed = true;
+            }
+        }
+        if !changed {
+            return lifetimed;
+        }
+    }
+}
+
+/// Collects the structs and messages that hold a `float`, `float64` or map,
+/// directly or through a nested struct or message. Floats aren't `Eq` or
+/// `Hash` and maps aren't `Hash`, so only the types outside this set derive
+/// them.
+fn find_unhashable_definitions(schema: &Schema) -> HashSet<String> {
+    let mut unhashable = HashSet::new();
+    loop {
+        let mut changed = false;
+        for definition in &schema.definitions {
+            if definition.kind == DefinitionKind::Enum || unhashable.contains(&definition.name) {
+                continue;
+            }
+            let blocks_hash = definition.fields.iter().any(|field| {
+                field.is_map()
+                    || field
+                        .type_
+                        .as_deref()
+                        .is_some_and(|ty| ty == "float" || ty == "float64" || unhashable.contains(ty))
+            });
+            if blocks_hash {
+                unhashable.insert(definition.name.clone());
+                changed = true;
+            }
+        }
+        if !changed {
+            return unhashable;
         }
     }
 }
 
-/// Returns the correct `as_...()` call on a `Value`.
-fn conversion_method(type_name: &str) -> String {
+/// Returns the `Value` expression for `expr`, which is either a field place
+/// (`self.x`) or, when `is_ref` is set, a reference to one (`item`).
+/// `cow_strings` copies a `Cow<'a, str>` rather than cloning a `String`.
+fn value_constructor(type_name: &str, expr: &str, is_ref: bool, cow_strings: bool) -> String {
+    let copied = if is_ref { format!("*{}", expr) } else { expr.to_string() };
     match type_name {
-        "bool"   => "as_bool()".to_string(),
-        "byte"   => "as_byte()".to_string(),
-        "int"    => "as_int()".to_string(),
-        "uint"   => "as_uint()".to_string(),
-        "float"  => "as_float()".to_string(),
-        "string" => "as_string().to_string()".to_string(),
-        "int64"  => "as_int64()".to_string(),
-        "uint64" => "as_uint64()".to_string(),
-        _        => "as_string()".to_string(),
+        "bool"   => format!("Value::Bool({})", copied),
+        "byte"   => format!("Value::Byte({})", copied),
+        "int"    => format!("Value::Int({})", copied),
+        "uint"   => format!("Value::UInt({})", copied),
+        "float"  => format!("Value::Float({})", copied),
+        "string" if cow_strings => format!("Value::String({}.to_string())", expr),
+        "string" => format!("Value::String({}.clone())", expr),
+        "int64"  => format!("Value::Int64({})", copied),
+        "uint64" => format!("Value::UInt64({})", copied),
+        "float64" => format!("Value::Float64({})", copied),
+        _        => format!("{}.to_kiwi()", expr),
     }
 }
 
-/// Escape Rust keywords by appending an underscore.
+/// Escape Rust keywords as raw identifiers (`type` → `r#type`). The few
+/// keywords that cannot be raw identifiers get an underscore suffix instead.
 fn escape_rust_keyword(s: &str) -> String {
     let keywords = [
-        "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn",
-        "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
-        "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
-        "use", "where", "while",
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+        "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if",
+        "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+        "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
     ];
-    if keywords.contains(&s) {
+    let not_raw = ["crate", "self", "super", "Self"];
+    if not_raw.contains(&s) {
         format!("{}_", s)
+    } else if keywords.contains(&s) {
+        format!("r#{}", s)
     } else {
         s.to_string()
     }
 }
 
+/// Options controlling the shape of the generated Rust code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenOptions {
+    /// Derive `serde::Serialize` on every generated type (and mark structs with
+    /// `#[skip_serializing_none]`). Turn this off to drop the `serde` and
+    /// `serde_with` dependencies from the generated module.
+    pub derive_serde: bool,
+
+    /// Append a `#[cfg(test)] mod generated_tests` that round-trips a default
+    /// instance of every generated type through `to_kiwi` → encode → decode →
+    /// `from_kiwi` using the embedded `schema()`.
+    pub emit_tests: bool,
+
+    /// No longer has any effect: generated enums always derive `Eq` and
+    /// `Hash`, since they carry no floats.
+    #[deprecated(note = "generated enums always derive `Eq` and `Hash`")]
+    pub hashable_enums: bool,
+
+    /// Mark bare `Vec<T>` fields (struct arrays) with
+    /// `#[serde(skip_serializing_if = "Vec::is_empty")]` so empty arrays are
+    /// left out of serialized output. Only applies with `derive_serde`.
+    pub skip_empty_vecs: bool,
+
+    /// Generate `set_field(&mut self, name, value)` on messages, assigning a
+    /// field by its schema name from a dynamic `Value`.
+    pub dynamic_setters: bool,
+
+    /// Append a `pub mod prelude` re-exporting every generated type, so
+    /// consumers can bring them all into scope with one `use`.
+    pub emit_prelude: bool,
+
+    /// Generate `byte[]` fields as `bytes::Bytes` instead of `Vec<u8>`, for
+    /// handing blobs to async networking code without copying. The generated
+    /// module then needs the `bytes` crate (with its `serde` feature when
+    /// `derive_serde` is on).
+    pub use_bytes_crate: bool,
+
+    /// Generate string fields as `Cow<'a, str>`, giving every struct and
+    /// message that (transitively) holds a string a lifetime parameter, and
+    /// add `from_kiwi_ref(&'a Value)`, which borrows those strings from the
+    /// decoded `Value` instead of copying them. `FromKiwi` still returns
+    /// owned strings.
+    pub zero_copy_strings: bool,
+
+    /// Emit code that only needs `core` and `alloc`: `String`, `Vec`, `Box`
+    /// and `Cow` are imported from `alloc`, and no `std::` paths are used.
+    /// The module declares `extern crate alloc;` itself. Map fields become
+    /// `Vec<(K, V)>` in wire order, since `HashMap` needs `std`.
+    pub no_std: bool,
+
+    /// Give every enum an `Unknown(String)` variant that `from_kiwi` maps
+    /// unrecognized variant names to instead of failing, for consumers that
+    /// must accept values added by newer schemas. `Unknown` values have no
+    /// wire value, so `wire_value` returns an `Option`, `encode` (on enums
+    /// and on every struct and message) returns a `Result`, and `to_kiwi`
+    /// turns `Unknown` into an empty variant name that `try_encode` rejects.
+    pub tolerant_enums: bool,
+
+    /// Mark every generated enum `#[non_exhaustive]`, so crates exposing them
+    /// can add variants later without a breaking change. Code outside the
+    /// generated crate then needs a wildcard arm to match on them.
+    pub non_exhaustive_enums: bool,
+
+    /// Extra traits appended to every generated `#[derive(...)]` line, such
+    /// as `PartialOrd`. Traits a type already derives are not repeated.
+    pub extra_derives: Vec<String>,
+
+    /// Path the generated module imports the runtime (`Value`, `KiwiError`,
+    /// `FromKiwi`, …) from with a glob `use`. Defaults to `brine_kiwi`; point
+    /// it elsewhere when the SDK is re-exported, e.g. `crate::kiwi`.
+    pub module_path: String,
+}
+
+impl Default for GenOptions {
+    #[allow(deprecated)]
+    fn default() -> Self {
+        GenOptions {
+            derive_serde:         true,
+            emit_tests:           false,
+            hashable_enums:       true,
+            skip_empty_vecs:      false,
+            dynamic_setters:      false,
+            emit_prelude:         false,
+            use_bytes_crate:      false,
+            zero_copy_strings:    false,
+            no_std:               false,
+            tolerant_enums:       false,
+            non_exhaustive_enums: false,
+            extra_derives:        Vec::new(),
+            module_path:          "brine_kiwi".to_string(),
+        }
+    }
+}
+
+/// Append `options.extra_derives` to `derives`, skipping any already present.
+fn push_extra_derives<'a>(derives: &mut Vec<&'a str>, options: &'a GenOptions) {
+    for extra in &options.extra_derives {
+        if !derives.contains(&extra.as_str()) {
+            derives.push(extra);
+        }
+    }
+}
+
 /// Entry point: given a `Schema`, return a `String` containing the entire Rust module.
 /// 
-/// Each generated `from_kiwi(…)` now returns `Result<_, KiwiError>`.
+/// Each generated `from_kiwi(…)` returns `Result<_, KiwiError>`. Enum
+/// `from_kiwi` and `try_from_str` match variant names exactly as the schema
+/// spells them, case included. The embedded `schema()` and the `emit_tests`
+/// module are only generated for a schema `encode_binary_schema` accepts,
+/// which any verified one is.
 pub fn compile_schema_to_rust(schema: &Schema) -> String {
+    compile_schema_to_rust_with(schema, &GenOptions::default())
+}
+
+/// Same as [`compile_schema_to_rust`], but with explicit [`GenOptions`].
+pub fn compile_schema_to_rust_with(schema: &Schema, options: &GenOptions) -> String {
     let mut definitions_map: HashMap<String, Definition> = HashMap::new();
     let package = schema.package.clone();
     let mut rust_code: Vec<String> = Vec::new();
@@ -125,16 +537,31 @@ pub fn compile_schema_to_rust(schema: &Schema) -> String {
     rust_code.push("".to_string());
 
     // If there's a package, wrap everything in a `pub mod PascalCaseName { … }`.
-    if let Some(ref name) = package {
-        rust_code.push(format!("pub mod {} {{", to_pascal_case(name)));
+    // A dotted package (`game.net`) becomes one nested module per segment.
+    let package_segments: Vec<&str> = package.as_deref().map_or(Vec::new(), |name| name.split('.').collect());
+    for segment in &package_segments {
+        rust_code.push(format!("pub mod {} {{", to_pascal_case(segment)));
     }
 
     // Import `Value`, 'KiwiError',  and `FromKiwi`.
-    rust_code.push("use brine_kiwi::*;".to_string());
+    rust_code.push(format!("use {}::*;", options.module_path));
+
+    // Without `std`, the types its prelude provides come from `alloc`.
+    if options.no_std {
+        rust_code.push("extern crate alloc;".to_string());
+        rust_code.push("#[allow(unused_imports)]".to_string());
+        rust_code.push("use alloc::{boxed::Box, string::{String, ToString}, vec::Vec};".to_string());
+    }
 
     // Serde imports
-    rust_code.push("use serde::Serialize;".to_string());
-    rust_code.push("use serde_with::skip_serializing_none;".to_string());
+    if options.derive_serde {
+        rust_code.push("use serde::Serialize;".to_string());
+        rust_code.push("use serde_with::skip_serializing_none;".to_string());
+    }
+    if options.zero_copy_strings {
+        let krate = if options.no_std { "alloc" } else { "std" };
+        rust_code.push(format!("use {}::borrow::Cow;", krate));
+    }
     rust_code.push("".to_string());
 
     // Build a lookup map from name → Definition
@@ -142,56 +569,298 @@ pub fn compile_schema_to_rust(schema: &Schema) -> String {
         definitions_map.insert(def.name.clone(), def.clone());
     }
 
+    // Embed the binary schema so generated types can be encoded/decoded without
+    // shipping the `.kiwi.bin` file separately. Unverified schemas may fail to
+    // encode, in which case `schema()` is simply not generated.
+    let has_schema = match encode_binary_schema(schema) {
+        Ok(bytes) => {
+            rust_code.push(generate_schema_fn(&bytes));
+            true
+        }
+        Err(_) => false,
+    };
+
+    let boxed = find_recursive_fields(&definitions_map);
+    let unhashable = find_unhashable_definitions(schema);
+    let lifetimed = if options.zero_copy_strings {
+        find_lifetimed_definitions(schema)
+    } else {
+        HashSet::new()
+    };
+
     // Now generate code for each definition
-    for definition in &schema.definitions {
+    // A definition's type id is its position in the schema.
+    for (type_id, definition) in schema.definitions.iter().enumerate() {
         match definition.kind {
             DefinitionKind::Enum => {
-                rust_code.push(generate_enum(definition));
+                rust_code.push(generate_enum(definition, options));
             }
             DefinitionKind::Struct => {
-                rust_code.push(generate_struct(definition, false));
+                rust_code.push(generate_struct(definition, type_id, false, options, &boxed, &lifetimed, &unhashable));
             }
             DefinitionKind::Message => {
-                rust_code.push(generate_struct(definition, true));
+                rust_code.push(generate_struct(definition, type_id, true, options, &boxed, &lifetimed, &unhashable));
+                let getters = generate_message_getters(definition, options, &definitions_map, &boxed, &lifetimed);
+                if !getters.is_empty() {
+                    rust_code.push(getters);
+                }
             }
         }
     }
 
-    // Close package block if needed
-    if package.is_some() {
+    if options.emit_tests && has_schema {
+        rust_code.push(generate_tests(schema, options));
+    }
+
+    if options.emit_prelude {
+        rust_code.push(generate_prelude(schema));
+    }
+
+    // Close package blocks if needed
+    for _ in &package_segments {
         rust_code.push("}".to_string());
     }
 
     rust_code.join("\n")
 }
 
+/// Generates `pub mod prelude`, re-exporting each generated type by name.
+fn generate_prelude(schema: &Schema) -> String {
+    let names: Vec<String> = schema
+        .definitions
+        .iter()
+        .map(|def| to_pascal_case(&def.name))
+        .collect();
+    format!("\npub mod prelude {{\n    pub use super::{{{}}};\n}}\n", names.join(", "))
+}
+
 /// Generates a Rust enum + `FromKiwi` impl that returns `Result<…, KiwiError>`.
-fn generate_enum(definition: &Definition) -> String {
+fn generate_enum(definition: &Definition, options: &GenOptions) -> String {
     let enum_name = to_pascal_case(&definition.name);
     let mut variants = Vec::new();
-    for field in &definition.fields {
+    for (i, field) in definition.fields.iter().enumerate() {
         let var_name = escape_rust_keyword(&to_pascal_case(&field.name));
+        let mut variant = String::new();
         if field.is_deprecated {
-            variants.push(format!("    #[deprecated]\n    {},", var_name));
-        } else {
-            variants.push(format!("    {},", var_name));
+            variant.push_str("    #[deprecated]\n");
+        }
+        // The first variant doubles as the `Default`, so structs holding an
+        // enum can still derive `Default`.
+        if i == 0 {
+            variant.push_str("    #[default]\n");
         }
+        // The discriminant is the schema value, so `variant as i32` matches it.
+        variant.push_str(&format!("    {} = {},", var_name, field.reserved_index));
+        variants.push(variant);
+    }
+    if options.tolerant_enums {
+        // `Unknown` needs a discriminant of its own: an implicit one could
+        // collide with a later explicit value or overflow after `i32::MAX`.
+        let discriminant = if definition.fields.is_empty() {
+            String::new()
+        } else {
+            let used: HashSet<i32> = definition.fields.iter().map(|f| f.reserved_index).collect();
+            let free = (0..=i32::MAX).find(|v| !used.contains(v)).expect("an enum has fewer than 2^31 variants");
+            format!(" = {}", free)
+        };
+        variants.push(format!(
+            "    /// A variant this schema doesn't know. It has no wire value, so it can't be encoded.\n    Unknown(String){},",
+            discriminant
+        ));
     }
 
-    let derived = "#[derive(Debug, Clone, PartialEq, Serialize)]";
+    // Enums carry no data, so `Eq`/`Hash` are always sound and let them be
+    // used as `HashMap` keys.
+    let mut derives = vec!["Debug", "Clone", "PartialEq", "Eq", "Hash"];
+    if !definition.fields.is_empty() {
+        derives.push("Default");
+    }
+    if options.derive_serde {
+        derives.push("Serialize");
+    }
+    push_extra_derives(&mut derives, options);
+    // `#[repr]` is not allowed on an enum without variants.
+    let repr = if definition.fields.is_empty() { "" } else { "#[repr(i32)]\n" };
+    let non_exhaustive = if options.non_exhaustive_enums { "#[non_exhaustive]\n" } else { "" };
     let enum_def = format!(
-        "{}\npub enum {} {{\n{}\n}}\n",
-        derived,
+        "{}{}#[derive({})]\npub enum {} {{\n{}\n}}\n",
+        repr,
+        non_exhaustive,
+        derives.join(", "),
         enum_name,
         variants.join("\n")
     );
 
-    let from_kiwi_impl = generate_enum_from_kiwi(definition);
-    format!("{}\n{}", enum_def, from_kiwi_impl)
+    let methods_impl = generate_enum_methods(definition, options);
+    let conversions_impl = generate_enum_int_conversions(definition, options);
+    let from_kiwi_impl = generate_enum_from_kiwi(definition, options);
+    let to_kiwi_impl = generate_enum_to_kiwi(definition, options);
+    let display_impl = generate_enum_display(definition, options);
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        enum_def, methods_impl, conversions_impl, from_kiwi_impl, to_kiwi_impl, display_impl
+    )
+}
+
+/// Generates `From<Enum> for i32` and `TryFrom<i32> for Enum`, converting
+/// between variants and the values the schema assigns them. With
+/// `tolerant_enums` the former is `TryFrom`, since `Unknown` has no value.
+fn generate_enum_int_conversions(definition: &Definition, options: &GenOptions) -> String {
+    let enum_name = to_pascal_case(&definition.name);
+    let mut match_arms = Vec::new();
+
+    for field in &definition.fields {
+        let variant_name = escape_rust_keyword(&to_pascal_case(&field.name));
+        match_arms.push(format!(
+            "            {} => Ok({}::{}),",
+            field.reserved_index, enum_name, variant_name
+        ));
+    }
+    match_arms.push(
+        "            other => Err(KiwiError::InvalidEnumVariant(other.to_string())),".to_string()
+    );
+
+    let to_int = if options.tolerant_enums {
+        let mut value_arms: Vec<String> = definition
+            .fields
+            .iter()
+            .map(|field| {
+                format!(
+                    "            {}::{} => Ok({}),",
+                    enum_name,
+                    escape_rust_keyword(&to_pascal_case(&field.name)),
+                    field.reserved_index
+                )
+            })
+            .collect();
+        value_arms.push(format!(
+            "            {}::Unknown(name) => Err(KiwiError::InvalidEnumVariant(name)),",
+            enum_name
+        ));
+        format!(
+            r#"impl TryFrom<{0}> for i32 {{
+    type Error = KiwiError;
+
+    fn try_from(value: {0}) -> Result<i32, KiwiError> {{
+        match value {{
+{1}
+        }}
+    }}
+}}
+"#,
+            enum_name,
+            value_arms.join("\n")
+        )
+    } else {
+        format!(
+            r#"impl From<{0}> for i32 {{
+    fn from(value: {0}) -> i32 {{
+        value.wire_value() as i32
+    }}
+}}
+"#,
+            enum_name
+        )
+    };
+
+    format!(
+        r#"{2}
+impl TryFrom<i32> for {0} {{
+    type Error = KiwiError;
+
+    fn try_from(value: i32) -> Result<Self, KiwiError> {{
+        match value {{
+{1}
+        }}
+    }}
+}}
+"#,
+        enum_name,
+        match_arms.join("\n"),
+        to_int
+    )
+}
+
+/// Generates `wire_value()`, returning the numeric id the schema assigns to
+/// each variant (ids need not be contiguous), and the typed `encode()`.
+fn generate_enum_methods(definition: &Definition, options: &GenOptions) -> String {
+    let enum_name = to_pascal_case(&definition.name);
+    let mut match_arms = Vec::new();
+
+    // With `tolerant_enums`, `Unknown` has no wire value.
+    let wrap = |value: String| if options.tolerant_enums { format!("Some({})", value) } else { value };
+    for field in &definition.fields {
+        let variant_name = escape_rust_keyword(&to_pascal_case(&field.name));
+        match_arms.push(format!(
+            "            {}::{} => {},",
+            enum_name, variant_name, wrap((field.reserved_index as u32).to_string())
+        ));
+    }
+    let (wire_value_type, encode_impl) = if options.tolerant_enums {
+        match_arms.push(format!("            {}::Unknown(_) => None,", enum_name));
+        (
+            "Option<u32>",
+            r#"    /// Writes this value to `bb` without going through `Value`. Fails on
+    /// `Unknown`, which has no wire value.
+    pub fn encode(&self, bb: &mut ByteBufferMut) -> Result<(), KiwiError> {
+        let value = self.wire_value().ok_or_else(|| KiwiError::InvalidEnumVariant(self.to_string()))?;
+        bb.write_var_uint(value);
+        Ok(())
+    }"#,
+        )
+    } else {
+        (
+            "u32",
+            r#"    /// Writes this value to `bb` without going through `Value`.
+    pub fn encode(&self, bb: &mut ByteBufferMut) {
+        bb.write_var_uint(self.wire_value());
+    }"#,
+        )
+    };
+
+    let mut name_arms = Vec::new();
+    for field in &definition.fields {
+        let variant_name = escape_rust_keyword(&to_pascal_case(&field.name));
+        name_arms.push(format!(
+            "            \"{}\" => Some({}::{}),",
+            field.name, enum_name, variant_name
+        ));
+    }
+    name_arms.push("            _ => None,".to_string());
+
+    format!(
+        r#"impl {} {{
+    /// Looks up a variant by its schema name, spelled exactly as in the
+    /// schema (case included), or `None` if the schema has no such variant.
+    pub fn try_from_str(name: &str) -> Option<Self> {{
+        match name {{
+{}
+        }}
+    }}
+
+    /// The numeric value of this variant in the schema{}.
+    pub fn wire_value(&self) -> {} {{
+        match *self {{
+{}
+        }}
+    }}
+
+{}
+}}
+"#,
+        enum_name,
+        name_arms.join("\n"),
+        if options.tolerant_enums { ", or `None` for `Unknown`" } else { "" },
+        wire_value_type,
+        match_arms.join("\n"),
+        encode_impl
+    )
 }
 
 /// Generates the `FromKiwi` impl for an enum, returning `Result<_, KiwiError>`.
-fn generate_enum_from_kiwi(definition: &Definition) -> String {
+/// Names match exactly as the schema spells them. With `tolerant_enums`,
+/// unrecognized names become `Unknown` instead.
+fn generate_enum_from_kiwi(definition: &Definition, options: &GenOptions) -> String {
     let enum_name = to_pascal_case(&definition.name);
     let mut match_arms = Vec::new();
 
@@ -199,16 +868,20 @@ fn generate_enum_from_kiwi(definition: &Definition) -> String {
         let variant_name = escape_rust_keyword(&to_pascal_case(&field.name));
         match_arms.push(format!(
             "            \"{}\" => Ok({}::{}),",
-            field.name.to_uppercase(),
+            field.name,
             enum_name,
             variant_name
         ));
     }
 
     // If no match, return Err(KiwiError::InvalidEnumVariant(_))
-    match_arms.push(format!(
-        "            other => Err(KiwiError::InvalidEnumVariant(other.to_string())),"
-    ));
+    if options.tolerant_enums {
+        match_arms.push(format!("            other => Ok({}::Unknown(other.to_string())),", enum_name));
+    } else {
+        match_arms.push(
+            "            other => Err(KiwiError::InvalidEnumVariant(other.to_string())),".to_string()
+        );
+    }
 
     let impl_block = format!(
         r#"impl FromKiwi for {} {{
@@ -227,15 +900,106 @@ fn generate_enum_from_kiwi(definition: &Definition) -> String {
     impl_block
 }
 
+/// Generates the `ToKiwi` impl for an enum.
+fn generate_enum_to_kiwi(definition: &Definition, options: &GenOptions) -> String {
+    let enum_name = to_pascal_case(&definition.name);
+    let mut match_arms = Vec::new();
+
+    for field in &definition.fields {
+        let variant_name = escape_rust_keyword(&to_pascal_case(&field.name));
+        match_arms.push(format!(
+            "            {}::{} => Value::Enum(\"{}\", \"{}\"),",
+            enum_name, variant_name, definition.name, field.name
+        ));
+    }
+    // `Unknown` has no schema name to borrow, so it becomes a variant no
+    // schema has, which `try_encode` and `check_against` reject.
+    if options.tolerant_enums {
+        match_arms.push(format!(
+            "            {}::Unknown(_) => Value::Enum(\"{}\", \"\"),",
+            enum_name, definition.name
+        ));
+    }
+
+    // An empty enum has no values, so `match *self {}` is exhaustive.
+    format!(
+        r#"impl ToKiwi for {} {{
+    fn to_kiwi(&self) -> Value<'static> {{
+        match *self {{
+{}
+        }}
+    }}
+}}
+"#,
+        enum_name,
+        match_arms.join("\n")
+    )
+}
+
+/// Generates a `Display` impl printing each variant's schema name, the name
+/// `from_kiwi` and `try_from_str` accept. `Unknown` prints the name it holds.
+fn generate_enum_display(definition: &Definition, options: &GenOptions) -> String {
+    let enum_name = to_pascal_case(&definition.name);
+    let mut match_arms = Vec::new();
+
+    for field in &definition.fields {
+        let variant_name = escape_rust_keyword(&to_pascal_case(&field.name));
+        match_arms.push(format!(
+            "            {}::{} => \"{}\",",
+            enum_name, variant_name, field.name
+        ));
+    }
+    if options.tolerant_enums {
+        match_arms.push(format!("            {}::Unknown(ref name) => name,", enum_name));
+    }
+
+    // `core::fmt` so the impl also builds under `no_std`.
+    format!(
+        r#"impl core::fmt::Display for {} {{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {{
+        f.write_str(match *self {{
+{}
+        }})
+    }}
+}}
+"#,
+        enum_name,
+        match_arms.join("\n")
+    )
+}
+
 /// Generates a Rust struct/message + `FromKiwi` impl that returns `Result<_, KiwiError>`.
-fn generate_struct(definition: &Definition, is_message: bool) -> String {
-    let struct_name = to_pascal_case(&definition.name);
+fn generate_struct(
+    definition: &Definition,
+    type_id: usize,
+    is_message: bool,
+    options: &GenOptions,
+    boxed: &HashSet<(String, String)>,
+    lifetimed: &HashSet<String>,
+    unhashable: &HashSet<String>,
+) -> String {
+    let struct_name = type_with_lifetime(&definition.name, lifetimed);
     let mut fields_code = Vec::new();
 
     for field in &definition.fields {
         let rust_name = escape_rust_keyword(&to_snake_case(&field.name));
-        let field_type = if let Some(ref t) = field.type_ {
-            map_type(t, is_message && definition.kind == DefinitionKind::Message, field.is_array)
+        let field_type = if field.is_map() {
+            let map = map_field_type(field, options, lifetimed);
+            let is_message = is_message && definition.kind == DefinitionKind::Message;
+            if is_message { format!("Option<{}>", map) } else { map }
+        } else if is_bytes_field(field, options) {
+            let is_message = is_message && definition.kind == DefinitionKind::Message;
+            if is_message { "Option<bytes::Bytes>".to_string() } else { "bytes::Bytes".to_string() }
+        } else if let Some(ref t) = field.type_ {
+            let is_boxed = boxed.contains(&(definition.name.clone(), field.name.clone()));
+            map_type(
+                t,
+                is_message && definition.kind == DefinitionKind::Message,
+                field.array_depth,
+                is_boxed,
+                options.zero_copy_strings,
+                lifetimed,
+            )
         } else {
             // If no type, treat as i32 for enums or String for fallback
             if definition.kind == DefinitionKind::Enum {
@@ -249,32 +1013,261 @@ fn generate_struct(definition: &Definition, is_message: bool) -> String {
         if field.is_deprecated {
             line.push_str("    #[deprecated]\n");
         }
+        if options.derive_serde && options.skip_empty_vecs && field_type.starts_with("Vec<") {
+            line.push_str("    #[serde(skip_serializing_if = \"Vec::is_empty\")]\n");
+        }
         line.push_str(&format!("    pub {}: {},", rust_name, field_type));
         fields_code.push(line);
     }
 
-    let derived = "#[derive(Debug, Clone, PartialEq, Default, Serialize)]";
-    let serde_attr = "#[skip_serializing_none]";
+    let mut derives = vec!["Debug", "Clone", "PartialEq"];
+    if !unhashable.contains(&definition.name) {
+        derives.extend(["Eq", "Hash"]);
+    }
+    derives.push("Default");
+    let mut attrs = String::new();
+    if options.derive_serde {
+        derives.push("Serialize");
+        attrs.push_str("#[skip_serializing_none]\n");
+    }
+    push_extra_derives(&mut derives, options);
     let struct_def = format!(
-        "\n{}\n{}\npub struct {} {{\n{}\n}}\n",
-        serde_attr,
-        derived,
+        "\n{}#[derive({})]\npub struct {} {{\n{}\n}}\n",
+        attrs,
+        derives.join(", "),
         struct_name,
         fields_code.join("\n")
     );
 
-    let from_kiwi_impl = generate_struct_from_kiwi(definition, is_message);
-    format!("{}\n{}", struct_def, from_kiwi_impl)
+    let from_kiwi_impl = generate_struct_from_kiwi(definition, is_message, options, boxed, lifetimed, false);
+    let to_kiwi_impl = generate_struct_to_kiwi(definition, is_message, options, lifetimed);
+    let methods_impl = generate_struct_methods(definition, type_id, is_message, options, lifetimed);
+    let mut code = format!("{}\n{}\n\n{}\n\n{}", struct_def, methods_impl, from_kiwi_impl, to_kiwi_impl);
+    if lifetimed.contains(&definition.name) {
+        code.push_str("\n\n");
+        code.push_str(&generate_struct_from_kiwi(definition, is_message, options, boxed, lifetimed, true));
+    }
+    if options.dynamic_setters && is_message {
+        code.push_str("\n\n");
+        code.push_str(&generate_message_set_field(definition, options, boxed, lifetimed));
+    }
+    code
+}
+
+/// Method names a getter must not take: the generated inherent methods, and
+/// trait methods an inherent method of the same name would shadow.
+const RESERVED_METHOD_NAMES: &[&str] = &[
+    "from_kiwi_bytes", "encode", "set_field", "from_kiwi_ref", "from_kiwi", "to_kiwi",
+    "clone", "default", "eq", "ne", "fmt", "hash", "serialize", "cmp", "partial_cmp",
+];
+
+/// Generates a getter per message field that unwraps the option with the
+/// schema default (zero, `""`, an empty slice, the first enum variant), plus a
+/// `has_…` method telling whether the field is present. Deprecated fields and
+/// names that would clash with another method are left out. A getter wins
+/// over a `has_…` method of the same name (`bool has_name` over the presence
+/// check of `string name`), whatever the field order.
+fn generate_message_getters(
+    definition: &Definition,
+    options: &GenOptions,
+    definitions_map: &HashMap<String, Definition>,
+    boxed: &HashSet<(String, String)>,
+    lifetimed: &HashSet<String>,
+) -> String {
+    let fields: Vec<&Field> = definition.fields.iter().filter(|f| !f.is_deprecated).collect();
+    let getters: Vec<Option<(String, String)>> = fields
+        .iter()
+        .map(|field| message_getter(definition, field, options, definitions_map, boxed, lifetimed))
+        .collect();
+    let getter_names: HashSet<String> = fields
+        .iter()
+        .zip(&getters)
+        .filter(|(_, getter)| getter.is_some())
+        .map(|(field, _)| escape_rust_keyword(&to_snake_case(&field.name)))
+        .collect();
+
+    let mut taken: HashSet<String> = RESERVED_METHOD_NAMES.iter().map(|s| s.to_string()).collect();
+    let mut methods = Vec::new();
+    for (field, getter) in fields.into_iter().zip(getters) {
+        let snake = to_snake_case(&field.name);
+        let rust_name = escape_rust_keyword(&snake);
+        if let Some((ret, body)) = getter {
+            if taken.insert(rust_name.clone()) {
+                methods.push(format!(
+                    "    /// Returns `{0}`, or its default when absent.\n    pub fn {1}(&self) -> {2} {{\n        {3}\n    }}",
+                    field.name, rust_name, ret, body
+                ));
+            }
+        }
+        let has_name = format!("has_{}", snake);
+        if !getter_names.contains(&has_name) && taken.insert(has_name.clone()) {
+            methods.push(format!(
+                "    /// Returns true if `{0}` is present.\n    pub fn {1}(&self) -> bool {{\n        self.{2}.is_some()\n    }}",
+                field.name, has_name, rust_name
+            ));
+        }
+    }
+
+    if methods.is_empty() {
+        return String::new();
+    }
+    format!("{}\n{}\n}}", impl_header("", &definition.name, lifetimed), methods.join("\n\n"))
+}
+
+/// Returns the return type and body of the getter for the message field
+/// `field`, or `None` if it gets none: maps and enums without variants.
+fn message_getter(
+    definition: &Definition,
+    field: &Field,
+    options: &GenOptions,
+    definitions_map: &HashMap<String, Definition>,
+    boxed: &HashSet<(String, String)>,
+    lifetimed: &HashSet<String>,
+) -> Option<(String, String)> {
+    let rust_name = escape_rust_keyword(&to_snake_case(&field.name));
+    let type_name = field.type_.as_deref().unwrap_or("");
+
+    if field.is_map() {
+        None
+    } else if field.is_array() {
+        let elem = map_type(type_name, false, field.array_depth - 1, false, options.zero_copy_strings, lifetimed);
+        Some((
+            format!("&[{}]", elem),
+            format!("self.{}.as_deref().unwrap_or(&[])", rust_name),
+        ))
+    } else {
+        match type_name {
+            "string" => Some(("&str".to_string(), format!("self.{}.as_deref().unwrap_or(\"\")", rust_name))),
+            "bool" | "byte" | "int" | "uint" | "float" | "int64" | "uint64" | "float64" => Some((
+                map_type(type_name, false, 0, false, false, lifetimed),
+                format!("self.{}.unwrap_or_default()", rust_name),
+            )),
+            other => match definitions_map.get(other) {
+                // An enum without variants has no `Default`.
+                Some(def) if def.kind == DefinitionKind::Enum && def.fields.is_empty() => None,
+                Some(_) => {
+                    let is_boxed = boxed.contains(&(definition.name.clone(), field.name.clone()));
+                    let unwrap = if is_boxed {
+                        format!("self.{}.as_deref().cloned().unwrap_or_default()", rust_name)
+                    } else {
+                        format!("self.{}.clone().unwrap_or_default()", rust_name)
+                    };
+                    Some((type_with_lifetime(other, lifetimed), unwrap))
+                }
+                None => None,
+            },
+        }
+    }
+}
+
+/// Generates `set_field`, which assigns a message field by its schema name.
+/// Unknown names and values of the wrong kind return `KiwiError::MissingField`;
+/// nested values that fail to convert return the error from their `from_kiwi`.
+fn generate_message_set_field(
+    definition: &Definition,
+    options: &GenOptions,
+    boxed: &HashSet<(String, String)>,
+    lifetimed: &HashSet<String>,
+) -> String {
+    let strings = if options.zero_copy_strings { StringMode::OwnedCow } else { StringMode::Owned };
+
+    let mut lines = Vec::new();
+    lines.push(impl_header("", &definition.name, lifetimed));
+    lines.push("    /// Sets the field named `name` (as spelled in the schema) from `value`.".into());
+    if definition.fields.is_empty() {
+        lines.push("    pub fn set_field(&mut self, name: &str, _value: Value) -> Result<(), KiwiError> {".into());
+        lines.push("        Err(KiwiError::MissingField(name.to_string()))".into());
+        lines.push("    }".into());
+        lines.push("}".into());
+        return lines.join("\n");
+    }
+    lines.push("    pub fn set_field(&mut self, name: &str, value: Value) -> Result<(), KiwiError> {".into());
+    lines.push("        match name {".into());
+
+    for field in &definition.fields {
+        let original = &field.name;
+        let rust_name = escape_rust_keyword(&to_snake_case(original));
+        let type_name = field.type_.as_deref().unwrap_or("");
+        let is_base = NATIVE_TYPES.contains(&type_name);
+        let nested_type = to_pascal_case(type_name);
+
+        let converted = if field.is_map() {
+            map_from_value(field, "value", strings, options.no_std, &|_| "from_kiwi")
+        } else if field.is_array() && type_name == "byte" {
+            let bytes = bytes_from_value("value", original, field.array_depth);
+            if is_bytes_field(field, options) { format!("{}.into()", bytes) } else { bytes }
+        } else if field.is_array() && is_base {
+            let elem = format!("item.{}", conversion_method(type_name, original, strings));
+            format!(
+                "{{ let mut tmp = Vec::new(); for item in value.as_array() {{ tmp.push({}); }} {} }}",
+                nested_item_conversion(&elem, field.array_depth - 1),
+                if is_bytes_field(field, options) { "tmp.into()" } else { "tmp" }
+            )
+        } else if field.array_depth > 1 {
+            let elem = format!("{}::from_kiwi(item)?", nested_type);
+            format!(
+                "value.as_array().iter().map(|item| Ok({})).collect::<Result<_, KiwiError>>()?",
+                nested_item_conversion(&elem, field.array_depth - 1)
+            )
+        } else if field.is_array() {
+            format!(
+                "value.as_array().iter().map({}::from_kiwi).collect::<Result<_, KiwiError>>()?",
+                nested_type
+            )
+        } else if is_base {
+            format!("value.{}", conversion_method(type_name, original, strings))
+        } else if boxed.contains(&(definition.name.clone(), original.clone())) {
+            format!("Box::new({}::from_kiwi(&value)?)", nested_type)
+        } else {
+            format!("{}::from_kiwi(&value)?", nested_type)
+        };
+
+        lines.push(format!(
+            "            \"{}\" => self.{} = Some({}),",
+            original, rust_name, converted
+        ));
+    }
+
+    lines.push("            other => return Err(KiwiError::MissingField(other.to_string())),".into());
+    lines.push("        }".into());
+    lines.push("        Ok(())".into());
+    lines.push("    }".into());
+    lines.push("}".into());
+    lines.join("\n")
 }
 
 /// Generates the `FromKiwi` impl for a struct/message, returning `Result<..., KiwiError>`.
-fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> String {
+/// With `borrowed`, generates the inherent `from_kiwi_ref` instead, whose
+/// strings (and those of nested lifetimed types) borrow from `value`.
+fn generate_struct_from_kiwi(
+    definition: &Definition,
+    is_message: bool,
+    options: &GenOptions,
+    boxed: &HashSet<(String, String)>,
+    lifetimed: &HashSet<String>,
+    borrowed: bool,
+) -> String {
     let struct_name = to_pascal_case(&definition.name);
-    let instance = to_snake_case(&struct_name);
+    let instance = escape_rust_keyword(&to_snake_case(&struct_name));
+    let strings = match (options.zero_copy_strings, borrowed) {
+        (false, _)    => StringMode::Owned,
+        (true, false) => StringMode::OwnedCow,
+        (true, true)  => StringMode::BorrowedCow,
+    };
+    // Nested lifetimed types are borrowed through their own `from_kiwi_ref`.
+    let from_kiwi = |type_name: &str| {
+        if borrowed && lifetimed.contains(type_name) { "from_kiwi_ref" } else { "from_kiwi" }
+    };
 
     let mut lines = Vec::new();
-    lines.push(format!("impl FromKiwi for {} {{", struct_name));
-    lines.push("    fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {".into());
+    if borrowed {
+        lines.push(impl_header("", &definition.name, lifetimed));
+        lines.push("    /// Like `from_kiwi`, but borrows strings from `value` instead of copying them.".into());
+        lines.push("    pub fn from_kiwi_ref(value: &'a Value) -> Result<Self, KiwiError> {".into());
+    } else {
+        lines.push(impl_header("FromKiwi", &definition.name, lifetimed));
+        lines.push("    fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {".into());
+    }
     lines.push(format!("        let mut {} = Self::default();", instance));
     lines.push("".into());
 
@@ -282,11 +1275,44 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
         let original = &field.name;
         let rust_name = escape_rust_keyword(&to_snake_case(original));
         let type_name = field.type_.as_deref().unwrap_or("");
-        let is_array = field.is_array;
+        let is_array = field.is_array();
         let is_base = NATIVE_TYPES.contains(&type_name);
+        let nested = if boxed.contains(&(definition.name.clone(), original.clone())) {
+            format!("Box::new({}::{}(val)?)", to_pascal_case(type_name), from_kiwi(type_name))
+        } else {
+            format!("{}::{}(val)?", to_pascal_case(type_name), from_kiwi(type_name))
+        };
 
-        if is_array {
+        if field.is_map() {
+            let converted = map_from_value(field, "val", strings, options.no_std, &from_kiwi);
+            lines.push(format!("        if let Some(val) = value.get(\"{}\") {{", original));
+            if is_message && definition.kind == DefinitionKind::Message {
+                lines.push(format!("            {}.{} = Some({});", instance, rust_name, converted));
+            } else {
+                lines.push(format!("            {}.{} = {};", instance, rust_name, converted));
+                lines.push("        } else {".into());
+                lines.push(format!(
+                    "            return Err(KiwiError::MissingField(\"{}\".into()));",
+                    original
+                ));
+            }
+            lines.push("        }".into());
+        } else if is_array {
+            let base_item = format!("item.{}", conversion_method(type_name, original, strings));
+            let nested_item = format!("{}::{}(item)?", to_pascal_case(type_name), from_kiwi(type_name));
             // Handle array of primitives vs array of messages
+            let collected = if is_bytes_field(field, options) { "bytes::Bytes::from(tmp)" } else { "tmp" };
+            let fill_tmp = if type_name == "byte" {
+                vec![format!("            let tmp = {};", bytes_from_value("arr", original, field.array_depth))]
+            } else {
+                vec![
+                    "            let mut tmp = Vec::new();".to_string(),
+                    format!(
+                        "            for item in arr.as_array() {{ tmp.push({}); }}",
+                        nested_item_conversion(&base_item, field.array_depth - 1)
+                    ),
+                ]
+            };
             if is_base {
                 if is_message && definition.kind == DefinitionKind::Message {
                     // Option<Vec<primitive>>
@@ -294,16 +1320,10 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                         "        if let Some(arr) = value.get(\"{}\") {{",
                         original
                     ));
+                    lines.extend(fill_tmp);
                     lines.push(format!(
-                        "            let mut tmp = Vec::new();"
-                    ));
-                    lines.push(format!(
-                        "            for item in arr.as_array() {{ tmp.push(item.{}); }}",
-                        conversion_method(type_name)
-                    ));
-                    lines.push(format!(
-                        "            {}.{} = Some(tmp);",
-                        instance, rust_name
+                        "            {}.{} = Some({});",
+                        instance, rust_name, collected
                     ));
                     lines.push("        }".into());
                 } else {
@@ -312,16 +1332,10 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                         "        if let Some(arr) = value.get(\"{}\") {{",
                         original
                     ));
+                    lines.extend(fill_tmp);
                     lines.push(format!(
-                        "            let mut tmp = Vec::new();"
-                    ));
-                    lines.push(format!(
-                        "            for item in arr.as_array() {{ tmp.push(item.{}); }}",
-                        conversion_method(type_name)
-                    ));
-                    lines.push(format!(
-                        "            {}.{} = tmp;",
-                        instance, rust_name
+                        "            {}.{} = {};",
+                        instance, rust_name, collected
                     ));
                     lines.push("        } else {".into());
                     lines.push(format!(
@@ -338,10 +1352,10 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                         "        if let Some(arr) = value.get(\"{}\") {{",
                         original
                     ));
-                    lines.push(format!("            let mut tmp = Vec::new();"));
+                    lines.push("            let mut tmp = Vec::new();".into());
                     lines.push(format!(
-                        "            for item in arr.as_array() {{ tmp.push({}::from_kiwi(item)?); }}",
-                        to_pascal_case(type_name)
+                        "            for item in arr.as_array() {{ tmp.push({}); }}",
+                        nested_item_conversion(&nested_item, field.array_depth - 1)
                     ));
                     lines.push(format!(
                         "            {}.{} = Some(tmp);",
@@ -354,10 +1368,10 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                         "        if let Some(arr) = value.get(\"{}\") {{",
                         original
                     ));
-                    lines.push(format!("            let mut tmp = Vec::new();"));
+                    lines.push("            let mut tmp = Vec::new();".into());
                     lines.push(format!(
-                        "            for item in arr.as_array() {{ tmp.push({}::from_kiwi(item)?); }}",
-                        to_pascal_case(type_name)
+                        "            for item in arr.as_array() {{ tmp.push({}); }}",
+                        nested_item_conversion(&nested_item, field.array_depth - 1)
                     ));
                     lines.push(format!(
                         "            {}.{} = tmp;",
@@ -382,7 +1396,7 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                     ));
                     lines.push(format!(
                         "            {}.{} = Some(val.{});",
-                        instance, rust_name, conversion_method(type_name)
+                        instance, rust_name, conversion_method(type_name, original, strings)
                     ));
                     lines.push("        }".into());
                 } else {
@@ -391,8 +1405,8 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                         original
                     ));
                     lines.push(format!(
-                        "            {}.{} = Some({}::from_kiwi(val)?);",
-                        instance, rust_name, to_pascal_case(type_name)
+                        "            {}.{} = Some({});",
+                        instance, rust_name, nested
                     ));
                     lines.push("        }".into());
                 }
@@ -405,7 +1419,7 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                     ));
                     lines.push(format!(
                         "            {}.{} = val.{};",
-                        instance, rust_name, conversion_method(type_name)
+                        instance, rust_name, conversion_method(type_name, original, strings)
                     ));
                     lines.push("        } else {".into());
                     lines.push(format!(
@@ -419,8 +1433,8 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
                         original
                     ));
                     lines.push(format!(
-                        "            {}.{} = {}::from_kiwi(val)?;",
-                        instance, rust_name, to_pascal_case(type_name)
+                        "            {}.{} = {};",
+                        instance, rust_name, nested
                     ));
                     lines.push("        } else {".into());
                     lines.push(format!(
@@ -440,3 +1454,282 @@ fn generate_struct_from_kiwi(definition: &Definition, is_message: bool) -> Strin
     lines.push("}".into());
     lines.join("\n")
 }
+
+/// Returns the statements writing the reference `item`, a value of
+/// `type_name` nested `array_depth` arrays deep, to `bb`.
+fn write_item_statement(type_name: &str, array_depth: u32, fallible: bool) -> String {
+    match array_depth {
+        0 => write_statement(type_name, "item", true, fallible),
+        1 if type_name == "byte" => "bb.write_var_uint(item.len() as u32); bb.write_bytes(item);".to_string(),
+        _ => format!(
+            "bb.write_var_uint(item.len() as u32); for item in item.iter() {{ {} }}",
+            write_item_statement(type_name, array_depth - 1, fallible)
+        ),
+    }
+}
+
+/// Returns the statement writing `expr` to `bb`, matching the layout
+/// `Value::encode_bb` produces. `expr` is either a field place (`self.x`) or,
+/// when `is_ref` is set, a reference to one (`item`). With `fallible`, the
+/// `encode` of enums and structs returns a `Result`, which is propagated.
+fn write_statement(type_name: &str, expr: &str, is_ref: bool, fallible: bool) -> String {
+    let copied = if is_ref { format!("*{}", expr) } else { expr.to_string() };
+    let borrowed = if is_ref { expr.to_string() } else { format!("&{}", expr) };
+    match type_name {
+        "bool"   => format!("bb.write_bool({});", copied),
+        "byte"   => format!("bb.write_byte({});", copied),
+        "int"    => format!("bb.write_var_int({});", copied),
+        "uint"   => format!("bb.write_var_uint({});", copied),
+        "float"  => format!("bb.write_var_float({});", copied),
+        "string" => format!("bb.write_string({});", borrowed),
+        "int64"  => format!("bb.write_var_int64({});", copied),
+        "uint64" => format!("bb.write_var_uint64({});", copied),
+        "float64" => format!("bb.write_var_float64({});", copied),
+        _        => format!("{}.encode(bb){};", expr, if fallible { "?" } else { "" }),
+    }
+}
+
+/// Generates `from_kiwi_bytes()`, which decodes bytes of this type with a
+/// runtime schema, and a typed `encode()` that writes fields in schema order
+/// straight to a `ByteBufferMut`, avoiding the `Value` map. With
+/// `tolerant_enums`, `encode()` fails on an `Unknown` enum anywhere inside.
+fn generate_struct_methods(
+    definition: &Definition,
+    type_id: usize,
+    is_message: bool,
+    options: &GenOptions,
+    lifetimed: &HashSet<String>,
+) -> String {
+    let is_message = is_message && definition.kind == DefinitionKind::Message;
+    let bb = if definition.fields.is_empty() && !is_message { "_bb" } else { "bb" };
+    let fallible = options.tolerant_enums;
+
+    let mut lines = Vec::new();
+    lines.push(impl_header("", &definition.name, lifetimed));
+    lines.push("    /// Decodes `bytes` as this type using `schema` (e.g. the embedded `schema()`).".into());
+    lines.push("    pub fn from_kiwi_bytes(schema: &Schema, bytes: &[u8]) -> Result<Self, KiwiError> {".into());
+    lines.push(format!("        let value = Value::decode(schema, {}, bytes)", type_id));
+    lines.push(format!(
+        "            .map_err(|_| KiwiError::DecodeError(\"invalid {} bytes\".into()))?;",
+        definition.name
+    ));
+    lines.push("        Self::from_kiwi(&value)".into());
+    lines.push("    }".into());
+    lines.push("".into());
+    lines.push("    /// Writes this value to `bb` without going through `Value`.".into());
+    if fallible {
+        lines.push("    /// Fails on an `Unknown` enum, leaving a partial value in `bb`.".into());
+        lines.push(format!("    pub fn encode(&self, {}: &mut ByteBufferMut) -> Result<(), KiwiError> {{", bb));
+    } else {
+        lines.push(format!("    pub fn encode(&self, {}: &mut ByteBufferMut) {{", bb));
+    }
+
+    for field in &definition.fields {
+        let rust_name = escape_rust_keyword(&to_snake_case(&field.name));
+        let type_name = field.type_.as_deref().unwrap_or("");
+        // Message fields are written only when present, prefixed by their id.
+        let (place, is_ref, indent) = if is_message {
+            lines.push(format!("        if let Some(ref val) = self.{} {{", rust_name));
+            lines.push(format!("            bb.write_var_uint({});", field.reserved_index));
+            ("val".to_string(), true, "            ")
+        } else {
+            (format!("self.{}", rust_name), false, "        ")
+        };
+
+        if let Some(ref key_type) = field.key_type {
+            lines.push(format!("{}bb.write_var_uint({}.len() as u32);", indent, place));
+            lines.push(format!(
+                "{}for (key, item) in {}.iter() {{ {} {} }}",
+                indent,
+                place,
+                write_statement(key_type, "key", true, fallible),
+                write_statement(type_name, "item", true, fallible)
+            ));
+        } else if field.array_depth == 1 && type_name == "byte" {
+            lines.push(format!("{}bb.write_var_uint({}.len() as u32);", indent, place));
+            let borrowed = if is_ref { place.clone() } else { format!("&{}", place) };
+            lines.push(format!("{}bb.write_bytes({});", indent, borrowed));
+        } else if field.is_array() {
+            lines.push(format!("{}bb.write_var_uint({}.len() as u32);", indent, place));
+            lines.push(format!(
+                "{}for item in {}.iter() {{ {} }}",
+                indent,
+                place,
+                write_item_statement(type_name, field.array_depth - 1, fallible)
+            ));
+        } else {
+            lines.push(format!("{}{}", indent, write_statement(type_name, &place, is_ref, fallible)));
+        }
+
+        if is_message {
+            lines.push("        }".into());
+        }
+    }
+
+    if is_message {
+        lines.push("        bb.write_byte(0);".into());
+    }
+    if fallible {
+        lines.push("        Ok(())".into());
+    }
+    lines.push("    }".into());
+    lines.push("}".into());
+    lines.join("\n")
+}
+
+/// Generates the `ToKiwi` impl for a struct/message.
+fn generate_struct_to_kiwi(
+    definition: &Definition,
+    is_message: bool,
+    options: &GenOptions,
+    lifetimed: &HashSet<String>,
+) -> String {
+    let cow_strings = options.zero_copy_strings;
+
+    let mut lines = Vec::new();
+    lines.push(impl_header("ToKiwi", &definition.name, lifetimed));
+    lines.push("    fn to_kiwi(&self) -> Value<'static> {".into());
+    // `Map` is whichever map the runtime's `Value::Object` holds, with or
+    // without `std`.
+    lines.push("        let mut fields = Map::new();".into());
+
+    for field in &definition.fields {
+        let original = &field.name;
+        let rust_name = escape_rust_keyword(&to_snake_case(original));
+        let type_name = field.type_.as_deref().unwrap_or("");
+
+        if is_message && definition.kind == DefinitionKind::Message {
+            // Option<...>: only present fields are written
+            let value = if field.is_map() {
+                map_value(field, "val", cow_strings)
+            } else if field.is_array() {
+                array_value(type_name, "val", field.array_depth, cow_strings)
+            } else {
+                value_constructor(type_name, "val", true, cow_strings)
+            };
+            lines.push(format!(
+                "        if let Some(ref val) = self.{} {{ {} }}",
+                rust_name,
+                map_insert(original, &value)
+            ));
+        } else {
+            let value = if field.is_map() {
+                map_value(field, &format!("self.{}", rust_name), cow_strings)
+            } else if field.is_array() {
+                array_value(type_name, &format!("self.{}", rust_name), field.array_depth, cow_strings)
+            } else {
+                value_constructor(type_name, &format!("self.{}", rust_name), false, cow_strings)
+            };
+            lines.push(format!("        {}", map_insert(original, &value)));
+        }
+    }
+
+    lines.push(format!("        Value::Object(\"{}\", fields)", definition.name));
+    lines.push("    }".into());
+    lines.push("}".into());
+    lines.join("\n")
+}
+
+/// Returns the `Value::Map` expression for the map field at `place`.
+fn map_value(field: &Field, place: &str, cow_strings: bool) -> String {
+    format!(
+        "Value::Map({}.iter().map(|(key, item)| ({}, {})).collect())",
+        place,
+        value_constructor(field.key_type.as_deref().unwrap_or(""), "key", true, cow_strings),
+        value_constructor(field.type_.as_deref().unwrap_or(""), "item", true, cow_strings)
+    )
+}
+
+/// Returns the statement adding `value` under `name` to `fields`.
+fn map_insert(name: &str, value: &str) -> String {
+    format!("fields.insert(\"{}\", {});", name, value)
+}
+
+/// Generates the embedded binary schema and a `schema()` accessor for it.
+fn generate_schema_fn(bytes: &[u8]) -> String {
+    let rows = bytes
+        .chunks(16)
+        .map(|row| {
+            let row = row.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+            format!("    {},", row.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"/// The binary schema these types were generated from.
+pub const SCHEMA_BYTES: &[u8] = &[
+{}
+];
+
+/// Decodes the embedded binary schema.
+pub fn schema() -> Schema {{
+    Schema::decode(SCHEMA_BYTES).expect("embedded schema is valid")
+}}
+"#,
+        rows
+    )
+}
+
+/// Generates a `generated_tests` module that round-trips every type.
+fn generate_tests(schema: &Schema, options: &GenOptions) -> String {
+    let krate = if options.no_std { "core" } else { "std" };
+    let mut lines: Vec<String> = vec![
+        "".into(),
+        "#[cfg(test)]".into(),
+        "mod generated_tests {".into(),
+        "    use super::*;".into(),
+        "".into(),
+        format!("    fn round_trip<T: FromKiwi + ToKiwi + PartialEq + {}::fmt::Debug>(name: &str, sample: T) {{", krate),
+        "        let schema = schema();".into(),
+        "        let type_id = schema.def(name).expect(\"definition exists\").index;".into(),
+        "        let bytes = sample.to_kiwi().encode(&schema);".into(),
+        "        let value = Value::decode(&schema, type_id, &bytes).expect(\"decode failed\");".into(),
+        "        assert_eq!(T::from_kiwi(&value).expect(\"from_kiwi failed\"), sample);".into(),
+        "    }".into(),
+    ];
+
+    for definition in &schema.definitions {
+        // There is nothing to construct for an enum without variants.
+        if definition.kind == DefinitionKind::Enum && definition.fields.is_empty() {
+            continue;
+        }
+        lines.push("".into());
+        lines.push("    #[test]".into());
+        lines.push(format!(
+            "    fn round_trip_{}() {{",
+            to_snake_case(&to_pascal_case(&definition.name))
+        ));
+        lines.push(format!(
+            "        round_trip(\"{}\", {}::default());",
+            definition.name,
+            to_pascal_case(&definition.name)
+        ));
+        lines.push("    }".into());
+    }
+
+    lines.push("}".into());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_snake_case() {
+        let cases = [
+            ("clientID",   "client_id"),
+            ("HTTPStatus", "http_status"),
+            ("2dPos",      "_2d_pos"),
+            ("2d_pos",     "_2d_pos"),
+            ("v2",         "v2"),
+            ("alpha",      "alpha"),
+            ("ColorRGB",   "color_rgb"),
+            ("Example",    "example"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(to_snake_case(input), expected, "to_snake_case({:?})", input);
+        }
+    }
+}
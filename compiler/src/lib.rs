@@ -1,11 +1,17 @@
 //! brine-kiwi-compiler
 //!
 //! This crate implements:
-//!  1) A tokenizer + parser for `.kiwi` IDL files,
+//!  1) A tokenizer + parser for `.kiwi` IDL files, and `compile_schema_files`
+//!     / `compile_schema_with_resolver` for schemas split across files with
+//!     `import`,
 //!  2) A schema verifier (duplicate types, recursive structs, missing types, etc.),
 //!  3) `encode_binary_schema` / `decode_binary_schema` (flat‐buffer style),
-//!  4) Code generation (`compile_schema_to_rust` → `String`),
-//!  5) Error types (`KiwiError`), and `FromKiwi` trait.
+//!     plus `explain_binary_schema` for an offset-annotated dump,
+//!  4) `diff_schemas` for comparing two versions of a schema, and
+//!     `check_compatibility` for rejecting breaking ones,
+//!  5) Code generation (`compile_schema_to_rust` → `String`), and
+//!     `schema_to_kiwi_text` for printing a schema back as canonical `.kiwi`,
+//!  6) Error types (`KiwiError`), and `FromKiwi` trait.
 
 pub mod error;
 pub mod types;
@@ -14,10 +20,20 @@ pub mod tokenizer;
 pub mod parser;
 pub mod verifier;
 pub mod compiler;
+pub mod compat;
+pub mod format;
 pub mod gen_rust;
 pub mod traits;
 
 pub use compiler::compile_schema;
+pub use compiler::compile_schema_files;
+pub use compiler::compile_schema_with_resolver;
 pub use compiler::decode_binary_schema;
 pub use compiler::encode_binary_schema;
+pub use compiler::explain_binary_schema;
+pub use compiler::SchemaCache;
+pub use compat::{check_compatibility, diff_schemas, CompatError, SchemaChange};
+pub use format::schema_to_kiwi_text;
 pub use gen_rust::compile_schema_to_rust;
+pub use gen_rust::compile_schema_to_rust_with;
+pub use gen_rust::GenOptions;
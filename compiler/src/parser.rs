@@ -1,6 +1,6 @@
 use crate::{
     tokenizer::Token,
-    types::{Definition, DefinitionKind, Field, Schema},
+    types::{Definition, DefinitionKind, Field, Schema, Span},
     utils::{error, quote},
     error::KiwiError,
 };
@@ -8,28 +8,61 @@ use lazy_static::lazy_static;
 use regex::Regex;
 
 lazy_static! {
-    static ref IDENTIFIER:       Regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    static ref IDENTIFIER:       Regex = Regex::new(r"^[\p{XID_Start}_]\p{XID_Continue}*$").unwrap();
     static ref EQUALS:           Regex = Regex::new(r"^=$").unwrap();
     static ref SEMICOLON:        Regex = Regex::new(r"^;$").unwrap();
-    static ref INTEGER:          Regex = Regex::new(r"^-?\d+$").unwrap();
+    static ref COMMA:            Regex = Regex::new(r"^,$").unwrap();
+    static ref DOT:              Regex = Regex::new(r"^\.$").unwrap();
+    static ref INTEGER:          Regex = Regex::new(r"^-?(?:0[xX][0-9A-Fa-f]+|\d+)$").unwrap();
     static ref LEFT_BRACE:       Regex = Regex::new(r"^\{$").unwrap();
     static ref RIGHT_BRACE:      Regex = Regex::new(r"^\}$").unwrap();
     static ref ARRAY_TOKEN:      Regex = Regex::new(r"^\[\]$").unwrap();
+    static ref LEFT_ANGLE:       Regex = Regex::new(r"^<$").unwrap();
+    static ref RIGHT_ANGLE:      Regex = Regex::new(r"^>$").unwrap();
+    static ref MAP_KEYWORD:      Regex = Regex::new(r"^map$").unwrap();
     static ref ENUM_KEYWORD:     Regex = Regex::new(r"^enum$").unwrap();
     static ref STRUCT_KEYWORD:   Regex = Regex::new(r"^struct$").unwrap();
     static ref MESSAGE_KEYWORD:  Regex = Regex::new(r"^message$").unwrap();
     static ref PACKAGE_KEYWORD:  Regex = Regex::new(r"^package$").unwrap();
+    static ref IMPORT_KEYWORD:   Regex = Regex::new(r"^import$").unwrap();
+    static ref STRING:           Regex = Regex::new(r#"^"[^"\n]*"$"#).unwrap();
+    static ref RESERVED_KEYWORD: Regex = Regex::new(r"^reserved$").unwrap();
+    static ref TO_KEYWORD:       Regex = Regex::new(r"^to$").unwrap();
     static ref DEPRECATED_TOKEN: Regex = Regex::new(r"^\[deprecated\]$").unwrap();
     static ref EOF:              Regex = Regex::new(r"^$").unwrap();
 }
 
+/// Parses a decimal or `0x`-prefixed hexadecimal integer literal.
+fn parse_integer(text: &str) -> Option<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None       => (false, text),
+    };
+    let magnitude = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None      => digits.parse::<i64>().ok()?,
+    };
+    i32::try_from(if negative { -magnitude } else { magnitude }).ok()
+}
+
+/// Returns the span from the start of `first` to the end of `last`.
+fn span_between(first: &Token, last: &Token) -> Span {
+    Span {
+        start_line:   first.line,
+        start_column: first.column,
+        end_line:     last.line,
+        end_column:   last.column + last.text.chars().count(),
+    }
+}
+
 /// Now returns `Result<Schema, KiwiError>`.
 pub fn parse_schema(tokens: &[Token]) -> Result<Schema, KiwiError> {
     let mut definitions  = Vec::new();
     let mut package_text = None;
+    let mut imports      = Vec::new();
     let mut index        = 0;
 
-    fn current_token<'a>(tokens: &'a [Token], index: usize) -> &'a Token {
+    fn current_token(tokens: &[Token], index: usize) -> &Token {
         tokens.get(index).expect("Unexpected end of tokens")
     }
 
@@ -63,25 +96,69 @@ pub fn parse_schema(tokens: &[Token]) -> Result<Schema, KiwiError> {
         )
     }
 
-    // Handle package declaration
+    /// Parses a field id or enum value, decimal or hexadecimal. Neither may
+    /// be negative.
+    fn parse_id(tokens: &[Token], index: &mut usize) -> Result<i32, KiwiError> {
+        let v_tok = current_token(tokens, *index);
+        expect(tokens, index, &INTEGER, "integer")?;
+        let value = parse_integer(&v_tok.text).ok_or_else(|| {
+            error(
+                &format!("Invalid integer {}", quote(&v_tok.text)),
+                v_tok.line,
+                v_tok.column,
+            )
+        })?;
+        if value < 0 {
+            return Err(error(
+                &format!("Negative id {} is not allowed", quote(&v_tok.text)),
+                v_tok.line,
+                v_tok.column,
+            ));
+        }
+        Ok(value)
+    }
+
+    // Handle package declaration, which may be dotted (`package game.net;`)
     if eat(tokens, &mut index, &PACKAGE_KEYWORD) {
         if index >= tokens.len() {
             return Err(error("Expected identifier after package", 0, 0));
         }
         let pkg_tok = current_token(tokens, index);
         expect(tokens, &mut index, &IDENTIFIER, "identifier")?;
-        package_text = Some(pkg_tok.text.clone());
+        let mut name = pkg_tok.text.clone();
+        while eat(tokens, &mut index, &DOT) {
+            let part_tok = current_token(tokens, index);
+            expect(tokens, &mut index, &IDENTIFIER, "identifier")?;
+            name.push('.');
+            name.push_str(&part_tok.text);
+        }
+        package_text = Some(name);
+        expect(tokens, &mut index, &SEMICOLON, "\";\"")?;
+    }
+
+    // Then any `import "other.kiwi";` statements, before the definitions
+    while eat(tokens, &mut index, &IMPORT_KEYWORD) {
+        let path_tok = current_token(tokens, index);
+        expect(tokens, &mut index, &STRING, "string")?;
+        imports.push(path_tok.text.trim_matches('"').to_string());
         expect(tokens, &mut index, &SEMICOLON, "\";\"")?;
     }
 
     // Parse definitions one by one
     while index < tokens.len() && !eat(tokens, &mut index, &EOF) {
+        let def_start = index;
         let kind = if eat(tokens, &mut index, &ENUM_KEYWORD) {
             DefinitionKind::Enum
         } else if eat(tokens, &mut index, &STRUCT_KEYWORD) {
             DefinitionKind::Struct
         } else if eat(tokens, &mut index, &MESSAGE_KEYWORD) {
             DefinitionKind::Message
+        } else if PACKAGE_KEYWORD.is_match(&current_token(tokens, index).text) {
+            let tok = current_token(tokens, index);
+            return Err(error("package declaration must be the first statement", tok.line, tok.column));
+        } else if IMPORT_KEYWORD.is_match(&current_token(tokens, index).text) {
+            let tok = current_token(tokens, index);
+            return Err(error("import statements must come before definitions", tok.line, tok.column));
         } else {
             return Err(unexpected_token(tokens, &mut index));
         };
@@ -93,35 +170,105 @@ pub fn parse_schema(tokens: &[Token]) -> Result<Schema, KiwiError> {
 
         // Collect fields
         let mut fields = Vec::new();
+        let mut reserved_ranges = Vec::new();
+        // `None` once the previous value was i32::MAX, which has no successor.
+        let mut next_enum_value = Some(0);
         while !eat(tokens, &mut index, &RIGHT_BRACE) {
+            // `reserved 3, 5, 7 to 9;` (or `7..9`) in a message. A field whose
+            // type is named `reserved` is still allowed, since it has no
+            // integer next.
+            if kind == DefinitionKind::Message
+                && RESERVED_KEYWORD.is_match(&current_token(tokens, index).text)
+                && tokens.get(index + 1).is_some_and(|t| INTEGER.is_match(&t.text))
+            {
+                index += 1;
+                loop {
+                    let start_tok = current_token(tokens, index);
+                    let start = parse_id(tokens, &mut index)?;
+                    let end = if eat(tokens, &mut index, &TO_KEYWORD) {
+                        parse_id(tokens, &mut index)?
+                    } else if eat(tokens, &mut index, &DOT) {
+                        expect(tokens, &mut index, &DOT, "\".\"")?;
+                        parse_id(tokens, &mut index)?
+                    } else {
+                        start
+                    };
+                    if end < start {
+                        return Err(error(
+                            &format!("Invalid reserved range {} to {}", start, end),
+                            start_tok.line,
+                            start_tok.column,
+                        ));
+                    }
+                    reserved_ranges.push((start, end));
+                    if !eat(tokens, &mut index, &COMMA) {
+                        break;
+                    }
+                }
+                expect(tokens, &mut index, &SEMICOLON, "\";\"")?;
+                continue;
+            }
+
+            let field_start      = index;
             let mut type_opt     = None;
-            let mut is_array     = false;
+            let mut key_type     = None;
+            let mut array_depth  = 0;
             let mut is_deprecated = false;
 
             if kind != DefinitionKind::Enum {
                 // Read the type token
                 let t_tok = current_token(tokens, index);
                 expect(tokens, &mut index, &IDENTIFIER, "identifier")?;
-                if eat(tokens, &mut index, &ARRAY_TOKEN) {
-                    is_array = true;
+                if MAP_KEYWORD.is_match(&t_tok.text) && eat(tokens, &mut index, &LEFT_ANGLE) {
+                    // `map<K, V>`: a type named `map` is still allowed, since
+                    // it has no `<` next.
+                    let k_tok = current_token(tokens, index);
+                    expect(tokens, &mut index, &IDENTIFIER, "identifier")?;
+                    expect(tokens, &mut index, &COMMA, "\",\"")?;
+                    let v_tok = current_token(tokens, index);
+                    expect(tokens, &mut index, &IDENTIFIER, "identifier")?;
+                    expect(tokens, &mut index, &RIGHT_ANGLE, "\">\"")?;
+                    if ARRAY_TOKEN.is_match(&current_token(tokens, index).text) {
+                        let array = current_token(tokens, index);
+                        return Err(error("Map fields cannot be arrays", array.line, array.column));
+                    }
+                    key_type = Some(k_tok.text.clone());
+                    type_opt = Some(v_tok.text.clone());
+                } else {
+                    // Each `[]` nests one more array: `int[][]` is an array of arrays.
+                    while eat(tokens, &mut index, &ARRAY_TOKEN) {
+                        array_depth += 1;
+                    }
+                    type_opt = Some(t_tok.text.clone());
                 }
-                type_opt = Some(t_tok.text.clone());
             }
 
             // Field name
             let f_tok = current_token(tokens, index);
             expect(tokens, &mut index, &IDENTIFIER, "identifier")?;
 
-            // Value (either explicit or auto‐increment for structs)
-            let value = if kind != DefinitionKind::Struct {
-                expect(tokens, &mut index, &EQUALS, "\"=\"")?;
-                let v_tok = current_token(tokens, index);
-                expect(tokens, &mut index, &INTEGER, "integer")?;
-                v_tok.text.parse::<i32>().map_err(|_| {
+            // Value (either explicit or auto‐increment for structs). Enum
+            // values may also be omitted, continuing from the previous value.
+            let has_value = match kind {
+                DefinitionKind::Enum    => eat(tokens, &mut index, &EQUALS),
+                DefinitionKind::Message => {
+                    expect(tokens, &mut index, &EQUALS, "\"=\"")?;
+                    true
+                }
+                DefinitionKind::Struct  => false,
+            };
+            let value = if has_value {
+                parse_id(tokens, &mut index)?
+            } else if kind == DefinitionKind::Enum {
+                next_enum_value.ok_or_else(|| {
                     error(
-                        &format!("Invalid integer {}", quote(&v_tok.text)),
-                        v_tok.line,
-                        v_tok.column,
+                        &format!(
+                            "Implicit value of {} overflows: the previous value is {}",
+                            quote(&f_tok.text),
+                            i32::MAX
+                        ),
+                        f_tok.line,
+                        f_tok.column,
                     )
                 })?
             } else {
@@ -132,14 +279,22 @@ pub fn parse_schema(tokens: &[Token]) -> Result<Schema, KiwiError> {
             // Deprecated?
             if eat(tokens, &mut index, &DEPRECATED_TOKEN) {
                 if kind != DefinitionKind::Message {
-                    let deprecated = current_token(tokens, index - 1);
-                    return Err(error("Cannot deprecate this field", deprecated.line, deprecated.column));
+                    return Err(error(
+                        &format!(
+                            "Cannot deprecate field {}: only message fields can be deprecated",
+                            quote(&f_tok.text)
+                        ),
+                        f_tok.line,
+                        f_tok.column,
+                    ));
                 }
                 is_deprecated = true;
             }
 
             expect(tokens, &mut index, &SEMICOLON, "\";\"")?;
 
+            next_enum_value = value.checked_add(1);
+
             let final_value = if kind != DefinitionKind::Struct {
                 value
             } else {
@@ -150,8 +305,10 @@ pub fn parse_schema(tokens: &[Token]) -> Result<Schema, KiwiError> {
                 name:           f_tok.text.clone(),
                 line:           f_tok.line,
                 column:         f_tok.column,
+                span:           span_between(&tokens[field_start], &tokens[index - 1]),
                 type_:          type_opt.clone(),
-                is_array,
+                array_depth,
+                key_type,
                 is_deprecated,
                 reserved_index: final_value,
             });
@@ -161,13 +318,18 @@ pub fn parse_schema(tokens: &[Token]) -> Result<Schema, KiwiError> {
             name:    name_tok.text.clone(),
             line:    name_tok.line,
             column:  name_tok.column,
+            span:    span_between(&tokens[def_start], &tokens[index - 1]),
             kind,
             fields,
+            reserved_ranges,
+            binary_index: None,
         });
     }
 
     Ok(Schema {
         package:    package_text,
+        imports,
         definitions,
+        comments:   Vec::new(),
     })
 }
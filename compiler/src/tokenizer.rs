@@ -4,7 +4,7 @@ use crate::utils::{quote, error};
 use crate::error::KiwiError;
 
 lazy_static! {
-    pub static ref TOKEN_REGEX:    Regex = Regex::new(r"((?:-|\b)\d+\b|[=;{}]|\[\]|\[deprecated\]|\b[A-Za-z_][A-Za-z0-9_]*\b|//.*|\s+)").unwrap();
+    pub static ref TOKEN_REGEX:    Regex = Regex::new(r#"((?:-|\b)(?:0[xX][0-9A-Fa-f]+|\d+)\b|[=;{},.<>]|\[\]|\[deprecated\]|"[^"\n]*"|\b[\p{XID_Start}_]\p{XID_Continue}*\b|//.*|\s+)"#).unwrap();
     pub static ref WHITESPACE_RX:  Regex = Regex::new(r"^(//.*|\s+)$").unwrap();
 }
 
@@ -17,7 +17,14 @@ pub struct Token {
 
 /// Now returns `Result<Vec<Token>, KiwiError>`.
 pub fn tokenize_schema(text: &str) -> Result<Vec<Token>, KiwiError> {
+    tokenize_schema_with_comments(text).map(|(tokens, _)| tokens)
+}
+
+/// Like [`tokenize_schema`], but also returns the `//` comments it skipped,
+/// each as a token holding the full comment text.
+pub fn tokenize_schema_with_comments(text: &str) -> Result<(Vec<Token>, Vec<Token>), KiwiError> {
     let mut tokens = Vec::new();
+    let mut comments = Vec::new();
     let mut line = 1;
     let mut column = 1;
     let mut last_end = 0;
@@ -37,7 +44,13 @@ pub fn tokenize_schema(text: &str) -> Result<Vec<Token>, KiwiError> {
             ));
         }
 
-        if !WHITESPACE_RX.is_match(part) && !part.starts_with("//") {
+        if part.starts_with("//") {
+            comments.push(Token {
+                text:   part.to_string(),
+                line,
+                column,
+            });
+        } else if !WHITESPACE_RX.is_match(part) {
             tokens.push(Token {
                 text:   part.to_string(),
                 line,
@@ -45,15 +58,16 @@ pub fn tokenize_schema(text: &str) -> Result<Vec<Token>, KiwiError> {
             });
         }
 
-        // Update line/column
+        // Update line/column, counting columns in characters so positions
+        // stay right after non-ASCII identifiers or comments.
         let newline_count = part.matches('\n').count();
         if newline_count > 0 {
             line += newline_count;
-            if let Some(last_line_part) = part.split('\n').last() {
-                column = last_line_part.len() + 1;
+            if let Some(last_line_part) = part.split('\n').next_back() {
+                column = last_line_part.chars().count() + 1;
             }
         } else {
-            column += part.len();
+            column += part.chars().count();
         }
 
         last_end = end;
@@ -74,7 +88,7 @@ pub fn tokenize_schema(text: &str) -> Result<Vec<Token>, KiwiError> {
         line,
         column,
     });
-    Ok(tokens)
+    Ok((tokens, comments))
 }
 
 #[cfg(test)]
@@ -119,6 +133,48 @@ mod tests {
         assert_eq!(got, expected);
     }
 
+    #[test]
+    fn test_tokenize_hex_integer() {
+        let input = "A = 0xFF;";
+        let expected = vec![
+            Token { text: "A".into(),    line: 1, column: 1 },
+            Token { text: "=".into(),    line: 1, column: 3 },
+            Token { text: "0xFF".into(), line: 1, column: 5 },
+            Token { text: ";".into(),    line: 1, column: 9 },
+            Token { text: "".into(),     line: 1, column: 10 },
+        ];
+        let got = tokenize_schema(input).unwrap();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_tokenize_unicode_identifiers() {
+        let input = "uint größe = 1; // ü\nstring 名前 = 2;";
+        let expected = vec![
+            Token { text: "uint".into(),   line: 1, column: 1 },
+            Token { text: "größe".into(),  line: 1, column: 6 },
+            Token { text: "=".into(),      line: 1, column: 12 },
+            Token { text: "1".into(),      line: 1, column: 14 },
+            Token { text: ";".into(),      line: 1, column: 15 },
+            Token { text: "string".into(), line: 2, column: 1 },
+            Token { text: "名前".into(),   line: 2, column: 8 },
+            Token { text: "=".into(),      line: 2, column: 11 },
+            Token { text: "2".into(),      line: 2, column: 13 },
+            Token { text: ";".into(),      line: 2, column: 14 },
+            Token { text: "".into(),       line: 2, column: 15 },
+        ];
+        let got = tokenize_schema(input).unwrap();
+        assert_eq!(got, expected);
+
+        // Symbols are still not identifiers.
+        let err = tokenize_schema("uint a€ = 1;").unwrap_err();
+        assert!(
+            matches!(err, KiwiError::ParseError { ref msg, line: 1, column: 7 } if msg == "Syntax error: \"€\""),
+            "unexpected error: {:?}",
+            err
+        );
+    }
+
     #[test]
     fn test_tokenize_unexpected_text() {
         let input = "int x = 10 @";
@@ -129,4 +185,37 @@ mod tests {
             err
         );
     }
+
+    #[test]
+    fn test_whitespace_run_is_one_match() {
+        let input = " \t  \t\n\t \r\n  x";
+        let first = TOKEN_REGEX.find(input).unwrap();
+        assert_eq!(first.as_str(), " \t  \t\n\t \r\n  ");
+    }
+
+    #[test]
+    fn test_tokenize_mixed_indentation() {
+        // Tabs and spaces mixed within and across lines, CRLF endings and
+        // comments between runs must not leave untokenized gaps.
+        let input = "message M {\r\n\t  uint a = 1;\r\n  \t\tuint b\t \t= 2; // note\t\r\n \t \n}\t \n";
+        let got = tokenize_schema(input).unwrap();
+        let expected = vec![
+            Token { text: "message".into(), line: 1, column: 1 },
+            Token { text: "M".into(),       line: 1, column: 9 },
+            Token { text: "{".into(),       line: 1, column: 11 },
+            Token { text: "uint".into(),    line: 2, column: 4 },
+            Token { text: "a".into(),       line: 2, column: 9 },
+            Token { text: "=".into(),       line: 2, column: 11 },
+            Token { text: "1".into(),       line: 2, column: 13 },
+            Token { text: ";".into(),       line: 2, column: 14 },
+            Token { text: "uint".into(),    line: 3, column: 5 },
+            Token { text: "b".into(),       line: 3, column: 10 },
+            Token { text: "=".into(),       line: 3, column: 14 },
+            Token { text: "2".into(),       line: 3, column: 16 },
+            Token { text: ";".into(),       line: 3, column: 17 },
+            Token { text: "}".into(),       line: 5, column: 1 },
+            Token { text: "".into(),        line: 6, column: 1 },
+        ];
+        assert_eq!(got, expected);
+    }
 }
@@ -7,3 +7,82 @@ pub trait FromKiwi: Sized {
     fn from_kiwi(value: &Value) -> Result<Self, KiwiError>;
 }
 
+/// Decodes a top-level `Value::Array` by converting each element, so a root
+/// type that is logically a list needs no hand-written loop.
+impl<T: FromKiwi> FromKiwi for Vec<T> {
+    fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {
+        value
+            .as_array_opt()
+            .ok_or_else(|| KiwiError::DecodeError("expected an array".to_string()))?
+            .iter()
+            .map(T::from_kiwi)
+            .collect()
+    }
+}
+
+/// The inverse of `FromKiwi`: converts a generated type back into a dynamic
+/// `Value` that can be encoded with the schema it was generated from.
+/// Generated code only uses `'static` names, hence the `'static` lifetime.
+pub trait ToKiwi {
+    fn to_kiwi(&self) -> Value<'static>;
+}
+
+/// `KiwiError`-returning accessors for `Value`, for hand-written `from_kiwi`
+/// code that wants to use `?` and report the same errors as generated code.
+/// (`KiwiError` lives in this crate, so these can't be inherent methods.)
+pub trait ValueExt<'a> {
+    /// Like `Value::get`, but returns `KiwiError::MissingField(name)` when the
+    /// field is absent or the value is not an object.
+    fn try_get(&self, name: &str) -> Result<&Value<'a>, KiwiError>;
+}
+
+impl<'a> ValueExt<'a> for Value<'a> {
+    fn try_get(&self, name: &str) -> Result<&Value<'a>, KiwiError> {
+        self.get(name)
+            .ok_or_else(|| KiwiError::MissingField(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Id(u32);
+
+    impl FromKiwi for Id {
+        fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {
+            value.as_uint_opt().map(Id).ok_or_else(|| KiwiError::DecodeError("expected a uint".to_string()))
+        }
+    }
+
+    #[test]
+    fn vec_from_kiwi_converts_each_element() {
+        let ids = Vec::<Id>::from_kiwi(&Value::Array(vec![Value::UInt(1), Value::UInt(2)])).unwrap();
+        assert_eq!(ids.iter().map(|id| id.0).collect::<Vec<_>>(), [1, 2]);
+
+        assert!(Vec::<Id>::from_kiwi(&Value::Array(vec![])).unwrap().is_empty());
+        assert!(matches!(
+            Vec::<Id>::from_kiwi(&Value::UInt(1)),
+            Err(KiwiError::DecodeError(ref msg)) if msg == "expected an array"
+        ));
+        assert!(Vec::<Id>::from_kiwi(&Value::Array(vec![Value::Bool(true)])).is_err());
+    }
+
+    #[test]
+    fn try_get_reports_missing_field() {
+        let mut fields = HashMap::new();
+        fields.insert("id", Value::UInt(7));
+        let object = Value::Object("Example", fields);
+
+        assert_eq!(object.try_get("id").unwrap(), &Value::UInt(7));
+        assert!(matches!(
+            object.try_get("name"),
+            Err(KiwiError::MissingField(ref name)) if name == "name"
+        ));
+        assert!(matches!(
+            Value::UInt(7).try_get("id"),
+            Err(KiwiError::MissingField(ref name)) if name == "id"
+        ));
+    }
+}
@@ -1,9 +1,97 @@
+use std::collections::HashSet;
+
 use serde::Serialize;
 
-#[derive(Debug, PartialEq, Serialize)]
+/// Comment tags reported by [`Schema::comment_tags`].
+pub const DEFAULT_COMMENT_TAGS: [&str; 2] = ["TODO", "FIXME"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Schema {
     pub package:    Option<String>,
+    /// Paths from `import "…";` statements, as written. Resolved by
+    /// `compile_schema_files`; not stored in binary.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub imports:    Vec<String>,
     pub definitions: Vec<Definition>,
+    /// `//` comments from the source text as `(line, text)`, with the
+    /// slashes and surrounding whitespace removed. Not stored in binary.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub comments:   Vec<(usize, String)>,
+}
+
+impl Schema {
+    /// Returns the names of the definitions, in order.
+    pub fn definition_names(&self) -> impl Iterator<Item = &str> {
+        self.definitions.iter().map(|d| d.name.as_str())
+    }
+
+    /// Returns the definition named `name`, if there is one.
+    pub fn find_definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|d| d.name == name)
+    }
+
+    /// Returns the type id `encode_binary_schema` gives the definition named
+    /// `name`: its position in `definitions`.
+    pub fn type_id_of(&self, name: &str) -> Option<i32> {
+        self.definitions.iter().position(|d| d.name == name).map(|i| i as i32)
+    }
+
+    /// Returns the comments starting with `TODO` or `FIXME`, as
+    /// `(line, text)`.
+    pub fn comment_tags(&self) -> Vec<(usize, String)> {
+        self.comment_tags_matching(&DEFAULT_COMMENT_TAGS)
+    }
+
+    /// Returns the comments starting with any of `tags`, as `(line, text)`.
+    pub fn comment_tags_matching(&self, tags: &[&str]) -> Vec<(usize, String)> {
+        self.comments
+            .iter()
+            .filter(|(_, text)| tags.iter().any(|tag| text.starts_with(tag)))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a copy of this schema holding only `roots` and the definitions
+    /// they reference through their fields, transitively. Definitions keep
+    /// their relative order, so type ids are renumbered consistently when the
+    /// result is encoded. Root names that aren't defined are ignored.
+    pub fn prune_to(&self, roots: &[&str]) -> Schema {
+        let mut keep: HashSet<&str> = HashSet::new();
+        let mut pending: Vec<&str> = roots.to_vec();
+
+        while let Some(name) = pending.pop() {
+            let def = match self.find_definition(name) {
+                Some(def) => def,
+                None => continue,
+            };
+            if !keep.insert(&def.name) {
+                continue;
+            }
+            for field in &def.fields {
+                pending.extend(field.type_.as_deref());
+                pending.extend(field.key_type.as_deref());
+            }
+        }
+
+        let definitions = self
+            .definitions
+            .iter()
+            .filter(|d| keep.contains(d.name.as_str()))
+            .enumerate()
+            .map(|(i, d)| Definition {
+                // Positions shift once definitions are dropped.
+                binary_index: d.binary_index.map(|_| i),
+                ..d.clone()
+            })
+            .collect();
+
+        Schema {
+            package: self.package.clone(),
+            imports: self.imports.clone(),
+            definitions,
+            comments: self.comments.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -13,22 +101,80 @@ pub enum DefinitionKind {
     Message = 2,
 }
 
+/// The text a declaration covers, from the start of its first token to just
+/// past its last (`end_column` is exclusive). Lines and columns count from 1,
+/// like `line` and `column`; all zero when decoded from binary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start_line:   usize,
+    pub start_column: usize,
+    pub end_line:     usize,
+    pub end_column:   usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Field {
     pub name:           String,
     pub line:           usize,
     pub column:         usize,
+    /// The whole declaration, from its type (or name, for enum variants) to
+    /// the `;`.
+    pub span:           Span,
     pub type_:          Option<String>,
-    pub is_array:       bool,
+    /// Number of `[]` after the type: 0 for a single value, 2 for `int[][]`.
+    pub array_depth:    u32,
+    /// The key type of a `map<K, V>` field, whose `type_` is then `V`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type:       Option<String>,
     pub is_deprecated:  bool,
     pub reserved_index: i32,
 }
 
+impl Definition {
+    /// Returns true if `id` falls in one of the `reserved` ranges.
+    pub fn is_reserved(&self, id: i32) -> bool {
+        self.reserved_ranges.iter().any(|&(start, end)| (start..=end).contains(&id))
+    }
+}
+
+impl Field {
+    /// Returns true if this field is an array, of any depth.
+    pub fn is_array(&self) -> bool {
+        self.array_depth > 0
+    }
+
+    /// Returns true if this field is a `map<K, V>`.
+    pub fn is_map(&self) -> bool {
+        self.key_type.is_some()
+    }
+
+    /// The field's type as written in a schema: `int[][]` or
+    /// `map<string, int>`, and `?` for enum variants, which have none.
+    pub fn type_text(&self) -> String {
+        let value = self.type_.as_deref().unwrap_or("?");
+        match self.key_type {
+            Some(ref key) => format!("map<{}, {}>", key, value),
+            None => format!("{}{}", value, "[]".repeat(self.array_depth as usize)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Definition {
     pub name:    String,
     pub line:    usize,
     pub column:  usize,
+    /// The whole declaration, from the `enum`, `struct` or `message` keyword
+    /// to the closing `}`.
+    pub span:    Span,
     pub kind:    DefinitionKind,
     pub fields:  Vec<Field>,
+    /// Message ids declared with `reserved`, which fields may not use, as
+    /// inclusive `(start, end)` ranges in source order (a lone id is
+    /// `(id, id)`). Not stored in the binary format, so always empty when
+    /// decoded.
+    pub reserved_ranges: Vec<(i32, i32)>,
+    /// Position of this definition in a decoded binary schema (the index that
+    /// field type references point at). `None` when parsed from text.
+    pub binary_index: Option<usize>,
 }
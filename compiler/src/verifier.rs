@@ -1,13 +1,13 @@
 use std::collections::HashMap;
 use crate::{
-    types::{Schema, Definition, DefinitionKind},
+    types::{Schema, Definition, DefinitionKind, Field},
     utils::quote,
     error::KiwiError,
 };
 
 pub const RESERVED_NAMES: [&str; 2] = ["ByteBuffer", "package"];
-pub const NATIVE_TYPES: [&str; 8] = [
-    "bool", "byte", "int", "uint", "float", "string", "int64", "uint64",
+pub const NATIVE_TYPES: [&str; 9] = [
+    "bool", "byte", "int", "uint", "float", "string", "int64", "uint64", "float64",
 ];
 
 /// Returns `Ok(())` if verification passed, or `Err(KiwiError::VerifierError(_))` otherwise.
@@ -15,7 +15,8 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
     let mut defined_types: Vec<String> = NATIVE_TYPES.iter().map(|s| s.to_string()).collect();
     let mut definitions_map: HashMap<String, &Definition> = HashMap::new();
 
-    // 1) Check duplicate / reserved type names
+    // 1) Check duplicate / reserved type names, and duplicate field names
+    //    within each definition
     for def in &schema.definitions {
         if defined_types.contains(&def.name) {
             return Err(KiwiError::VerifierError(format!(
@@ -31,9 +32,66 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
         }
         defined_types.push(def.name.clone());
         definitions_map.insert(def.name.clone(), def);
+
+        for (i, field) in def.fields.iter().enumerate() {
+            if let Some(first) = def.fields[..i].iter().find(|f| f.name == field.name) {
+                return Err(KiwiError::VerifierError(format!(
+                    "The {} {} (line {}, column {}) in {} is already defined at line {}, column {}",
+                    if def.kind == DefinitionKind::Enum { "variant" } else { "field" },
+                    quote(&field.name),
+                    field.line,
+                    field.column,
+                    quote(&def.name),
+                    first.line,
+                    first.column
+                )));
+            }
+        }
+    }
+
+    // 2) Check that enums have variants and that their values are
+    //    non-negative and unique (they may be implicit in the IDL).
+    for def in &schema.definitions {
+        if def.kind != DefinitionKind::Enum {
+            continue;
+        }
+        if def.fields.is_empty() {
+            return Err(KiwiError::VerifierError(format!(
+                "The enum {} (line {}, column {}) has no variants",
+                quote(&def.name),
+                def.line,
+                def.column
+            )));
+        }
+        let mut seen: Vec<&Field> = Vec::new();
+        for field in &def.fields {
+            if field.reserved_index < 0 {
+                return Err(KiwiError::VerifierError(format!(
+                    "The value {} for enum variant {} (line {}, column {}) in {} is negative",
+                    field.reserved_index,
+                    quote(&field.name),
+                    field.line,
+                    field.column,
+                    quote(&def.name)
+                )));
+            }
+            if let Some(first) = seen.iter().find(|f| f.reserved_index == field.reserved_index) {
+                return Err(KiwiError::VerifierError(format!(
+                    "The value {} for enum variant {} (line {}, column {}) is already used by {} (line {}, column {})",
+                    field.reserved_index,
+                    quote(&field.name),
+                    field.line,
+                    field.column,
+                    quote(&first.name),
+                    first.line,
+                    first.column
+                )));
+            }
+            seen.push(field);
+        }
     }
 
-    // 2) Check fields inside each non‐enum definition
+    // 3) Check fields inside each non‐enum definition
     for def in &schema.definitions {
         if let DefinitionKind::Enum = def.kind {
             continue;
@@ -53,6 +111,17 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
                     )));
                 }
             }
+            if let Some(ref key) = field.key_type {
+                check_map_key(field, key, &definitions_map)?;
+            }
+            // Higher depths would collide with the map flag in binary.
+            if field.array_depth >= brine_kiwi_schema::MAP_FIELD_FLAG {
+                return Err(KiwiError::VerifierError(format!(
+                    "Field {} nests arrays more than {} deep",
+                    quote(&field.name),
+                    brine_kiwi_schema::MAP_FIELD_FLAG - 1
+                )));
+            }
         }
 
         // Check reserved_index uniqueness and bounds
@@ -70,7 +139,16 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
                     quote(&field.name)
                 )));
             }
-            if field.reserved_index > def.fields.len() as i32 {
+            if def.is_reserved(field.reserved_index) {
+                return Err(KiwiError::VerifierError(format!(
+                    "The id {} for field {} is reserved",
+                    field.reserved_index,
+                    quote(&field.name)
+                )));
+            }
+            // Message ids may be sparse so old ids can stay reserved; struct
+            // fields are positional, so their ids must stay within range.
+            if def.kind == DefinitionKind::Struct && field.reserved_index > def.fields.len() as i32 {
                 return Err(KiwiError::VerifierError(format!(
                     "The id for field {} cannot be larger than {}",
                     quote(&field.name),
@@ -81,7 +159,9 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
         }
     }
 
-    // 3) Check that structs do not contain themselves recursively
+    // 4) Check that structs do not contain themselves recursively. Only
+    //    non-array struct links count: arrays, maps and messages can be
+    //    empty, so a cycle through any of them still terminates.
     let mut state: HashMap<String, u8> = HashMap::new();
     fn check_recursion(
         name: &str,
@@ -105,7 +185,7 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
             }
             state.insert(name.to_string(), 1);
             for field in &definition.fields {
-                if !field.is_array {
+                if !field.is_array() && !field.is_map() {
                     if let Some(ref ty) = field.type_ {
                         check_recursion(ty, definitions_map, state)?;
                     }
@@ -122,3 +202,28 @@ pub fn verify_schema(schema: &Schema) -> Result<(), KiwiError> {
 
     Ok(())
 }
+
+/// Map keys must be scalars that can be hashed: a native type other than the
+/// floats, or an enum.
+fn check_map_key(field: &Field, key: &str, definitions_map: &HashMap<String, &Definition>) -> Result<(), KiwiError> {
+    let allowed = match definitions_map.get(key) {
+        Some(def) => def.kind == DefinitionKind::Enum,
+        None if NATIVE_TYPES.contains(&key) => key != "float" && key != "float64",
+        None => {
+            return Err(KiwiError::VerifierError(format!(
+                "The type {} is not defined for field {}",
+                quote(key),
+                quote(&field.name)
+            )))
+        }
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(KiwiError::VerifierError(format!(
+            "The type {} cannot be a map key for field {}; keys must be enums or non-float native types",
+            quote(key),
+            quote(&field.name)
+        )))
+    }
+}
@@ -1,9 +1,20 @@
 #![cfg(test)]
 
 use brine_kiwi_compiler::{
+    check_compatibility,
+    compile_schema,
+    compile_schema_files,
+    compile_schema_with_resolver,
+    decode_binary_schema,
+    diff_schemas,
+    encode_binary_schema,
+    schema_to_kiwi_text,
+    SchemaCache,
+    error::KiwiError,
     parser::parse_schema,
     tokenizer::tokenize_schema,
-    types::DefinitionKind,
+    types::{Definition, DefinitionKind, Field, Span},
+    verifier::verify_schema,
 };
 
 #[test]
@@ -57,19 +68,19 @@ fn test_parse_schema() {
     assert_eq!(color_def.fields.len(), 4);
     assert_eq!(color_def.fields[0].name, "red");
     assert_eq!(color_def.fields[0].type_.as_ref().unwrap(), "byte");
-    assert_eq!(color_def.fields[0].is_array, false);
+    assert!(!color_def.fields[0].is_array());
     assert_eq!(color_def.fields[0].reserved_index, 1);
     assert_eq!(color_def.fields[1].name, "green");
     assert_eq!(color_def.fields[1].type_.as_ref().unwrap(), "byte");
-    assert_eq!(color_def.fields[1].is_array, false);
+    assert!(!color_def.fields[1].is_array());
     assert_eq!(color_def.fields[1].reserved_index, 2);
     assert_eq!(color_def.fields[2].name, "blue");
     assert_eq!(color_def.fields[2].type_.as_ref().unwrap(), "byte");
-    assert_eq!(color_def.fields[2].is_array, false);
+    assert!(!color_def.fields[2].is_array());
     assert_eq!(color_def.fields[2].reserved_index, 3);
     assert_eq!(color_def.fields[3].name, "alpha");
     assert_eq!(color_def.fields[3].type_.as_ref().unwrap(), "byte");
-    assert_eq!(color_def.fields[3].is_array, false);
+    assert!(!color_def.fields[3].is_array());
     assert_eq!(color_def.fields[3].reserved_index, 4);
 
     // Check message Example
@@ -79,17 +90,908 @@ fn test_parse_schema() {
     assert_eq!(message_def.fields.len(), 3);
     assert_eq!(message_def.fields[0].name, "clientID");
     assert_eq!(message_def.fields[0].type_.as_ref().unwrap(), "uint");
-    assert_eq!(message_def.fields[0].is_array, false);
+    assert!(!message_def.fields[0].is_array());
     assert_eq!(message_def.fields[0].reserved_index, 1);
 
     assert_eq!(message_def.fields[1].name, "type");
     assert_eq!(message_def.fields[1].type_.as_ref().unwrap(), "Type");
-    assert_eq!(message_def.fields[1].is_array, false);
+    assert!(!message_def.fields[1].is_array());
     assert_eq!(message_def.fields[1].reserved_index, 2);
 
     assert_eq!(message_def.fields[2].name, "colors");
     assert_eq!(message_def.fields[2].type_.as_ref().unwrap(), "Color");
-    assert_eq!(message_def.fields[2].is_array, true);
+    assert!(message_def.fields[2].is_array());
     assert_eq!(message_def.fields[2].reserved_index, 3);
 
 }
+
+#[test]
+fn test_parse_enum_implicit_values() {
+    let input = r#"
+    enum Color {
+      RED;
+      GREEN;
+      BLUE = 10;
+      ALPHA;
+      NONE = 3;
+      OTHER;
+    }
+    "#;
+
+    let tokens = tokenize_schema(input).expect("tokenize_schema failed");
+    let schema = parse_schema(&tokens).expect("parse_schema failed");
+
+    let values: Vec<(&str, i32)> = schema.definitions[0]
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.reserved_index))
+        .collect();
+    assert_eq!(
+        values,
+        vec![("RED", 0), ("GREEN", 1), ("BLUE", 10), ("ALPHA", 11), ("NONE", 3), ("OTHER", 4)]
+    );
+    assert!(compile_schema(input).is_ok());
+}
+
+#[test]
+fn test_parse_enum_implicit_value_overflow() {
+    let input = "enum E {\n  A = 2147483647;\n  B;\n}\n";
+
+    let tokens = tokenize_schema(input).expect("tokenize_schema failed");
+    let err = parse_schema(&tokens).unwrap_err();
+    assert!(
+        matches!(err, KiwiError::ParseError { ref msg, line: 3, column: 3 } if msg.contains("\"B\"")),
+        "expected a ParseError at B but got {:?}",
+        err
+    );
+    assert!(compile_schema("enum E { A = 2147483646; B; }").is_ok());
+}
+
+#[test]
+fn test_verify_duplicate_implicit_enum_value() {
+    let input = r#"
+    enum Color {
+      RED = 1;
+      GREEN = 0;
+      BLUE;
+    }
+    "#;
+
+    let err = compile_schema(input).unwrap_err();
+    assert!(
+        matches!(err, KiwiError::VerifierError(ref msg) if msg.contains("\"BLUE\"")),
+        "expected a VerifierError for BLUE but got {:?}",
+        err
+    );
+}
+
+#[test]
+fn test_verify_duplicate_enum_value_names_both_variants() {
+    let input = "enum Kind {\n  FOO = 1;\n  BAR = 1;\n}\n";
+
+    let err = compile_schema(input).unwrap_err();
+    match err {
+        KiwiError::VerifierError(msg) => assert_eq!(
+            msg,
+            "The value 1 for enum variant \"BAR\" (line 3, column 3) is already used by \"FOO\" (line 2, column 3)"
+        ),
+        other => panic!("expected a VerifierError but got {:?}", other),
+    }
+}
+
+#[test]
+fn test_verify_rejects_empty_enum() {
+    let err = compile_schema("message M { int x = 1; }\nenum Kind {}\n").unwrap_err();
+    match err {
+        KiwiError::VerifierError(msg) => assert_eq!(msg, "The enum \"Kind\" (line 2, column 6) has no variants"),
+        other => panic!("expected a VerifierError but got {:?}", other),
+    }
+
+    // Empty structs and messages are still fine.
+    compile_schema("struct S {}\nmessage M {}\n").expect("compile_schema failed");
+}
+
+#[test]
+fn test_verify_rejects_duplicate_field_names() {
+    let err = compile_schema("struct Point {\n  int x;\n  int x;\n}\n").unwrap_err();
+    match err {
+        KiwiError::VerifierError(msg) => {
+            assert_eq!(msg, "The field \"x\" (line 3, column 7) in \"Point\" is already defined at line 2, column 7")
+        }
+        other => panic!("expected a VerifierError but got {:?}", other),
+    }
+
+    let err = compile_schema("message M { int a = 1; string a = 2; }").unwrap_err();
+    assert!(matches!(err, KiwiError::VerifierError(ref msg) if msg.starts_with("The field \"a\" (line 1, column 31)")));
+    let err = compile_schema("enum E { A; B; A; }").unwrap_err();
+    assert!(matches!(err, KiwiError::VerifierError(ref msg) if msg.starts_with("The variant \"A\" (line 1, column 16)")));
+
+    // The same name in different definitions is fine.
+    compile_schema("struct A { int x; }\nstruct B { int x; }\n").expect("compile_schema failed");
+}
+
+#[test]
+fn test_parse_declaration_spans() {
+    let input = "message Example {\n  uint clientID = 1;\n  map<string, int> counts = 2 [deprecated];\n}\nenum E { A; }";
+    let schema = parse_schema(&tokenize_schema(input).unwrap()).unwrap();
+    let span = |start_line, start_column, end_line, end_column| Span { start_line, start_column, end_line, end_column };
+
+    let example = &schema.definitions[0];
+    assert_eq!(example.span, span(1, 1, 4, 2));
+    assert_eq!(example.fields[0].span, span(2, 3, 2, 21));
+    assert_eq!(example.fields[1].span, span(3, 3, 3, 44));
+    assert_eq!(schema.definitions[1].span, span(5, 1, 5, 14));
+    assert_eq!(schema.definitions[1].fields[0].span, span(5, 10, 5, 12));
+
+    // Binary schemas have no source text.
+    let decoded = decode_binary_schema(&encode_binary_schema(&schema).unwrap()).unwrap();
+    assert_eq!(decoded.definitions[0].span, Span::default());
+}
+
+#[test]
+fn test_parse_rejects_deprecated_struct_field() {
+    let tokens = tokenize_schema("struct Color {\n  byte red [deprecated];\n}").unwrap();
+    let err = parse_schema(&tokens).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            KiwiError::ParseError { ref msg, line: 2, column: 8 }
+                if msg == "Cannot deprecate field \"red\": only message fields can be deprecated"
+        ),
+        "unexpected error: {:?}",
+        err
+    );
+}
+
+#[test]
+fn test_parse_hex_ids() {
+    let input = r#"
+    enum Flags {
+      NONE = 0x0;
+      ALL = 0xFF;
+    }
+
+    message Example {
+      uint id = 0x1;
+    }
+    "#;
+
+    let tokens = tokenize_schema(input).expect("tokenize_schema failed");
+    let schema = parse_schema(&tokens).expect("parse_schema failed");
+    assert_eq!(schema.definitions[0].fields[0].reserved_index, 0);
+    assert_eq!(schema.definitions[0].fields[1].reserved_index, 255);
+    assert_eq!(schema.definitions[1].fields[0].reserved_index, 1);
+}
+
+#[test]
+fn test_verify_negative_enum_value() {
+    let input = "enum Status {\n  ERROR = -1;\n  UNKNOWN;\n  READY = 0x10;\n}";
+
+    match parse_schema(&tokenize_schema(input).unwrap()) {
+        Err(KiwiError::ParseError { msg, line, column }) => {
+            assert_eq!(msg, "Negative id \"-1\" is not allowed");
+            assert_eq!((line, column), (2, 11));
+        }
+        other => panic!("expected a ParseError but got {:?}", other),
+    }
+
+    // Schemas built some other way are still checked.
+    let mut schema = parse_schema(&tokenize_schema("enum Status {\n  ERROR;\n  READY;\n}").unwrap()).unwrap();
+    schema.definitions[0].fields[0].reserved_index = -1;
+    let err = verify_schema(&schema).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Verifier error: The value -1 for enum variant \"ERROR\" (line 2, column 3) in \"Status\" is negative"
+    );
+}
+
+#[test]
+fn test_parse_overflowing_literal_points_at_token() {
+    let input = "enum Flags {\n  A = 0x1;\n  B = 0x100000000;\n}";
+
+    let tokens = tokenize_schema(input).expect("tokenize_schema failed");
+    match parse_schema(&tokens) {
+        Err(KiwiError::ParseError { msg, line, column }) => {
+            assert_eq!(msg, "Invalid integer \"0x100000000\"");
+            assert_eq!((line, column), (3, 7));
+        }
+        other => panic!("expected a ParseError but got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_negative_id_is_rejected() {
+    let input = "message Example {\n  uint id = -1;\n}";
+
+    let tokens = tokenize_schema(input).expect("tokenize_schema failed");
+    match parse_schema(&tokens) {
+        Err(KiwiError::ParseError { line, column, .. }) => {
+            assert_eq!((line, column), (2, 13));
+        }
+        other => panic!("expected a ParseError but got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_dotted_package() {
+    let input = "package game.net;\nmessage Ping { uint id = 1; }";
+
+    let tokens = tokenize_schema(input).expect("tokenize_schema failed");
+    let schema = parse_schema(&tokens).expect("parse_schema failed");
+    assert_eq!(schema.package.as_deref(), Some("game.net"));
+
+    let tokens = tokenize_schema("package game.;").expect("tokenize_schema failed");
+    assert!(matches!(parse_schema(&tokens), Err(KiwiError::ParseError { .. })));
+}
+
+#[test]
+fn test_schema_comment_tags() {
+    let input = "// TODO: add a timestamp\nmessage Ping {\n  uint id = 1; // FIXME: should be uint64\n  // just a note\n}\n";
+
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    assert_eq!(schema.comments.len(), 3);
+    assert_eq!(
+        schema.comment_tags(),
+        vec![
+            (1, "TODO: add a timestamp".to_string()),
+            (3, "FIXME: should be uint64".to_string()),
+        ]
+    );
+    assert_eq!(schema.comment_tags_matching(&["just"]), vec![(4, "just a note".to_string())]);
+}
+
+#[test]
+fn test_decode_rejects_counts_beyond_buffer() {
+    // Four billion definitions claimed in a six-byte buffer
+    let err = decode_binary_schema(&[255, 255, 255, 255, 15, 0]).unwrap_err();
+    assert!(
+        matches!(err, KiwiError::DecodeError(ref msg) if msg == "Definition count 4294967295 exceeds the 1 bytes left"),
+        "unexpected error: {:?}",
+        err
+    );
+
+    // One message "A" claiming four billion fields
+    let err = decode_binary_schema(&[1, b'A', 0, 2, 255, 255, 255, 255, 15]).unwrap_err();
+    assert!(matches!(err, KiwiError::DecodeError(ref msg) if msg.starts_with("Field count 4294967295")));
+}
+
+#[test]
+fn test_decode_records_binary_index() {
+    let input = r#"
+    enum Type { FLAT = 0; }
+    struct Color { byte red; }
+    message Example { Color color = 1; }
+    "#;
+
+    let (schema, bin) = compile_schema(input).expect("compile_schema failed");
+    assert!(schema.definitions.iter().all(|d| d.binary_index.is_none()));
+
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+    let indices: Vec<(&str, Option<usize>)> = decoded
+        .definitions
+        .iter()
+        .map(|d| (d.name.as_str(), d.binary_index))
+        .collect();
+    assert_eq!(
+        indices,
+        vec![("Type", Some(0)), ("Color", Some(1)), ("Example", Some(2))]
+    );
+}
+
+#[test]
+fn test_verify_sparse_message_ids() {
+    let input = r#"
+    message Example {
+      uint id = 1;
+      string name = 2;
+      bool flag = 50;
+    }
+    "#;
+
+    let (_schema, bin) = compile_schema(input).expect("compile_schema failed");
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+    let ids: Vec<i32> = decoded.definitions[0].fields.iter().map(|f| f.reserved_index).collect();
+    assert_eq!(ids, vec![1, 2, 50]);
+
+    // Struct fields are positional, so the bound still applies to them.
+    let mut schema = parse_schema(&tokenize_schema("struct Example { uint id; bool flag; }").unwrap())
+        .expect("parse_schema failed");
+    schema.definitions[0].fields[1].reserved_index = 50;
+
+    let err = verify_schema(&schema).unwrap_err();
+    assert!(
+        matches!(err, KiwiError::VerifierError(ref msg) if msg.contains("cannot be larger than")),
+        "expected a VerifierError but got {:?}",
+        err
+    );
+}
+
+#[test]
+fn test_verify_struct_recursion_boundary() {
+    // A cycle made only of struct links has no framing and can never end.
+    let input = r#"
+    struct A { B b; }
+    struct B { A a; }
+    "#;
+    let err = compile_schema(input).unwrap_err();
+    assert!(
+        matches!(err, KiwiError::VerifierError(ref msg) if msg.contains("Recursive nesting")),
+        "expected a VerifierError but got {:?}",
+        err
+    );
+
+    // A message in the loop has optional, framed fields, so the cycle is fine.
+    let input = r#"
+    struct A { M m; }
+    message M { A a = 1; }
+    "#;
+    assert!(compile_schema(input).is_ok());
+}
+
+#[test]
+fn test_binary_schema_round_trips_package() {
+    let (schema, bin) = compile_schema("package game.net;\nmessage Ping { uint id = 1; }").unwrap();
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+    assert_eq!(decoded.package.as_deref(), Some("game.net"));
+    assert_eq!(encode_binary_schema(&decoded).unwrap(), bin);
+
+    // The package trails the definitions, so binaries without one still
+    // decode, and the runtime decoder skips it.
+    let (_, plain) = compile_schema("message Ping { uint id = 1; }").unwrap();
+    assert_eq!(bin, [plain.as_slice(), b"game.net\0"].concat());
+    assert_eq!(decode_binary_schema(&plain).unwrap().package, None);
+    assert_eq!(brine_kiwi_schema::Schema::decode(&bin), brine_kiwi_schema::Schema::decode(&plain));
+    assert_eq!(schema.package, decoded.package);
+}
+
+#[test]
+fn test_parse_package_after_definitions() {
+    let err = compile_schema("enum X {}\n  package foo;\n").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            KiwiError::ParseError { ref msg, line: 2, column: 3 }
+                if msg == "package declaration must be the first statement"
+        ),
+        "unexpected error: {:?}",
+        err
+    );
+
+    // A second declaration is just as misplaced.
+    let err = compile_schema("package a;\npackage b;\n").unwrap_err();
+    assert!(matches!(err, KiwiError::ParseError { line: 2, column: 1, .. }));
+}
+
+#[test]
+fn test_parse_reserved_ids() {
+    let input = r#"
+    message Example {
+      reserved 2, 5 to 7;
+      uint id = 1;
+      string name = 3;
+    }
+    "#;
+
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    assert_eq!(schema.definitions[0].reserved_ranges, vec![(2, 2), (5, 7)]);
+    assert!(schema.definitions[0].is_reserved(6));
+    assert!(!schema.definitions[0].is_reserved(4));
+    assert_eq!(schema.definitions[0].fields.len(), 2);
+
+    let input = r#"
+    message Example {
+      reserved 2, 5 to 7;
+      uint id = 6;
+    }
+    "#;
+
+    let err = compile_schema(input).unwrap_err();
+    assert!(
+        matches!(err, KiwiError::VerifierError(ref msg) if msg.contains("is reserved")),
+        "expected a VerifierError but got {:?}",
+        err
+    );
+}
+
+#[test]
+fn test_parse_reserved_dot_ranges() {
+    let input = r#"
+    message Example {
+      reserved 2, 5..7;
+      reserved 10 .. 11;
+      uint id = 1;
+    }
+    "#;
+
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    assert_eq!(schema.definitions[0].reserved_ranges, vec![(2, 2), (5, 7), (10, 11)]);
+
+    let err = compile_schema("message Example { reserved 9..7; }").unwrap_err();
+    assert!(
+        matches!(err, KiwiError::ParseError { ref msg, .. } if msg == "Invalid reserved range 9 to 7"),
+        "unexpected error: {:?}",
+        err
+    );
+
+    let err = compile_schema("message Example { reserved 5..7; uint id = 7; }").unwrap_err();
+    assert!(matches!(err, KiwiError::VerifierError(ref msg) if msg.contains("is reserved")));
+
+    // Ranges are kept as ranges, however wide.
+    let (schema, _) = compile_schema("message Example { reserved 1 to 2147483647; }").expect("compile_schema failed");
+    assert_eq!(schema.definitions[0].reserved_ranges, vec![(1, 2147483647)]);
+    assert_eq!(schema_to_kiwi_text(&schema), "message Example {\n  reserved 1 to 2147483647;\n}\n");
+}
+
+#[test]
+fn test_binary_round_trip_preserves_definitions() {
+    let input = include_str!("../../example/simple.kiwi");
+
+    let (schema, bin) = compile_schema(input).expect("compile_schema failed");
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+
+    // Positions, deprecation and reserved ids are not part of the binary
+    // format, so only compare what it does store.
+    fn stored(definitions: &[Definition]) -> Vec<Definition> {
+        definitions
+            .iter()
+            .map(|d| Definition {
+                line:         0,
+                column:       0,
+                span:         Span::default(),
+                binary_index: None,
+                reserved_ranges: Vec::new(),
+                fields:       d
+                    .fields
+                    .iter()
+                    .map(|f| Field { line: 0, column: 0, span: Span::default(), is_deprecated: false, ..f.clone() })
+                    .collect(),
+                ..d.clone()
+            })
+            .collect()
+    }
+
+    assert_eq!(stored(&decoded.definitions), stored(&schema.definitions));
+    assert_eq!(encode_binary_schema(&decoded).expect("encode_binary_schema failed"), bin);
+}
+
+#[test]
+fn test_schema_cache_reuses_compiled_schemas() {
+    let base = include_str!("../../example/simple.kiwi");
+    let mut cache = SchemaCache::new();
+
+    let first = cache.compile(base).expect("compile failed").1.to_vec();
+    let (schema, bin) = cache.compile(base).expect("compile failed");
+    assert_eq!(bin, &first[..]);
+    assert_eq!(schema.definitions.len(), 3);
+    assert_eq!(cache.len(), 1);
+
+    cache.compile("message Other { uint id = 1; }").expect("compile failed");
+    assert_eq!(cache.len(), 2);
+
+    assert!(cache.compile("message Broken {").is_err());
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn test_schema_cache_parses_shared_imports_once() {
+    let base = "enum Type { A; B; }\nstruct Color { byte red; }\n";
+    let mut reads = 0;
+    let mut cache = SchemaCache::new();
+    for root in ["import \"base.kiwi\";\nmessage One { Type t = 1; }\n", "import \"base.kiwi\";\nmessage Two { Color c = 1; }\n"] {
+        let (schema, bin) = cache
+            .compile_with_resolver(root, |_| {
+                reads += 1;
+                Ok(base.to_string())
+            })
+            .expect("compile_with_resolver failed");
+        assert_eq!(schema.definitions.len(), 3);
+        assert_eq!((schema, bin), compile_schema_with_resolver(root, |_| Ok(base.to_string())).unwrap());
+    }
+    // The import is still read per root, but parsed only once.
+    assert_eq!(reads, 2);
+    assert_eq!(cache.parsed_len(), 3);
+
+    // Each root is verified with its own imports.
+    let err = cache.compile_with_resolver("import \"base.kiwi\";\nmessage Type { }\n", |_| Ok(base.to_string())).unwrap_err();
+    assert_eq!(err.to_string(), "Verifier error: The type \"Type\" is defined twice");
+    assert!(cache.compile_with_resolver("import \"bad.kiwi\";\n", |_| Ok("enum {".to_string())).is_err());
+    // Both roots parsed, but not the broken import.
+    assert_eq!(cache.parsed_len(), 5);
+
+    let dir = std::env::temp_dir().join("brine_kiwi_cache_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base.kiwi"), base).unwrap();
+    std::fs::write(dir.join("main.kiwi"), "import \"base.kiwi\";\nmessage One { Type t = 1; }\n").unwrap();
+    let cached = cache.compile_files(&dir.join("main.kiwi")).expect("compile_files failed");
+    assert_eq!(cached, compile_schema_files(&dir.join("main.kiwi")).unwrap());
+    assert_eq!(cache.parsed_len(), 5);
+}
+
+#[test]
+fn test_diff_schemas_flags_breaking_changes() {
+    let old = r#"
+    enum Type { FLAT = 0; ROUND = 1; }
+    struct Color { byte red; byte green; }
+    message Example { uint id = 1; Type type = 2; string name = 3; }
+    message Gone { uint id = 1; }
+    "#;
+    let new = r#"
+    enum Type { FLAT = 0; ROUND = 2; POINTED = 3; }
+    struct Color { byte red; byte green; byte blue; }
+    message Example { uint[] id = 1; Type type = 2; string note = 4; }
+    message Added { uint id = 1; }
+    "#;
+    let (old, _) = compile_schema(old).expect("compile_schema failed");
+    let (new, _) = compile_schema(new).expect("compile_schema failed");
+
+    let changes: Vec<(String, String, bool)> = diff_schemas(&old, &new)
+        .into_iter()
+        .map(|c| (c.definition, c.description, c.breaking))
+        .collect();
+    let expected = [
+        ("Type", "variant \"ROUND\" value changed from 1 to 2", true),
+        ("Type", "variant \"POINTED\" added (value 3)", false),
+        ("Color", "field \"blue\" added (byte, id 3)", true),
+        ("Example", "field \"id\" changed from single value to array", true),
+        ("Example", "field \"name\" removed", true),
+        ("Example", "field \"note\" added (string, id 4)", false),
+        ("Gone", "message removed", true),
+        ("Added", "message added", false),
+    ];
+    let expected: Vec<(String, String, bool)> = expected
+        .iter()
+        .map(|(d, c, b)| (d.to_string(), c.to_string(), *b))
+        .collect();
+    assert_eq!(changes, expected);
+
+    assert!(diff_schemas(&old, &old).is_empty());
+}
+
+#[test]
+fn test_check_compatibility() {
+    let old = r#"
+    message Example { uint id = 1; string name = 2; string nick = 3; int score = 4; }
+    "#;
+    let compatible = r#"
+    message Example { uint id = 1; string name = 2 [deprecated]; int score = 4; float ratio = 5; }
+    "#;
+    let (old, _) = compile_schema(old).expect("compile_schema failed");
+    let (compatible, _) = compile_schema(compatible).expect("compile_schema failed");
+    assert_eq!(check_compatibility(&old, &old), Ok(()));
+    // `nick` was never deprecated, so dropping it breaks old writers.
+    let errors = check_compatibility(&old, &compatible).unwrap_err();
+    assert_eq!(
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        vec!["Example: field \"nick\" removed"]
+    );
+
+    let newer = r#"
+    message Example { uint id = 1; int[] score = 5; float ratio = 6; }
+    "#;
+    let (newer, _) = compile_schema(newer).expect("compile_schema failed");
+    let errors = check_compatibility(&compatible, &newer).unwrap_err();
+    let descriptions: Vec<&str> = errors.iter().map(|e| e.description.as_str()).collect();
+    assert_eq!(
+        descriptions,
+        vec![
+            "field \"score\" changed from single value to array",
+            "field \"score\" id changed from 4 to 5",
+            "field \"ratio\" id changed from 5 to 6",
+        ]
+    );
+
+    let typed = r#"
+    message Example { int id = 1; string name = 2 [deprecated]; int score = 4; float ratio = 5; }
+    "#;
+    let (typed, _) = compile_schema(typed).expect("compile_schema failed");
+    let errors = check_compatibility(&compatible, &typed).unwrap_err();
+    assert_eq!(errors[0].description, "field \"id\" type changed from uint to int");
+}
+
+#[test]
+fn test_schema_definition_lookup() {
+    let (_, bin) = compile_schema(include_str!("../../example/simple.kiwi")).expect("compile_schema failed");
+    let schema = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+
+    let names: Vec<&str> = schema.definition_names().collect();
+    assert_eq!(names, schema.definitions.iter().map(|d| d.name.as_str()).collect::<Vec<_>>());
+    assert_eq!(schema.find_definition("Color").map(|d| &d.kind), Some(&DefinitionKind::Struct));
+    assert!(schema.find_definition("Missing").is_none());
+
+    // Type ids match the runtime schema built from the same bytes.
+    let runtime = brine_kiwi_schema::Schema::decode(&bin).expect("Schema::decode failed");
+    for name in names {
+        assert_eq!(schema.type_id_of(name), runtime.def_name_to_index.get(name).map(|&i| i as i32));
+    }
+    assert_eq!(schema.type_id_of("Missing"), None);
+}
+
+#[test]
+fn test_decode_binary_schema_rejects_invalid_utf8() {
+    // "message A { int x = 1; }" with a stray 0xFF in each name in turn.
+    for (bytes, what) in [
+        (vec![1, 65, 0xFF, 0, 2, 1, 120, 0, 5, 0, 1], "definition name"),
+        (vec![1, 65, 0, 2, 1, 120, 0xFF, 0, 5, 0, 1], "field name"),
+        (vec![1, 65, 0, 2, 1, 120, 0, 5, 0, 1, 0xFF, 0], "package name"),
+    ] {
+        let err = decode_binary_schema(&bytes).unwrap_err();
+        assert!(
+            matches!(err, KiwiError::DecodeError(ref msg) if *msg == format!("Invalid UTF-8 in {}", what)),
+            "unexpected error: {:?}",
+            err
+        );
+    }
+    assert!(matches!(
+        decode_binary_schema(&[1, 65, 0xFF]),
+        Err(KiwiError::DecodeError(ref msg)) if msg == "Failed to read definition name: ()"
+    ));
+}
+
+#[test]
+fn test_decode_binary_schema_rejects_huge_array_depth() {
+    // "message A { int x = 1; }" with x nested 63 arrays deep, then 999_936
+    let deepest = decode_binary_schema(&[1, 65, 0, 2, 1, 120, 0, 5, 63, 1]).expect("decode_binary_schema failed");
+    assert_eq!(deepest.definitions[0].fields[0].array_depth, 63);
+    assert!(matches!(
+        decode_binary_schema(&[1, 65, 0, 2, 1, 120, 0, 5, 0x80, 0x84, 0x3D, 1]),
+        Err(KiwiError::DecodeError(ref msg)) if msg == "Field \"x\" has an array depth of 999936, more than 63"
+    ));
+}
+
+#[test]
+fn test_prune_schema_to_roots() {
+    let (schema, _) = compile_schema(include_str!("../../example/simple.kiwi")).expect("compile_schema failed");
+
+    let pruned = schema.prune_to(&["Color"]);
+    let names: Vec<&str> = pruned.definitions.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, vec!["Color"]);
+
+    let pruned = schema.prune_to(&["Example"]);
+    let names: Vec<&str> = pruned.definitions.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, vec!["Type", "Color", "Example"]);
+
+    // Type ids are renumbered when the pruned schema is encoded.
+    let decoded = decode_binary_schema(&encode_binary_schema(&schema).unwrap()).unwrap();
+    let pruned = decoded.prune_to(&["Color", "Missing"]);
+    assert_eq!(pruned.definitions.len(), 1);
+    assert_eq!(pruned.definitions[0].binary_index, Some(0));
+    let bin = encode_binary_schema(&pruned).expect("encode_binary_schema failed");
+    assert_eq!(decode_binary_schema(&bin).unwrap().definitions[0].name, "Color");
+}
+
+#[test]
+fn test_schema_to_kiwi_text() {
+    let input = r#"package game.net;
+enum Type { FLAT; ROUND = 0x10; NEXT; }
+struct Color {byte red;   byte[] tint;}
+message Example {
+    reserved 9, 4, 5 to 6, 5..5;
+  uint clientID = 1;   Type type = 2 [deprecated];
+      Color[] colors = 10;
+}
+enum Empty {}
+"#;
+    let expected = r#"package game.net;
+
+enum Type {
+  FLAT  = 0;
+  ROUND = 16;
+  NEXT  = 17;
+}
+
+struct Color {
+  byte red;
+  byte[] tint;
+}
+
+message Example {
+  reserved 4 to 6, 9;
+  uint clientID  = 1;
+  Type type      = 2 [deprecated];
+  Color[] colors = 10;
+}
+
+enum Empty {
+}
+"#;
+    let tokens = tokenize_schema(input).unwrap();
+    let schema = parse_schema(&tokens).unwrap();
+    let formatted = schema_to_kiwi_text(&schema);
+    assert_eq!(formatted, expected);
+
+    // Formatting is stable and describes the same schema.
+    let reparsed = parse_schema(&tokenize_schema(&formatted).unwrap()).unwrap();
+    assert_eq!(schema_to_kiwi_text(&reparsed), formatted);
+    assert_eq!(encode_binary_schema(&reparsed).unwrap(), encode_binary_schema(&schema).unwrap());
+
+    // `Display` prints the same text, and works on schemas decoded from binary.
+    assert_eq!(schema.to_string(), formatted);
+    let decoded = decode_binary_schema(&encode_binary_schema(&schema).unwrap()).unwrap();
+    let printed = decoded.to_string();
+    assert!(printed.starts_with("package game.net;\n\nenum Type {\n"));
+    assert!(printed.contains("  Color[] colors = 10;\n"));
+}
+
+#[test]
+fn test_nested_arrays() {
+    let text = "struct Grid { int[][] cells; byte[] row; }\n";
+    let (schema, bin) = compile_schema(text).expect("compile_schema failed");
+    let fields = &schema.definitions[0].fields;
+    assert_eq!(fields[0].array_depth, 2);
+    assert_eq!(fields[1].array_depth, 1);
+
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+    assert_eq!(decoded.definitions[0].fields[0].array_depth, 2);
+    assert!(schema_to_kiwi_text(&schema).contains("  int[][] cells;\n"));
+
+    // Depth 1 is written as the single byte an `is_array` bool used to be.
+    let (flat, flat_bin) = compile_schema("struct Row { byte[] row; }").expect("compile_schema failed");
+    assert_eq!(flat_bin, [1, 82, 111, 119, 0, 1, 1, 114, 111, 119, 0, 3, 1, 1]);
+    assert_eq!(flat.definitions[0].fields[0].array_depth, 1);
+}
+
+#[test]
+fn test_import_statements() {
+    let tokens = tokenize_schema("package p;\nimport \"a.kiwi\";\nimport \"sub/b.kiwi\";\nenum E { X; }").unwrap();
+    let schema = parse_schema(&tokens).unwrap();
+    assert_eq!(schema.imports, ["a.kiwi", "sub/b.kiwi"]);
+    assert!(schema_to_kiwi_text(&schema).starts_with("package p;\n\nimport \"a.kiwi\";\nimport \"sub/b.kiwi\";\n\nenum E {"));
+
+    let tokens = tokenize_schema("enum E { X; }\nimport \"a.kiwi\";").unwrap();
+    assert!(matches!(
+        parse_schema(&tokens),
+        Err(KiwiError::ParseError { ref msg, line: 2, column: 1 }) if msg == "import statements must come before definitions"
+    ));
+}
+
+#[test]
+fn test_compile_schema_files() {
+    let dir = std::env::temp_dir().join("brine_kiwi_import_test");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let write = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+    write("main.kiwi", "package game;\nimport \"sub/common.kiwi\";\nimport \"sub/types.kiwi\";\nmessage Example { Color c = 1; Type t = 2; }\n");
+    write("sub/common.kiwi", "import \"types.kiwi\";\nstruct Color { byte red; Type t; }\n");
+    write("sub/types.kiwi", "enum Type { A; }\n");
+
+    // Imports come first, and a file imported twice is only read once.
+    let (schema, bin) = compile_schema_files(&dir.join("main.kiwi")).expect("compile_schema_files failed");
+    let names: Vec<&str> = schema.definitions.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["Type", "Color", "Example"]);
+    assert_eq!(schema.package.as_deref(), Some("game"));
+    assert!(schema.imports.is_empty());
+    assert_eq!(decode_binary_schema(&bin).unwrap().definitions.len(), 3);
+
+    write("sub/types.kiwi", "enum Color { A; }\nenum Type { A; }\n");
+    let err = compile_schema_files(&dir.join("main.kiwi")).unwrap_err();
+    assert_eq!(err.to_string(), "Verifier error: The type \"Color\" is defined twice");
+
+    write("sub/types.kiwi", "import \"../main.kiwi\";\nenum Type { A; }\n");
+    let err = compile_schema_files(&dir.join("main.kiwi")).unwrap_err();
+    let sub = dir.join("sub");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Verifier error: Import cycle: {} -> {} -> {} -> {}",
+            dir.join("main.kiwi").display(),
+            sub.join("common.kiwi").display(),
+            sub.join("types.kiwi").display(),
+            sub.join("../main.kiwi").display()
+        )
+    );
+
+    write("sub/types.kiwi", "enum Type { A B }\n");
+    match compile_schema_files(&dir.join("main.kiwi")).unwrap_err() {
+        KiwiError::Import { path, source } => {
+            assert_eq!(path, dir.join("sub").join("types.kiwi"));
+            assert!(matches!(*source, KiwiError::ParseError { line: 1, column: 15, .. }));
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn test_compile_schema_with_resolver() {
+    use std::collections::HashMap;
+
+    let files = |entries: &[(&str, &str)]| -> HashMap<String, String> {
+        entries.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect()
+    };
+    let compile = |text: &str, files: &HashMap<String, String>| {
+        let mut requested = Vec::new();
+        let result = compile_schema_with_resolver(text, |name| {
+            requested.push(name.to_string());
+            files.get(name).cloned().ok_or_else(|| KiwiError::VerifierError(format!("no file {}", name)))
+        });
+        (result, requested)
+    };
+    let main = "package game;\nimport \"common.kiwi\";\nimport \"types.kiwi\";\nmessage Example { Color c = 1; Type t = 2; }\n";
+
+    let ok = files(&[("common.kiwi", "import \"types.kiwi\";\nstruct Color { byte red; Type t; }\n"), ("types.kiwi", "enum Type { A; }\n")]);
+    let (result, requested) = compile(main, &ok);
+    let (schema, bin) = result.expect("compile_schema_with_resolver failed");
+    let names: Vec<&str> = schema.definitions.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["Type", "Color", "Example"]);
+    assert_eq!(requested, ["common.kiwi", "types.kiwi"]);
+    assert_eq!(schema.package.as_deref(), Some("game"));
+    assert_eq!(decode_binary_schema(&bin).unwrap().definitions.len(), 3);
+
+    let duplicate = files(&[("common.kiwi", "struct Color { byte red; }\n"), ("types.kiwi", "enum Color { A; }\nenum Type { A; }\n")]);
+    let err = compile(main, &duplicate).0.unwrap_err();
+    assert_eq!(err.to_string(), "Verifier error: The type \"Color\" is defined twice");
+
+    let cycle = files(&[("common.kiwi", "import \"types.kiwi\";\nstruct Color { byte red; }\n"), ("types.kiwi", "import \"common.kiwi\";\nenum Type { A; }\n")]);
+    let err = compile(main, &cycle).0.unwrap_err();
+    assert_eq!(err.to_string(), "Verifier error: Import cycle: common.kiwi -> types.kiwi -> common.kiwi");
+
+    match compile(main, &files(&[("types.kiwi", "enum Type { A; }\n")])).0.unwrap_err() {
+        KiwiError::Import { path, source } => {
+            assert_eq!(path, std::path::Path::new("common.kiwi"));
+            assert!(matches!(*source, KiwiError::VerifierError(ref msg) if msg == "no file common.kiwi"));
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn test_runtime_schema_from_compiled_schema() {
+    let text = "package p;\nenum Type { A; B = 0x10; }\nstruct Color { byte red; Type[][] t; }\nmessage M { Color c = 3; string s = 1; }\n";
+    let (schema, bin) = compile_schema(text).expect("compile_schema failed");
+    let runtime = brine_kiwi_schema::Schema::try_from(&schema).expect("conversion failed");
+    assert_eq!(Some(runtime), brine_kiwi_schema::Schema::decode(&bin).ok());
+
+    // An unverified schema may name types that don't exist.
+    let unverified = parse_schema(&tokenize_schema("message M { Missing m = 1; }").unwrap()).unwrap();
+    assert!(matches!(
+        brine_kiwi_schema::Schema::try_from(&unverified),
+        Err(KiwiError::EncodeError(ref msg)) if msg.contains("'Missing'")
+    ));
+}
+
+#[test]
+fn test_float64_native_type() {
+    let (schema, bin) = compile_schema("message Sample { float64 value = 1; }").expect("compile_schema failed");
+    let runtime = brine_kiwi_schema::Schema::decode(&bin).expect("Schema::decode failed");
+    assert_eq!(runtime.defs[0].fields[0].type_id, brine_kiwi_schema::TYPE_FLOAT64);
+    assert_eq!(runtime.type_name(brine_kiwi_schema::TYPE_FLOAT64), Some("float64"));
+
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+    assert_eq!(decoded.definitions[0].fields[0].type_.as_deref(), Some("float64"));
+    assert_eq!(schema.definitions[0].fields[0].type_, decoded.definitions[0].fields[0].type_);
+}
+
+#[test]
+fn test_map_fields() {
+    let text = "enum Kind { A; B; }\nmessage Bag {\n  map<string, int> counts = 1;\n  map<Kind, Bag> children = 2;\n}\n";
+    let (schema, bin) = compile_schema(text).expect("compile_schema failed");
+    let fields = &schema.definitions[1].fields;
+    assert_eq!(fields[0].key_type.as_deref(), Some("string"));
+    assert_eq!(fields[0].type_.as_deref(), Some("int"));
+    assert!(fields[1].is_map());
+
+    let decoded = decode_binary_schema(&bin).expect("decode_binary_schema failed");
+    assert_eq!(decoded.definitions[1].fields, fields.iter().map(|f| Field { line: 0, column: 0, span: Span::default(), ..f.clone() }).collect::<Vec<_>>());
+    let formatted = schema_to_kiwi_text(&schema);
+    assert!(formatted.contains("  map<string, int> counts = 1;\n"), "{}", formatted);
+    assert!(formatted.contains("  map<Kind, Bag> children = 2;\n"), "{}", formatted);
+
+    let runtime = brine_kiwi_schema::Schema::decode(&bin).expect("Schema::decode failed");
+    assert_eq!(runtime.defs[1].fields[0].key_type_id, Some(brine_kiwi_schema::TYPE_STRING));
+    assert_eq!(runtime.defs[1].fields[1].key_type_id, Some(0));
+
+    for (source, message) in [
+        ("struct P { int x; }\nmessage M { map<P, int> m = 1; }", "cannot be a map key"),
+        ("message M { map<float, int> m = 1; }", "cannot be a map key"),
+        ("message M { map<Missing, int> m = 1; }", "is not defined"),
+    ] {
+        let err = compile_schema(source).unwrap_err();
+        assert!(
+            matches!(err, KiwiError::VerifierError(ref msg) if msg.contains(message)),
+            "expected a VerifierError but got {:?}",
+            err
+        );
+    }
+    assert!(matches!(
+        compile_schema("message M { map<int, int>[] m = 1; }"),
+        Err(KiwiError::ParseError { ref msg, .. }) if msg.contains("cannot be arrays")
+    ));
+}
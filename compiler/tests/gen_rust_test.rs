@@ -0,0 +1,652 @@
+#![cfg(test)]
+
+use brine_kiwi_compiler::{
+    compile_schema, compile_schema_to_rust, compile_schema_to_rust_with, parser::parse_schema, tokenizer::tokenize_schema,
+    GenOptions,
+};
+
+const SCHEMA: &str = r#"
+    enum Type {
+      FLAT = 0;
+      ROUND = 1;
+    }
+
+    struct Color {
+      byte red;
+      byte green;
+    }
+
+    message Example {
+      uint clientID = 1;
+      Type type = 2;
+      Color[] colors = 3;
+    }
+"#;
+
+#[test]
+fn test_gen_rust_derives_serde_by_default() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+
+    assert!(code.contains("use serde::Serialize;"));
+    assert!(code.contains("use serde_with::skip_serializing_none;"));
+    assert!(code.contains("#[skip_serializing_none]"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]\npub enum Type"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]\npub struct Color"));
+}
+
+#[test]
+fn test_gen_rust_without_serde() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let options = GenOptions {
+        derive_serde: false,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+
+    assert!(!code.contains("serde"));
+    assert!(!code.contains("skip_serializing_none"));
+    assert!(!code.contains("Serialize"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]\npub enum Type"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]\npub struct Example"));
+}
+
+#[test]
+fn test_gen_rust_emit_tests() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub fn schema() -> Schema"));
+    assert!(code.contains("impl ToKiwi for Example"));
+    assert!(!code.contains("mod generated_tests"));
+
+    let options = GenOptions {
+        emit_tests: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("#[cfg(test)]\nmod generated_tests {"));
+    assert!(code.contains("round_trip(\"Type\", Type::default());"));
+    assert!(code.contains("fn round_trip_color()"));
+    assert!(code.contains("round_trip(\"Example\", Example::default());"));
+
+    // A schema that can't be encoded gets neither.
+    let unverified = parse_schema(&tokenize_schema("message M { Missing m = 1; }").unwrap()).unwrap();
+    let code = compile_schema_to_rust_with(&unverified, &options);
+    assert!(!code.contains("pub fn schema()"));
+    assert!(!code.contains("mod generated_tests"));
+}
+
+#[test]
+fn test_gen_rust_escapes_keywords_as_raw_identifiers() {
+    let input = r#"
+    struct Match {
+      int type;
+      int self;
+    }
+
+    message Example {
+      Match match = 1;
+      string[] type = 2;
+    }
+    "#;
+
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+
+    assert!(code.contains("pub r#type: i32,"));
+    assert!(code.contains("pub self_: i32,"));
+    assert!(code.contains("pub r#match: Option<Match>,"));
+    assert!(code.contains("let mut r#match = Self::default();"));
+    assert!(code.contains("example.r#type = Some(tmp);"));
+    assert!(code.contains("value.get(\"type\")"));
+
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_hashable_types() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+
+    // Enums and float-free structs and messages get `Eq`/`Hash`.
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("Eq, Hash, Default, Serialize)]\npub enum Type"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]\npub struct Color"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]\npub struct Example"));
+
+    // A float, even through a nested type or array, rules them out.
+    let (floaty, _) = compile_schema(
+        "struct Point { float x; float y; }\nstruct Path { Point[] points; }\nmessage Sample { float64 value = 1; }\nstruct Id { uint id; }",
+    )
+    .expect("compile_schema failed");
+    let code = compile_schema_to_rust(&floaty);
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Default, Serialize)]\npub struct Point"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Default, Serialize)]\npub struct Path"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Default, Serialize)]\npub struct Sample"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]\npub struct Id"));
+
+    // Not affected by other options.
+    let options = GenOptions {
+        derive_serde: false,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]\npub enum Type"));
+
+    // The old switch is still accepted, and ignored.
+    #[allow(deprecated)]
+    let options = GenOptions {
+        hashable_enums: false,
+        ..GenOptions::default()
+    };
+    assert_eq!(compile_schema_to_rust_with(&schema, &options), compile_schema_to_rust(&schema));
+}
+
+#[test]
+fn test_gen_rust_boxes_recursive_messages() {
+    let input = r#"
+    message Node {
+      int value = 1;
+      Node child = 2;
+      Node[] children = 3;
+      Leaf leaf = 4;
+    }
+
+    message Leaf {
+      int value = 1;
+    }
+
+    struct Pair {
+      int key;
+      Entry entry;
+    }
+
+    message Entry {
+      Pair pair = 1;
+    }
+    "#;
+
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+
+    assert!(code.contains("pub child: Option<Box<Node>>,"));
+    assert!(code.contains("node.child = Some(Box::new(Node::from_kiwi(val)?));"));
+    assert!(code.contains("pub children: Option<Vec<Node>>,"));
+    assert!(code.contains("pub leaf: Option<Leaf>,"));
+
+    // Indirect struct → message → struct cycles are boxed on both sides.
+    assert!(code.contains("pub entry: Box<Entry>,"));
+    assert!(code.contains("pub pair: Option<Box<Pair>>,"));
+
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_skip_empty_vecs() {
+    let input = r#"
+    struct Path {
+      float[] points;
+      int count;
+    }
+
+    message Shape {
+      Path[] paths = 1;
+    }
+    "#;
+    let attr = "    #[serde(skip_serializing_if = \"Vec::is_empty\")]\n";
+
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    assert!(!compile_schema_to_rust(&schema).contains(attr));
+
+    let options = GenOptions {
+        skip_empty_vecs: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains(&format!("{}    pub points: Vec<f32>,", attr)));
+    assert!(code.contains("\n    pub count: i32,"));
+    // Message arrays are `Option<Vec<_>>` and already skipped when `None`.
+    assert!(code.contains("\n    pub paths: Option<Vec<Path>>,"));
+    syn::parse_file(&code).expect("generated code should parse");
+
+    // Without serde there is nothing to annotate.
+    let options = GenOptions {
+        derive_serde: false,
+        skip_empty_vecs: true,
+        ..GenOptions::default()
+    };
+    assert!(!compile_schema_to_rust_with(&schema, &options).contains("serde"));
+}
+
+#[test]
+fn test_gen_rust_enum_discriminants() {
+    let input = r#"
+    enum Type {
+      FLAT = 0;
+      ROUND = 1;
+      POINTED = 10;
+    }
+    "#;
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+
+    assert!(code.contains("#[repr(i32)]\n#[derive("));
+    assert!(code.contains("    Flat = 0,"));
+    assert!(code.contains("    Round = 1,"));
+    assert!(code.contains("    Pointed = 10,"));
+    assert!(code.contains("pub fn wire_value(&self) -> u32"));
+    assert!(code.contains("Type::Pointed => 10,"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_dynamic_setters() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(!code.contains("pub fn set_field"));
+
+    let options = GenOptions {
+        dynamic_setters: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("impl Example {"));
+    assert!(code.contains("\"clientID\" => self.client_id = Some(value.as_uint_opt()"));
+    assert!(code.contains("\"type\" => self.r#type = Some(Type::from_kiwi(&value)?),"));
+    // Structs are positional and complete, so they get no setter.
+    assert_eq!(code.matches("pub fn set_field").count(), 1);
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_typed_encode() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+
+    assert!(code.contains("bb.write_var_uint(self.wire_value());"));
+    assert!(code.contains("        bb.write_byte(self.red);"));
+    assert!(code.contains("        if let Some(ref val) = self.client_id {\n            bb.write_var_uint(1);\n            bb.write_var_uint(*val);"));
+    assert!(code.contains("for item in val.iter() { item.encode(bb); }"));
+    assert!(code.contains("        bb.write_byte(0);\n    }"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_from_kiwi_bytes_uses_type_id() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+
+    assert!(code.contains("pub fn from_kiwi_bytes(schema: &Schema, bytes: &[u8]) -> Result<Self, KiwiError>"));
+    assert!(code.contains("let value = Value::decode(schema, 1, bytes)"));
+    assert!(code.contains("let value = Value::decode(schema, 2, bytes)"));
+    // Enums are not top-level encodable types.
+    assert_eq!(code.matches("fn from_kiwi_bytes").count(), 2);
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_emit_prelude() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(!code.contains("pub mod prelude"));
+
+    let options = GenOptions {
+        emit_prelude: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("pub mod prelude {\n    pub use super::{Type, Color, Example};\n}"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_use_bytes_crate() {
+    let input = r#"
+    struct Blob { byte[] data; }
+    message Packet { byte[] payload = 1; uint[] ids = 2; }
+    "#;
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub payload: Option<Vec<u8>>,"));
+    assert!(!code.contains("bytes::Bytes"));
+
+    let options = GenOptions {
+        use_bytes_crate: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("pub data: bytes::Bytes,"));
+    assert!(code.contains("pub payload: Option<bytes::Bytes>,"));
+    assert!(code.contains("pub ids: Option<Vec<u32>>,"));
+    assert!(code.contains("blob.data = bytes::Bytes::from(tmp);"));
+    assert!(code.contains("packet.payload = Some(bytes::Bytes::from(tmp));"));
+    assert!(code.contains("bb.write_var_uint(val.len() as u32);\n            bb.write_bytes(val);"));
+    assert!(code.contains("bb.write_bytes(&self.data);"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_byte_arrays_use_value_bytes() {
+    let input = r#"
+    struct Blob { byte[] data; byte[][] rows; }
+    message Packet { byte[] payload = 1; }
+    "#;
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+
+    let options = GenOptions { dynamic_setters: true, ..GenOptions::default() };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("pub data: Vec<u8>,"));
+    assert!(code.contains("pub rows: Vec<Vec<u8>>,"));
+    assert!(code.contains("fields.insert(\"data\", Value::Bytes(self.data.to_vec()));"));
+    assert!(code.contains("Value::Array(self.rows.iter().map(|item| Value::Bytes(item.to_vec())).collect())"));
+    assert!(code.contains(
+        "let tmp = match arr.as_bytes_opt() { Some(bytes) => bytes.to_vec(), None => arr.as_array().iter()\
+         .map(|byte| byte.as_byte_opt().ok_or_else(|| KiwiError::MissingField(\"data\".into())))\
+         .collect::<Result<Vec<u8>, KiwiError>>()? };"
+    ));
+    assert!(code.contains(
+        "\"payload\" => self.payload = Some(match value.as_bytes_opt() { Some(bytes) => bytes.to_vec(), None => "
+    ));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_zero_copy_strings() {
+    let input = r#"
+    struct Point { float x; float y; }
+    struct Label { string text; Point at; }
+    message Note { Label label = 1; string[] tags = 2; Point origin = 3; }
+    "#;
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(!code.contains("Cow<"));
+    assert!(!code.contains("from_kiwi_ref"));
+
+    let options = GenOptions {
+        zero_copy_strings: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("use std::borrow::Cow;"));
+    // Only types that hold strings get a lifetime.
+    assert!(code.contains("pub struct Point {"));
+    assert!(code.contains("pub struct Label<'a> {"));
+    assert!(code.contains("pub text: Cow<'a, str>,"));
+    assert!(code.contains("pub label: Option<Label<'a>>,"));
+    assert!(code.contains("pub tags: Option<Vec<Cow<'a, str>>>,"));
+    assert!(code.contains("impl<'a> FromKiwi for Note<'a> {"));
+    assert!(code.contains("impl<'a> ToKiwi for Label<'a> {"));
+    // `FromKiwi` copies; `from_kiwi_ref` borrows, recursing into nested types.
+    assert!(code.contains("label.text = val.as_string_opt().ok_or_else(|| KiwiError::MissingField(\"text\".into()))?.to_string().into();"));
+    assert!(code.contains("pub fn from_kiwi_ref(value: &'a Value) -> Result<Self, KiwiError> {"));
+    assert!(code.contains("label.text = val.as_string_opt().ok_or_else(|| KiwiError::MissingField(\"text\".into()))?.into();"));
+    assert!(code.contains("note.label = Some(Label::from_kiwi_ref(val)?);"));
+    assert!(code.contains("note.origin = Some(Point::from_kiwi(val)?);"));
+    assert_eq!(code.matches("pub fn from_kiwi_ref").count(), 2);
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_dotted_package_nests_modules() {
+    let input = r#"
+    package game.net;
+    struct Vec2 { float x; float y; }
+    message Move { Vec2 to = 1; }
+    "#;
+    let (schema, _) = compile_schema(input).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub mod Game {\npub mod Net {\nuse brine_kiwi::*;"));
+    assert!(code.ends_with("}\n}"));
+    // Sibling types live in the same module, so references stay unqualified.
+    assert!(code.contains("r#move.to = Some(Vec2::from_kiwi(val)?);"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_no_std() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+
+    // Objects are built through the runtime's `Map`, which is a `HashMap`
+    // or a `BTreeMap` depending on whether the runtime has `std`.
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("let mut fields = Map::new();"));
+    assert!(!code.contains("HashMap"));
+    assert!(!code.contains("alloc::"));
+
+    let options = GenOptions {
+        no_std: true,
+        emit_tests: true,
+        zero_copy_strings: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("extern crate alloc;"));
+    assert!(code.contains("use alloc::{boxed::Box, string::{String, ToString}, vec::Vec};"));
+    assert!(code.contains("use alloc::borrow::Cow;"));
+    assert!(code.contains("let mut fields = Map::new();"));
+    assert!(code.contains("Value::Object(\"Example\", fields)"));
+    assert!(!code.contains("std::"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_tolerant_enums() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(!code.contains("Unknown(String)"));
+    assert!(code.contains("other => Err(KiwiError::InvalidEnumVariant(other.to_string())),"));
+
+    let options = GenOptions {
+        tolerant_enums: true,
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("    Unknown(String) = 2,\n}"));
+    assert!(code.contains("other => Ok(Type::Unknown(other.to_string())),"));
+    // Only the integer conversions and `encode` still reject unknown values:
+    // `Unknown` has no wire value.
+    assert_eq!(code.matches("InvalidEnumVariant").count(), 3);
+    assert!(!code.contains("impl From<Type> for i32"));
+    assert!(code.contains("impl TryFrom<Type> for i32 {\n    type Error = KiwiError;"));
+    assert!(code.contains("            Type::Round => Ok(1),"));
+    assert!(code.contains("            Type::Unknown(name) => Err(KiwiError::InvalidEnumVariant(name)),"));
+    assert!(!code.contains("panic!"));
+    assert!(code.contains("pub fn wire_value(&self) -> Option<u32> {"));
+    assert!(code.contains("            Type::Round => Some(1),\n            Type::Unknown(_) => None,"));
+    assert!(code.contains("            Type::Unknown(_) => Value::Enum(\"Type\", \"\"),"));
+    // `encode` fails on `Unknown`, and so does every struct encode around it.
+    assert!(code.contains("self.wire_value().ok_or_else(|| KiwiError::InvalidEnumVariant(self.to_string()))?;"));
+    assert!(code.contains("pub fn encode(&self, bb: &mut ByteBufferMut) -> Result<(), KiwiError> {"));
+    assert!(!code.contains("pub fn encode(&self, bb: &mut ByteBufferMut) {"));
+    assert!(code.contains("val.encode(bb)?;"));
+    assert!(code.contains("for item in val.iter() { item.encode(bb)?; }"));
+    syn::parse_file(&code).expect("generated code should parse");
+
+    // `Unknown` takes the lowest free discriminant, so it can't collide with
+    // a later explicit value.
+    let (schema, _) = compile_schema("enum Code { OK = 0; LATER = 2; NEXT = 1; }").expect("compile_schema failed");
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("    Next = 1,\n    /// A variant this schema doesn't know. It has no wire value, so it can't be encoded.\n    Unknown(String) = 3,\n}"));
+}
+
+#[test]
+fn test_gen_rust_extra_derives_and_module_path() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let options = GenOptions {
+        derive_serde: false,
+        extra_derives: vec!["Eq".to_string(), "Hash".to_string(), "PartialOrd".to_string()],
+        module_path: "crate::kiwi".to_string(),
+        ..GenOptions::default()
+    };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("use crate::kiwi::*;"));
+    assert!(!code.contains("use brine_kiwi::*;"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, PartialOrd)]\npub enum Type"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, PartialOrd)]\npub struct Color"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_enum_try_from_str() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    for tolerant_enums in [false, true] {
+        let options = GenOptions { tolerant_enums, ..GenOptions::default() };
+        let code = compile_schema_to_rust_with(&schema, &options);
+        assert!(code.contains("pub fn try_from_str(name: &str) -> Option<Self> {"));
+        assert!(code.contains("\"ROUND\" => Some(Type::Round),"));
+        assert!(code.contains("            _ => None,"));
+        syn::parse_file(&code).expect("generated code should parse");
+    }
+
+    // Names match as the schema spells them, not upper-cased.
+    let (schema, _) = compile_schema("enum Color { Red = 0; }").expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("\"Red\" => Some(Color::Red),"));
+    assert!(code.contains("\"Red\" => Ok(Color::Red),"));
+    assert!(!code.contains("\"RED\""));
+}
+
+#[test]
+fn test_gen_rust_enum_display() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    for tolerant_enums in [false, true] {
+        let options = GenOptions { tolerant_enums, ..GenOptions::default() };
+        let code = compile_schema_to_rust_with(&schema, &options);
+        assert!(code.contains("impl core::fmt::Display for Type {"));
+        assert!(code.contains("            Type::Round => \"ROUND\","));
+        assert_eq!(code.contains("Type::Unknown(ref name) => name,"), tolerant_enums);
+        syn::parse_file(&code).expect("generated code should parse");
+    }
+}
+
+#[test]
+fn test_gen_rust_enum_int_conversions() {
+    let (schema, _) = compile_schema("enum Code { OK = 0; MISSING = 404; }").expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("impl From<Code> for i32 {\n    fn from(value: Code) -> i32 {\n        value.wire_value() as i32\n    }\n}"));
+    assert!(code.contains("impl TryFrom<i32> for Code {\n    type Error = KiwiError;"));
+    assert!(code.contains("            404 => Ok(Code::Missing),"));
+    assert!(code.contains("            other => Err(KiwiError::InvalidEnumVariant(other.to_string())),"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_unicode_identifiers() {
+    let (schema, _) = compile_schema("message Straße { uint größe = 1; string 名前 = 2; }").expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub struct Straße {"));
+    assert!(code.contains("pub größe: Option<u32>,"));
+    assert!(code.contains("pub 名前: Option<String>,"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_message_getters() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("    pub fn client_id(&self) -> u32 {\n        self.client_id.unwrap_or_default()\n    }"));
+    assert!(code.contains("    pub fn has_client_id(&self) -> bool {\n        self.client_id.is_some()\n    }"));
+    assert!(code.contains("    pub fn r#type(&self) -> Type {\n        self.r#type.clone().unwrap_or_default()\n    }"));
+    assert!(code.contains("    pub fn has_type(&self) -> bool {"));
+    assert!(code.contains("    pub fn colors(&self) -> &[Color] {\n        self.colors.as_deref().unwrap_or(&[])\n    }"));
+    // Structs have required fields, so they get no getters.
+    assert!(!code.contains("pub fn red(&self)"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_message_getters_skip_clashing_names() {
+    let (schema, _) = compile_schema("message M { int encode = 1; string name = 2; bool has_name = 3; int old = 4 [deprecated]; }")
+        .expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub fn has_encode(&self) -> bool {"));
+    assert!(!code.contains("pub fn encode(&self) -> i32"));
+    assert!(!code.contains("pub fn old(&self)"));
+    syn::parse_file(&code).expect("generated code should parse");
+
+    // The getter of `has_name` wins over the presence check of `name`, in
+    // either field order.
+    for input in [
+        "message M { string name = 1; bool has_name = 2; }",
+        "message M { bool has_name = 1; string name = 2; }",
+    ] {
+        let (schema, _) = compile_schema(input).expect("compile_schema failed");
+        let code = compile_schema_to_rust(&schema);
+        assert!(code.contains("pub fn name(&self) -> &str {\n        self.name.as_deref().unwrap_or(\"\")\n    }"));
+        assert_eq!(code.matches("pub fn has_name(&self)").count(), 1);
+        assert!(code.contains("pub fn has_name(&self) -> bool {\n        self.has_name.unwrap_or_default()\n    }"));
+        assert!(code.contains("pub fn has_has_name(&self) -> bool {"));
+        syn::parse_file(&code).expect("generated code should parse");
+    }
+}
+
+#[test]
+fn test_gen_rust_nested_arrays() {
+    let (schema, _) = compile_schema("struct Grid { int[][] cells; } message M { string[][] names = 1; }")
+        .expect("compile_schema failed");
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub cells: Vec<Vec<i32>>,"));
+    assert!(code.contains("pub names: Option<Vec<Vec<String>>>,"));
+    assert!(code.contains("pub fn names(&self) -> &[Vec<String>] {"));
+    assert!(code.contains(
+        "for item in self.cells.iter() { bb.write_var_uint(item.len() as u32); for item in item.iter() { bb.write_var_int(*item); } }"
+    ));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_non_exhaustive_enums() {
+    let (schema, _) = compile_schema(SCHEMA).expect("compile_schema failed");
+    assert!(!compile_schema_to_rust(&schema).contains("#[non_exhaustive]"));
+
+    for tolerant_enums in [false, true] {
+        let options = GenOptions { non_exhaustive_enums: true, tolerant_enums, ..GenOptions::default() };
+        let code = compile_schema_to_rust_with(&schema, &options);
+        assert!(code.contains("#[repr(i32)]\n#[non_exhaustive]\n#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]\npub enum Type {"));
+        assert_eq!(code.matches("#[non_exhaustive]").count(), 1);
+        syn::parse_file(&code).expect("generated code should parse");
+    }
+}
+
+#[test]
+fn test_gen_rust_float64_fields() {
+    let (schema, _) = compile_schema("message Sample { float64 value = 1; float64[] series = 2; }")
+        .expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub value: Option<f64>,"));
+    assert!(code.contains("pub series: Option<Vec<f64>>,"));
+    assert!(code.contains("as_float64_opt()"));
+    assert!(code.contains("Value::Float64("));
+    assert!(code.contains("bb.write_var_float64("));
+    syn::parse_file(&code).expect("generated code should parse");
+}
+
+#[test]
+fn test_gen_rust_map_fields() {
+    let (schema, _) = compile_schema(
+        "enum Kind { A; B; }\nstruct Tally { map<Kind, uint> counts; }\nmessage Bag { map<string, Tally> tallies = 1; }",
+    )
+    .expect("compile_schema failed");
+
+    let code = compile_schema_to_rust(&schema);
+    assert!(code.contains("pub counts: std::collections::HashMap<Kind, u32>,"));
+    assert!(code.contains("pub tallies: Option<std::collections::HashMap<String, Tally>>,"));
+    assert!(code.contains("Value::Map("));
+    assert!(code.contains("for (key, item) in val.as_map()"));
+    assert!(code.contains("#[derive(Debug, Clone, PartialEq, Default, Serialize)]\npub struct Tally"));
+    assert!(!code.contains("fn tallies("), "maps get no message getter");
+    syn::parse_file(&code).expect("generated code should parse");
+
+    let options = GenOptions { no_std: true, ..GenOptions::default() };
+    let code = compile_schema_to_rust_with(&schema, &options);
+    assert!(code.contains("pub counts: Vec<(Kind, u32)>,"));
+    assert!(!code.contains("std::"));
+    syn::parse_file(&code).expect("generated code should parse");
+}
@@ -4,6 +4,7 @@
 #
 # 1) Compile simple.kiwi → simple.kiwi.bin
 # 2) Generate Rust code → generated.rs
+# 3) Generate the options showcase features.kiwi → features.rs
 #
 
 set -euo pipefail
@@ -17,8 +18,13 @@ cargo run -p brine-kiwi-cli -- compile -i "$SCHEMA" -o "$HERE/simple.kiwi.bin"
 
 # 2) Generate Rust code
 echo "⏳  Generating Rust code from simple.kiwi → generated.rs"
-cargo run -p brine-kiwi-cli -- gen-rust -i "$SCHEMA" -o "$HERE/src/generated.rs"
+cargo run -p brine-kiwi-cli -- gen-rust -i "$SCHEMA" -o "$HERE/src/generated.rs" --emit-tests --dynamic-setters
+
+# 3) Generate the options showcase
+echo "⏳  Generating Rust code from features.kiwi → features.rs"
+cargo run -p brine-kiwi-cli -- gen-rust -i "$HERE/features.kiwi" -o "$HERE/src/features.rs" --emit-tests --tolerant-enums --zero-copy-strings --skip-empty-vecs --use-bytes-crate
 
 echo "✅  Done!"
 echo " - simple.kiwi.bin → $HERE/simple.kiwi.bin"
 echo " - generated.rs     → $HERE/src/generated.rs"
+echo " - features.rs      → $HERE/src/features.rs"
@@ -0,0 +1,395 @@
+// This file was generated by the brine-kiwi compiler.
+// Do not edit manually.
+
+use brine_kiwi::*;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use std::borrow::Cow;
+
+/// The binary schema these types were generated from.
+pub const SCHEMA_BYTES: &[u8] = &[
+    3, 83, 116, 97, 116, 117, 115, 0, 0, 2, 65, 67, 84, 73, 86, 69,
+    0, 0, 0, 0, 82, 69, 84, 73, 82, 69, 68, 0, 0, 0, 1, 80,
+    114, 111, 102, 105, 108, 101, 0, 1, 2, 98, 105, 111, 0, 11, 0, 1,
+    108, 105, 110, 107, 115, 0, 11, 1, 2, 65, 99, 99, 111, 117, 110, 116,
+    0, 2, 5, 105, 100, 0, 7, 0, 1, 115, 116, 97, 116, 117, 115, 0,
+    0, 0, 2, 110, 97, 109, 101, 0, 11, 0, 3, 112, 114, 111, 102, 105,
+    108, 101, 0, 2, 0, 4, 97, 118, 97, 116, 97, 114, 0, 3, 1, 5,
+];
+
+/// Decodes the embedded binary schema.
+pub fn schema() -> Schema {
+    Schema::decode(SCHEMA_BYTES).expect("embedded schema is valid")
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+pub enum Status {
+    #[default]
+    Active = 0,
+    Retired = 1,
+    /// A variant this schema doesn't know. It has no wire value, so it can't be encoded.
+    Unknown(String) = 2,
+}
+
+impl Status {
+    /// Looks up a variant by its schema name, spelled exactly as in the
+    /// schema (case included), or `None` if the schema has no such variant.
+    pub fn try_from_str(name: &str) -> Option<Self> {
+        match name {
+            "ACTIVE" => Some(Status::Active),
+            "RETIRED" => Some(Status::Retired),
+            _ => None,
+        }
+    }
+
+    /// The numeric value of this variant in the schema, or `None` for `Unknown`.
+    pub fn wire_value(&self) -> Option<u32> {
+        match *self {
+            Status::Active => Some(0),
+            Status::Retired => Some(1),
+            Status::Unknown(_) => None,
+        }
+    }
+
+    /// Writes this value to `bb` without going through `Value`. Fails on
+    /// `Unknown`, which has no wire value.
+    pub fn encode(&self, bb: &mut ByteBufferMut) -> Result<(), KiwiError> {
+        let value = self.wire_value().ok_or_else(|| KiwiError::InvalidEnumVariant(self.to_string()))?;
+        bb.write_var_uint(value);
+        Ok(())
+    }
+}
+
+impl TryFrom<Status> for i32 {
+    type Error = KiwiError;
+
+    fn try_from(value: Status) -> Result<i32, KiwiError> {
+        match value {
+            Status::Active => Ok(0),
+            Status::Retired => Ok(1),
+            Status::Unknown(name) => Err(KiwiError::InvalidEnumVariant(name)),
+        }
+    }
+}
+
+impl TryFrom<i32> for Status {
+    type Error = KiwiError;
+
+    fn try_from(value: i32) -> Result<Self, KiwiError> {
+        match value {
+            0 => Ok(Status::Active),
+            1 => Ok(Status::Retired),
+            other => Err(KiwiError::InvalidEnumVariant(other.to_string())),
+        }
+    }
+}
+
+impl FromKiwi for Status {
+    fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {
+        let s = value.as_string();
+        match s {
+            "ACTIVE" => Ok(Status::Active),
+            "RETIRED" => Ok(Status::Retired),
+            other => Ok(Status::Unknown(other.to_string())),
+        }
+    }
+}
+
+impl ToKiwi for Status {
+    fn to_kiwi(&self) -> Value<'static> {
+        match *self {
+            Status::Active => Value::Enum("Status", "ACTIVE"),
+            Status::Retired => Value::Enum("Status", "RETIRED"),
+            Status::Unknown(_) => Value::Enum("Status", ""),
+        }
+    }
+}
+
+impl core::fmt::Display for Status {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match *self {
+            Status::Active => "ACTIVE",
+            Status::Retired => "RETIRED",
+            Status::Unknown(ref name) => name,
+        })
+    }
+}
+
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+pub struct Profile<'a> {
+    pub bio: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Profile<'a> {
+    /// Decodes `bytes` as this type using `schema` (e.g. the embedded `schema()`).
+    pub fn from_kiwi_bytes(schema: &Schema, bytes: &[u8]) -> Result<Self, KiwiError> {
+        let value = Value::decode(schema, 1, bytes)
+            .map_err(|_| KiwiError::DecodeError("invalid Profile bytes".into()))?;
+        Self::from_kiwi(&value)
+    }
+
+    /// Writes this value to `bb` without going through `Value`.
+    /// Fails on an `Unknown` enum, leaving a partial value in `bb`.
+    pub fn encode(&self, bb: &mut ByteBufferMut) -> Result<(), KiwiError> {
+        bb.write_string(&self.bio);
+        bb.write_var_uint(self.links.len() as u32);
+        for item in self.links.iter() { bb.write_string(item); }
+        Ok(())
+    }
+}
+
+impl<'a> FromKiwi for Profile<'a> {
+    fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {
+        let mut profile = Self::default();
+
+        if let Some(val) = value.get("bio") {
+            profile.bio = val.as_string_opt().ok_or_else(|| KiwiError::MissingField("bio".into()))?.to_string().into();
+        } else {
+            return Err(KiwiError::MissingField("bio".into()));
+        }
+
+        if let Some(arr) = value.get("links") {
+            let mut tmp = Vec::new();
+            for item in arr.as_array() { tmp.push(item.as_string_opt().ok_or_else(|| KiwiError::MissingField("links".into()))?.to_string().into()); }
+            profile.links = tmp;
+        } else {
+            return Err(KiwiError::MissingField("links".into()));
+        }
+
+        Ok(profile)
+    }
+}
+
+impl<'a> ToKiwi for Profile<'a> {
+    fn to_kiwi(&self) -> Value<'static> {
+        let mut fields = Map::new();
+        fields.insert("bio", Value::String(self.bio.to_string()));
+        fields.insert("links", Value::Array(self.links.iter().map(|item| Value::String(item.to_string())).collect()));
+        Value::Object("Profile", fields)
+    }
+}
+
+impl<'a> Profile<'a> {
+    /// Like `from_kiwi`, but borrows strings from `value` instead of copying them.
+    pub fn from_kiwi_ref(value: &'a Value) -> Result<Self, KiwiError> {
+        let mut profile = Self::default();
+
+        if let Some(val) = value.get("bio") {
+            profile.bio = val.as_string_opt().ok_or_else(|| KiwiError::MissingField("bio".into()))?.into();
+        } else {
+            return Err(KiwiError::MissingField("bio".into()));
+        }
+
+        if let Some(arr) = value.get("links") {
+            let mut tmp = Vec::new();
+            for item in arr.as_array() { tmp.push(item.as_string_opt().ok_or_else(|| KiwiError::MissingField("links".into()))?.into()); }
+            profile.links = tmp;
+        } else {
+            return Err(KiwiError::MissingField("links".into()));
+        }
+
+        Ok(profile)
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+pub struct Account<'a> {
+    pub id: Option<u32>,
+    pub status: Option<Status>,
+    pub name: Option<Cow<'a, str>>,
+    pub profile: Option<Profile<'a>>,
+    pub avatar: Option<bytes::Bytes>,
+}
+
+impl<'a> Account<'a> {
+    /// Decodes `bytes` as this type using `schema` (e.g. the embedded `schema()`).
+    pub fn from_kiwi_bytes(schema: &Schema, bytes: &[u8]) -> Result<Self, KiwiError> {
+        let value = Value::decode(schema, 2, bytes)
+            .map_err(|_| KiwiError::DecodeError("invalid Account bytes".into()))?;
+        Self::from_kiwi(&value)
+    }
+
+    /// Writes this value to `bb` without going through `Value`.
+    /// Fails on an `Unknown` enum, leaving a partial value in `bb`.
+    pub fn encode(&self, bb: &mut ByteBufferMut) -> Result<(), KiwiError> {
+        if let Some(ref val) = self.id {
+            bb.write_var_uint(1);
+            bb.write_var_uint(*val);
+        }
+        if let Some(ref val) = self.status {
+            bb.write_var_uint(2);
+            val.encode(bb)?;
+        }
+        if let Some(ref val) = self.name {
+            bb.write_var_uint(3);
+            bb.write_string(val);
+        }
+        if let Some(ref val) = self.profile {
+            bb.write_var_uint(4);
+            val.encode(bb)?;
+        }
+        if let Some(ref val) = self.avatar {
+            bb.write_var_uint(5);
+            bb.write_var_uint(val.len() as u32);
+            bb.write_bytes(val);
+        }
+        bb.write_byte(0);
+        Ok(())
+    }
+}
+
+impl<'a> FromKiwi for Account<'a> {
+    fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {
+        let mut account = Self::default();
+
+        if let Some(val) = value.get("id") {
+            account.id = Some(val.as_uint_opt().ok_or_else(|| KiwiError::MissingField("id".into()))?);
+        }
+
+        if let Some(val) = value.get("status") {
+            account.status = Some(Status::from_kiwi(val)?);
+        }
+
+        if let Some(val) = value.get("name") {
+            account.name = Some(val.as_string_opt().ok_or_else(|| KiwiError::MissingField("name".into()))?.to_string().into());
+        }
+
+        if let Some(val) = value.get("profile") {
+            account.profile = Some(Profile::from_kiwi(val)?);
+        }
+
+        if let Some(arr) = value.get("avatar") {
+            let tmp = match arr.as_bytes_opt() { Some(bytes) => bytes.to_vec(), None => arr.as_array().iter().map(|byte| byte.as_byte_opt().ok_or_else(|| KiwiError::MissingField("avatar".into()))).collect::<Result<Vec<u8>, KiwiError>>()? };
+            account.avatar = Some(bytes::Bytes::from(tmp));
+        }
+
+        Ok(account)
+    }
+}
+
+impl<'a> ToKiwi for Account<'a> {
+    fn to_kiwi(&self) -> Value<'static> {
+        let mut fields = Map::new();
+        if let Some(ref val) = self.id { fields.insert("id", Value::UInt(*val)); }
+        if let Some(ref val) = self.status { fields.insert("status", val.to_kiwi()); }
+        if let Some(ref val) = self.name { fields.insert("name", Value::String(val.to_string())); }
+        if let Some(ref val) = self.profile { fields.insert("profile", val.to_kiwi()); }
+        if let Some(ref val) = self.avatar { fields.insert("avatar", Value::Bytes(val.to_vec())); }
+        Value::Object("Account", fields)
+    }
+}
+
+impl<'a> Account<'a> {
+    /// Like `from_kiwi`, but borrows strings from `value` instead of copying them.
+    pub fn from_kiwi_ref(value: &'a Value) -> Result<Self, KiwiError> {
+        let mut account = Self::default();
+
+        if let Some(val) = value.get("id") {
+            account.id = Some(val.as_uint_opt().ok_or_else(|| KiwiError::MissingField("id".into()))?);
+        }
+
+        if let Some(val) = value.get("status") {
+            account.status = Some(Status::from_kiwi(val)?);
+        }
+
+        if let Some(val) = value.get("name") {
+            account.name = Some(val.as_string_opt().ok_or_else(|| KiwiError::MissingField("name".into()))?.into());
+        }
+
+        if let Some(val) = value.get("profile") {
+            account.profile = Some(Profile::from_kiwi_ref(val)?);
+        }
+
+        if let Some(arr) = value.get("avatar") {
+            let tmp = match arr.as_bytes_opt() { Some(bytes) => bytes.to_vec(), None => arr.as_array().iter().map(|byte| byte.as_byte_opt().ok_or_else(|| KiwiError::MissingField("avatar".into()))).collect::<Result<Vec<u8>, KiwiError>>()? };
+            account.avatar = Some(bytes::Bytes::from(tmp));
+        }
+
+        Ok(account)
+    }
+}
+impl<'a> Account<'a> {
+    /// Returns `id`, or its default when absent.
+    pub fn id(&self) -> u32 {
+        self.id.unwrap_or_default()
+    }
+
+    /// Returns true if `id` is present.
+    pub fn has_id(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// Returns `status`, or its default when absent.
+    pub fn status(&self) -> Status {
+        self.status.clone().unwrap_or_default()
+    }
+
+    /// Returns true if `status` is present.
+    pub fn has_status(&self) -> bool {
+        self.status.is_some()
+    }
+
+    /// Returns `name`, or its default when absent.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("")
+    }
+
+    /// Returns true if `name` is present.
+    pub fn has_name(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// Returns `profile`, or its default when absent.
+    pub fn profile(&self) -> Profile<'a> {
+        self.profile.clone().unwrap_or_default()
+    }
+
+    /// Returns true if `profile` is present.
+    pub fn has_profile(&self) -> bool {
+        self.profile.is_some()
+    }
+
+    /// Returns `avatar`, or its default when absent.
+    pub fn avatar(&self) -> &[u8] {
+        self.avatar.as_deref().unwrap_or(&[])
+    }
+
+    /// Returns true if `avatar` is present.
+    pub fn has_avatar(&self) -> bool {
+        self.avatar.is_some()
+    }
+}
+
+#[cfg(test)]
+mod generated_tests {
+    use super::*;
+
+    fn round_trip<T: FromKiwi + ToKiwi + PartialEq + std::fmt::Debug>(name: &str, sample: T) {
+        let schema = schema();
+        let type_id = schema.def(name).expect("definition exists").index;
+        let bytes = sample.to_kiwi().encode(&schema);
+        let value = Value::decode(&schema, type_id, &bytes).expect("decode failed");
+        assert_eq!(T::from_kiwi(&value).expect("from_kiwi failed"), sample);
+    }
+
+    #[test]
+    fn round_trip_status() {
+        round_trip("Status", Status::default());
+    }
+
+    #[test]
+    fn round_trip_profile() {
+        round_trip("Profile", Profile::default());
+    }
+
+    #[test]
+    fn round_trip_account() {
+        round_trip("Account", Account::default());
+    }
+}
\ No newline at end of file
@@ -5,11 +5,77 @@ use brine_kiwi::*;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The binary schema these types were generated from.
+pub const SCHEMA_BYTES: &[u8] = &[
+    3, 84, 121, 112, 101, 0, 0, 3, 70, 76, 65, 84, 0, 0, 0, 0,
+    82, 79, 85, 78, 68, 0, 0, 0, 1, 80, 79, 73, 78, 84, 69, 68,
+    0, 0, 0, 2, 67, 111, 108, 111, 114, 0, 1, 4, 114, 101, 100, 0,
+    3, 0, 1, 103, 114, 101, 101, 110, 0, 3, 0, 2, 98, 108, 117, 101,
+    0, 3, 0, 3, 97, 108, 112, 104, 97, 0, 3, 0, 4, 69, 120, 97,
+    109, 112, 108, 101, 0, 2, 4, 99, 108, 105, 101, 110, 116, 73, 68, 0,
+    7, 0, 1, 116, 121, 112, 101, 0, 0, 0, 2, 99, 111, 108, 111, 114,
+    115, 0, 2, 1, 3, 115, 105, 103, 110, 97, 116, 117, 114, 101, 0, 3,
+    1, 4,
+];
+
+/// Decodes the embedded binary schema.
+pub fn schema() -> Schema {
+    Schema::decode(SCHEMA_BYTES).expect("embedded schema is valid")
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
 pub enum Type {
-    Flat,
-    Round,
-    Pointed,
+    #[default]
+    Flat = 0,
+    Round = 1,
+    Pointed = 2,
+}
+
+impl Type {
+    /// Looks up a variant by its schema name, spelled exactly as in the
+    /// schema (case included), or `None` if the schema has no such variant.
+    pub fn try_from_str(name: &str) -> Option<Self> {
+        match name {
+            "FLAT" => Some(Type::Flat),
+            "ROUND" => Some(Type::Round),
+            "POINTED" => Some(Type::Pointed),
+            _ => None,
+        }
+    }
+
+    /// The numeric value of this variant in the schema.
+    pub fn wire_value(&self) -> u32 {
+        match *self {
+            Type::Flat => 0,
+            Type::Round => 1,
+            Type::Pointed => 2,
+        }
+    }
+
+    /// Writes this value to `bb` without going through `Value`.
+    pub fn encode(&self, bb: &mut ByteBufferMut) {
+        bb.write_var_uint(self.wire_value());
+    }
+}
+
+impl From<Type> for i32 {
+    fn from(value: Type) -> i32 {
+        value.wire_value() as i32
+    }
+}
+
+impl TryFrom<i32> for Type {
+    type Error = KiwiError;
+
+    fn try_from(value: i32) -> Result<Self, KiwiError> {
+        match value {
+            0 => Ok(Type::Flat),
+            1 => Ok(Type::Round),
+            2 => Ok(Type::Pointed),
+            other => Err(KiwiError::InvalidEnumVariant(other.to_string())),
+        }
+    }
 }
 
 impl FromKiwi for Type {
@@ -24,9 +90,29 @@ impl FromKiwi for Type {
     }
 }
 
+impl ToKiwi for Type {
+    fn to_kiwi(&self) -> Value<'static> {
+        match *self {
+            Type::Flat => Value::Enum("Type", "FLAT"),
+            Type::Round => Value::Enum("Type", "ROUND"),
+            Type::Pointed => Value::Enum("Type", "POINTED"),
+        }
+    }
+}
+
+impl core::fmt::Display for Type {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match *self {
+            Type::Flat => "FLAT",
+            Type::Round => "ROUND",
+            Type::Pointed => "POINTED",
+        })
+    }
+}
+
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
@@ -34,30 +120,47 @@ pub struct Color {
     pub alpha: u8,
 }
 
+impl Color {
+    /// Decodes `bytes` as this type using `schema` (e.g. the embedded `schema()`).
+    pub fn from_kiwi_bytes(schema: &Schema, bytes: &[u8]) -> Result<Self, KiwiError> {
+        let value = Value::decode(schema, 1, bytes)
+            .map_err(|_| KiwiError::DecodeError("invalid Color bytes".into()))?;
+        Self::from_kiwi(&value)
+    }
+
+    /// Writes this value to `bb` without going through `Value`.
+    pub fn encode(&self, bb: &mut ByteBufferMut) {
+        bb.write_byte(self.red);
+        bb.write_byte(self.green);
+        bb.write_byte(self.blue);
+        bb.write_byte(self.alpha);
+    }
+}
+
 impl FromKiwi for Color {
     fn from_kiwi(value: &Value) -> Result<Self, KiwiError> {
         let mut color = Self::default();
 
         if let Some(val) = value.get("red") {
-            color.red = val.as_byte();
+            color.red = val.as_byte_opt().ok_or_else(|| KiwiError::MissingField("red".into()))?;
         } else {
             return Err(KiwiError::MissingField("red".into()));
         }
 
         if let Some(val) = value.get("green") {
-            color.green = val.as_byte();
+            color.green = val.as_byte_opt().ok_or_else(|| KiwiError::MissingField("green".into()))?;
         } else {
             return Err(KiwiError::MissingField("green".into()));
         }
 
         if let Some(val) = value.get("blue") {
-            color.blue = val.as_byte();
+            color.blue = val.as_byte_opt().ok_or_else(|| KiwiError::MissingField("blue".into()))?;
         } else {
             return Err(KiwiError::MissingField("blue".into()));
         }
 
         if let Some(val) = value.get("alpha") {
-            color.alpha = val.as_byte();
+            color.alpha = val.as_byte_opt().ok_or_else(|| KiwiError::MissingField("alpha".into()))?;
         } else {
             return Err(KiwiError::MissingField("alpha".into()));
         }
@@ -66,12 +169,56 @@ impl FromKiwi for Color {
     }
 }
 
+impl ToKiwi for Color {
+    fn to_kiwi(&self) -> Value<'static> {
+        let mut fields = Map::new();
+        fields.insert("red", Value::Byte(self.red));
+        fields.insert("green", Value::Byte(self.green));
+        fields.insert("blue", Value::Byte(self.blue));
+        fields.insert("alpha", Value::Byte(self.alpha));
+        Value::Object("Color", fields)
+    }
+}
+
 #[skip_serializing_none]
-#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
 pub struct Example {
     pub client_id: Option<u32>,
-    pub type_: Option<Type>,
+    pub r#type: Option<Type>,
     pub colors: Option<Vec<Color>>,
+    pub signature: Option<Vec<u8>>,
+}
+
+impl Example {
+    /// Decodes `bytes` as this type using `schema` (e.g. the embedded `schema()`).
+    pub fn from_kiwi_bytes(schema: &Schema, bytes: &[u8]) -> Result<Self, KiwiError> {
+        let value = Value::decode(schema, 2, bytes)
+            .map_err(|_| KiwiError::DecodeError("invalid Example bytes".into()))?;
+        Self::from_kiwi(&value)
+    }
+
+    /// Writes this value to `bb` without going through `Value`.
+    pub fn encode(&self, bb: &mut ByteBufferMut) {
+        if let Some(ref val) = self.client_id {
+            bb.write_var_uint(1);
+            bb.write_var_uint(*val);
+        }
+        if let Some(ref val) = self.r#type {
+            bb.write_var_uint(2);
+            val.encode(bb);
+        }
+        if let Some(ref val) = self.colors {
+            bb.write_var_uint(3);
+            bb.write_var_uint(val.len() as u32);
+            for item in val.iter() { item.encode(bb); }
+        }
+        if let Some(ref val) = self.signature {
+            bb.write_var_uint(4);
+            bb.write_var_uint(val.len() as u32);
+            bb.write_bytes(val);
+        }
+        bb.write_byte(0);
+    }
 }
 
 impl FromKiwi for Example {
@@ -79,11 +226,11 @@ impl FromKiwi for Example {
         let mut example = Self::default();
 
         if let Some(val) = value.get("clientID") {
-            example.client_id = Some(val.as_uint());
+            example.client_id = Some(val.as_uint_opt().ok_or_else(|| KiwiError::MissingField("clientID".into()))?);
         }
 
         if let Some(val) = value.get("type") {
-            example.type_ = Some(Type::from_kiwi(val)?);
+            example.r#type = Some(Type::from_kiwi(val)?);
         }
 
         if let Some(arr) = value.get("colors") {
@@ -92,6 +239,105 @@ impl FromKiwi for Example {
             example.colors = Some(tmp);
         }
 
+        if let Some(arr) = value.get("signature") {
+            let tmp = match arr.as_bytes_opt() { Some(bytes) => bytes.to_vec(), None => arr.as_array().iter().map(|byte| byte.as_byte_opt().ok_or_else(|| KiwiError::MissingField("signature".into()))).collect::<Result<Vec<u8>, KiwiError>>()? };
+            example.signature = Some(tmp);
+        }
+
         Ok(example)
     }
+}
+
+impl ToKiwi for Example {
+    fn to_kiwi(&self) -> Value<'static> {
+        let mut fields = Map::new();
+        if let Some(ref val) = self.client_id { fields.insert("clientID", Value::UInt(*val)); }
+        if let Some(ref val) = self.r#type { fields.insert("type", val.to_kiwi()); }
+        if let Some(ref val) = self.colors { fields.insert("colors", Value::Array(val.iter().map(|item| item.to_kiwi()).collect())); }
+        if let Some(ref val) = self.signature { fields.insert("signature", Value::Bytes(val.to_vec())); }
+        Value::Object("Example", fields)
+    }
+}
+
+impl Example {
+    /// Sets the field named `name` (as spelled in the schema) from `value`.
+    pub fn set_field(&mut self, name: &str, value: Value) -> Result<(), KiwiError> {
+        match name {
+            "clientID" => self.client_id = Some(value.as_uint_opt().ok_or_else(|| KiwiError::MissingField("clientID".into()))?),
+            "type" => self.r#type = Some(Type::from_kiwi(&value)?),
+            "colors" => self.colors = Some(value.as_array().iter().map(Color::from_kiwi).collect::<Result<_, KiwiError>>()?),
+            "signature" => self.signature = Some(match value.as_bytes_opt() { Some(bytes) => bytes.to_vec(), None => value.as_array().iter().map(|byte| byte.as_byte_opt().ok_or_else(|| KiwiError::MissingField("signature".into()))).collect::<Result<Vec<u8>, KiwiError>>()? }),
+            other => return Err(KiwiError::MissingField(other.to_string())),
+        }
+        Ok(())
+    }
+}
+impl Example {
+    /// Returns `clientID`, or its default when absent.
+    pub fn client_id(&self) -> u32 {
+        self.client_id.unwrap_or_default()
+    }
+
+    /// Returns true if `clientID` is present.
+    pub fn has_client_id(&self) -> bool {
+        self.client_id.is_some()
+    }
+
+    /// Returns `type`, or its default when absent.
+    pub fn r#type(&self) -> Type {
+        self.r#type.clone().unwrap_or_default()
+    }
+
+    /// Returns true if `type` is present.
+    pub fn has_type(&self) -> bool {
+        self.r#type.is_some()
+    }
+
+    /// Returns `colors`, or its default when absent.
+    pub fn colors(&self) -> &[Color] {
+        self.colors.as_deref().unwrap_or(&[])
+    }
+
+    /// Returns true if `colors` is present.
+    pub fn has_colors(&self) -> bool {
+        self.colors.is_some()
+    }
+
+    /// Returns `signature`, or its default when absent.
+    pub fn signature(&self) -> &[u8] {
+        self.signature.as_deref().unwrap_or(&[])
+    }
+
+    /// Returns true if `signature` is present.
+    pub fn has_signature(&self) -> bool {
+        self.signature.is_some()
+    }
+}
+
+#[cfg(test)]
+mod generated_tests {
+    use super::*;
+
+    fn round_trip<T: FromKiwi + ToKiwi + PartialEq + std::fmt::Debug>(name: &str, sample: T) {
+        let schema = schema();
+        let type_id = schema.def(name).expect("definition exists").index;
+        let bytes = sample.to_kiwi().encode(&schema);
+        let value = Value::decode(&schema, type_id, &bytes).expect("decode failed");
+        assert_eq!(T::from_kiwi(&value).expect("from_kiwi failed"), sample);
+    }
+
+    #[test]
+    fn round_trip_type() {
+        round_trip("Type", Type::default());
+    }
+
+    #[test]
+    fn round_trip_color() {
+        round_trip("Color", Color::default());
+    }
+
+    #[test]
+    fn round_trip_example() {
+        round_trip("Example", Example::default());
+    }
 }
\ No newline at end of file
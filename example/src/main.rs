@@ -1,19 +1,27 @@
 // example/src/main.rs
 
+// Not every generated helper is exercised by this demo.
+#[allow(dead_code)]
 mod generated;
 
-use std::collections::HashMap;
+// Generated from features.kiwi with the options the demo above doesn't use;
+// only its tests exercise it.
+#[cfg(test)]
+#[allow(dead_code)]
+mod features;
+
 use brine_kiwi::*;
 
 // Bring the generated types into scope:
-use generated::{Color, Example, Type};
+use generated::{schema, Color, Example};
 
 fn main() -> Result<(), KiwiError> {
 
     // Manually construct a `Value::Object("Example", fields)` that matches the schema.
     //
-    // Build the inner field‐map: HashMap<&'static str, Value>
-    let mut example_fields: HashMap<&'static str, Value> = HashMap::new();
+    // Build the inner field‐map: Map<&'static str, Value>, the runtime's
+    // map type (a HashMap, or a BTreeMap without its `std` feature)
+    let mut example_fields: Map<&'static str, Value> = Map::new();
 
     // 1) "clientID": 123
     example_fields.insert("clientID", Value::UInt(123));
@@ -22,14 +30,14 @@ fn main() -> Result<(), KiwiError> {
     example_fields.insert("type", Value::Enum("Type", "ROUND"));
 
     // 3) "colors": an array of two Color‐objects
-    let mut c1: HashMap<&'static str, Value> = HashMap::new();
+    let mut c1: Map<&'static str, Value> = Map::new();
     c1.insert("red",   Value::Byte(10));
     c1.insert("green", Value::Byte(20));
     c1.insert("blue",  Value::Byte(30));
     c1.insert("alpha", Value::Byte(255));
     let color1 = Value::Object("Color", c1);
 
-    let mut c2: HashMap<&'static str, Value> = HashMap::new();
+    let mut c2: Map<&'static str, Value> = Map::new();
     c2.insert("red",   Value::Byte(200));
     c2.insert("green", Value::Byte(100));
     c2.insert("blue",  Value::Byte(50));
@@ -44,13 +52,14 @@ fn main() -> Result<(), KiwiError> {
     // Now use the generated `Example::from_kiwi(&v)`:
     let example: Example = Example::from_kiwi(&v)?;
 
-    // Because Example is a "message", its fields are `Option<…>`.
-    let client_id = example.client_id.unwrap_or_default();
-    let typ       = example.type_.unwrap_or(Type::Flat);
-    let colors: Vec<Color> = example.colors.unwrap_or_default();
+    // Because Example is a "message", its fields are `Option<…>`; the
+    // getters fall back to the schema defaults.
+    let client_id = example.client_id();
+    let typ       = example.r#type();
+    let colors: &[Color] = example.colors();
 
     println!("clientID = {}", client_id);
-    println!("type    = {:?}", typ);
+    println!("type    = {:?} (wire value {})", typ, typ.wire_value());
     println!("colors.len() = {}", colors.len());
 
     for (i, c) in colors.iter().enumerate() {
@@ -60,5 +69,199 @@ fn main() -> Result<(), KiwiError> {
         );
     }
 
+    // Fields can also be set by their schema name, e.g. from dynamic input.
+    let mut example = example;
+    example.set_field("clientID", Value::UInt(client_id + 1))?;
+    println!("clientID after set_field = {:?}", example.client_id);
+
+    // Going the other way: `to_kiwi()` + the embedded schema encode it back to bytes.
+    // `try_encode` reports misspelled names in hand-built values instead of panicking.
+    let bytes = example.to_kiwi().try_encode(&schema())?;
+    println!("encoded {} bytes", bytes.len());
+
+    // The generated `encode()` writes the same bytes without building a `Value`.
+    let mut bb = ByteBufferMut::new();
+    example.encode(&mut bb);
+    assert_eq!(bb.data(), bytes);
+
+    // And back again, straight from bytes.
+    let decoded = Example::from_kiwi_bytes(&schema(), &bytes)?;
+    assert_eq!(decoded, example);
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generated::Type;
+    use std::collections::HashMap;
+
+    #[test]
+    fn generated_enum_as_map_key() {
+        let mut counts: HashMap<Type, u32> = HashMap::new();
+        *counts.entry(Type::Round).or_default() += 1;
+        *counts.entry(Type::Round).or_default() += 1;
+        *counts.entry(Type::Flat).or_default() += 1;
+
+        assert_eq!(counts[&Type::Round], 2);
+        assert_eq!(counts[&Type::Flat], 1);
+        assert!(!counts.contains_key(&Type::Pointed));
+    }
+
+    #[test]
+    fn wrong_kind_is_an_error() {
+        let mut fields: Map<&'static str, Value> = Map::new();
+        fields.insert("clientID", Value::String("123".to_owned()));
+        let v = Value::Object("Example", fields);
+
+        assert!(matches!(
+            Example::from_kiwi(&v),
+            Err(KiwiError::MissingField(ref name)) if name == "clientID"
+        ));
+    }
+
+    #[test]
+    fn typo_is_an_encode_error() {
+        let mut fields: Map<&'static str, Value> = Map::new();
+        fields.insert("type", Value::Enum("Type", "ROUNDED"));
+        let v = Value::Object("Example", fields);
+
+        assert_eq!(
+            v.try_encode(&schema()),
+            Err(EncodeError::UnknownVariant { definition: "Type".into(), variant: "ROUNDED".into() })
+        );
+    }
+
+    #[test]
+    fn set_field_by_name() {
+        let mut example = Example::default();
+        example.set_field("clientID", Value::UInt(42)).unwrap();
+        example.set_field("type", Value::Enum("Type", "POINTED")).unwrap();
+
+        assert_eq!(example.client_id, Some(42));
+        assert_eq!(example.r#type, Some(Type::Pointed));
+        assert!(matches!(
+            example.set_field("missing", Value::Bool(true)),
+            Err(KiwiError::MissingField(ref name)) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn byte_arrays_accept_packed_and_unpacked_values() {
+        let mut fields: Map<&'static str, Value> = Map::new();
+        fields.insert("signature", Value::Array(vec![Value::Byte(1), Value::Byte(2)]));
+        let example = Example::from_kiwi(&Value::Object("Example", fields)).unwrap();
+        assert_eq!(example.signature(), &[1, 2]);
+
+        let mut example = Example::default();
+        example.set_field("signature", Value::Bytes(vec![3, 4])).unwrap();
+        assert_eq!(example.signature(), &[3, 4]);
+        example.set_field("signature", Value::Array(vec![Value::Byte(5)])).unwrap();
+        assert_eq!(example.signature(), &[5]);
+        assert!(example.set_field("signature", Value::Array(vec![Value::Bool(true)])).is_err());
+
+        let bytes = example.to_kiwi().encode(&schema());
+        let decoded = Example::from_kiwi_bytes(&schema(), &bytes).unwrap();
+        assert_eq!(decoded.signature(), &[5]);
+    }
+
+    #[test]
+    fn tolerant_enum_to_int_is_fallible() {
+        use features::Status;
+
+        assert_eq!(i32::try_from(Status::Retired).unwrap(), 1);
+
+        let unknown = Status::from_kiwi(&Value::Enum("Status", "SUSPENDED")).unwrap();
+        assert_eq!(unknown, Status::Unknown("SUSPENDED".to_string()));
+        assert!(matches!(
+            i32::try_from(unknown),
+            Err(KiwiError::InvalidEnumVariant(ref name)) if name == "SUSPENDED"
+        ));
+    }
+
+    #[test]
+    fn tolerant_enum_unknown_does_not_encode() {
+        use features::{schema, Account, Status};
+
+        let mut fields: Map<&'static str, Value> = Map::new();
+        fields.insert("id", Value::UInt(7));
+        fields.insert("status", Value::Enum("Status", "SUSPENDED"));
+        let account = Account::from_kiwi(&Value::Object("Account", fields)).unwrap();
+        assert_eq!(account.status, Some(Status::Unknown("SUSPENDED".to_string())));
+
+        assert_eq!(Status::Retired.wire_value(), Some(1));
+        assert_eq!(account.status().wire_value(), None);
+
+        let mut bb = ByteBufferMut::new();
+        assert!(matches!(
+            account.encode(&mut bb),
+            Err(KiwiError::InvalidEnumVariant(ref name)) if name == "SUSPENDED"
+        ));
+        assert_eq!(
+            account.to_kiwi().try_encode(&schema()),
+            Err(EncodeError::UnknownVariant { definition: "Status".into(), variant: "".into() })
+        );
+
+        let known = Account { id: Some(7), status: Some(Status::Retired), ..Account::default() };
+        let mut bb = ByteBufferMut::new();
+        known.encode(&mut bb).unwrap();
+        assert_eq!(bb.data(), known.to_kiwi().encode(&schema()));
+    }
+
+    #[test]
+    fn zero_copy_strings_borrow_from_the_decoded_value() {
+        use features::{schema, Account, Profile};
+        use std::borrow::Cow;
+
+        let account = Account {
+            name: Some("ada".into()),
+            profile: Some(Profile { bio: "analyst".into(), links: vec!["a.example".into()] }),
+            ..Account::default()
+        };
+        let schema = schema();
+        let bytes = account.to_kiwi().encode(&schema);
+        let value = Value::decode(&schema, schema.def_name_to_index["Account"] as i32, &bytes).unwrap();
+
+        let borrowed = Account::from_kiwi_ref(&value).unwrap();
+        assert_eq!(borrowed, account);
+        assert!(matches!(borrowed.name, Some(Cow::Borrowed("ada"))));
+        let profile = borrowed.profile.unwrap();
+        assert!(matches!(profile.bio, Cow::Borrowed("analyst")));
+        assert!(matches!(profile.links[0], Cow::Borrowed("a.example")));
+
+        // `FromKiwi` still copies.
+        let owned = Account::from_kiwi(&value).unwrap();
+        assert!(matches!(owned.name, Some(Cow::Owned(_))));
+    }
+
+    #[test]
+    fn skip_empty_vecs_leaves_empty_arrays_out_of_json() {
+        use features::Profile;
+
+        let profile = Profile { bio: "analyst".into(), links: Vec::new() };
+        assert_eq!(serde_json::to_value(&profile).unwrap(), serde_json::json!({ "bio": "analyst" }));
+
+        let profile = Profile { links: vec!["a.example".into()], ..profile };
+        assert_eq!(
+            serde_json::to_value(&profile).unwrap(),
+            serde_json::json!({ "bio": "analyst", "links": ["a.example"] })
+        );
+    }
+
+    #[test]
+    fn bytes_crate_fields_round_trip() {
+        use features::{schema, Account};
+
+        let account = Account { avatar: Some(bytes::Bytes::from_static(&[0, 1, 254, 255])), ..Account::default() };
+
+        let mut bb = ByteBufferMut::new();
+        account.encode(&mut bb).unwrap();
+        let bytes = bb.data();
+        assert_eq!(bytes, account.to_kiwi().encode(&schema()));
+
+        let decoded = Account::from_kiwi_bytes(&schema(), &bytes).unwrap();
+        assert_eq!(decoded.avatar(), &[0, 1, 254, 255]);
+        assert_eq!(decoded, account);
+    }
+}
@@ -0,0 +1,63 @@
+//! Compares `Value::decode` with `Value::decode_into` on an array of many
+//! small records. Run with `cargo bench -p brine-kiwi-schema`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use brine_kiwi_schema::*;
+
+const RECORDS: usize = 100_000;
+
+fn schema() -> Schema {
+    Schema::new(vec![
+        Def::new("Record".to_owned(), DefKind::Struct, vec![
+            Field { name: "id".to_owned(), type_id: TYPE_UINT, array_depth: 0, key_type_id: None, value: 1 },
+            Field { name: "x".to_owned(), type_id: TYPE_FLOAT, array_depth: 0, key_type_id: None, value: 2 },
+            Field { name: "y".to_owned(), type_id: TYPE_FLOAT, array_depth: 0, key_type_id: None, value: 3 },
+            Field { name: "label".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 4 },
+        ]),
+        Def::new("Batch".to_owned(), DefKind::Message, vec![
+            Field { name: "records".to_owned(), type_id: 0, array_depth: 1, key_type_id: None, value: 1 },
+        ]),
+    ])
+}
+
+fn batch(schema: &Schema) -> Vec<u8> {
+    let records = (0..RECORDS)
+        .map(|i| {
+            let mut fields = Map::new();
+            fields.insert("id", Value::UInt(i as u32));
+            fields.insert("x", Value::Float(i as f32 * 0.5));
+            fields.insert("y", Value::Float(-(i as f32)));
+            fields.insert("label", Value::String(format!("record {}", i)));
+            Value::Object("Record", fields)
+        })
+        .collect();
+    let mut fields = Map::new();
+    fields.insert("records", Value::Array(records));
+    Value::Object("Batch", fields).encode(schema)
+}
+
+fn decode_into(c: &mut Criterion) {
+    let schema = schema();
+    let bytes = batch(&schema);
+
+    let mut group = c.benchmark_group(format!("{} records", RECORDS));
+    group.sample_size(10);
+
+    group.bench_function("decode", |b| {
+        b.iter(|| black_box(Value::decode(&schema, 1, &bytes).unwrap()))
+    });
+
+    let mut value = Value::decode(&schema, 1, &bytes).unwrap();
+    group.bench_function("decode_into", |b| {
+        b.iter(|| {
+            value.decode_into(&schema, 1, &bytes).unwrap();
+            black_box(&value);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode_into);
+criterion_main!(benches);
@@ -1,6 +1,8 @@
-use std::borrow::Cow;
-use std::f32;
-use std::str;
+use alloc::borrow::Cow;
+use core::str;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
 /// A Kiwi byte buffer meant for reading.
 ///
@@ -22,7 +24,7 @@ impl<'a> ByteBuffer<'a> {
     /// Create a new ByteBuffer that wraps the provided byte slice. The lifetime
     /// of the returned ByteBuffer must not outlive the lifetime of the byte
     /// slice.
-    pub fn new(data: &[u8]) -> ByteBuffer {
+    pub fn new(data: &[u8]) -> ByteBuffer<'_> {
         ByteBuffer { data, index: 0 }
     }
 
@@ -38,6 +40,35 @@ impl<'a> ByteBuffer<'a> {
         self.index
     }
 
+    /// Returns how many bytes are left to read after the current index.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.index
+    }
+
+    /// Returns the current index, to hand back to [`reset_to`](Self::reset_to)
+    /// after reading ahead.
+    pub fn mark(&self) -> usize {
+        self.index
+    }
+
+    /// Moves the read position back (or forward) to `index`, usually one
+    /// returned by [`mark`](Self::mark). Fails without moving if `index` is
+    /// past the end of the data.
+    pub fn reset_to(&mut self, index: usize) -> Result<(), ()> {
+        if index > self.data.len() {
+            Err(())
+        } else {
+            self.index = index;
+            Ok(())
+        }
+    }
+
+    /// Returns the byte at the current index without consuming it, or `None`
+    /// at the end of the data.
+    pub fn peek_byte(&self) -> Option<u8> {
+        self.data.get(self.index).copied()
+    }
+
     /// Try to read a boolean value starting at the current index.
     pub fn read_bool(&mut self) -> Result<bool, ()> {
         match self.read_byte() {
@@ -53,22 +84,37 @@ impl<'a> ByteBuffer<'a> {
             Err(())
         } else {
             let value = self.data[self.index];
-            self.index = self.index + 1;
+            self.index += 1;
             Ok(value)
         }
     }
 
     /// Try to read a byte starting at the current index.
     pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ()> {
-        if self.index + len > self.data.len() {
+        if len > self.remaining() {
             Err(())
         } else {
             let value = &self.data[self.index..self.index + len];
-            self.index = self.index + len;
+            self.index += len;
             Ok(value)
         }
     }
 
+    /// Try to read a `var_uint` length followed by that many bytes, returning
+    /// a new buffer over just those bytes. This splits concatenated
+    /// length-prefixed messages apart. Fails without moving if the length
+    /// runs past the end of the data.
+    pub fn read_length_prefixed(&mut self) -> Result<ByteBuffer<'a>, ()> {
+        let start = self.index;
+        match self.read_var_uint().and_then(|len| self.read_bytes(len as usize)) {
+            Ok(data) => Ok(ByteBuffer { data, index: 0 }),
+            Err(()) => {
+                self.index = start;
+                Err(())
+            }
+        }
+    }
+
     /// Try to read a variable-length signed 32-bit integer starting at the
     /// current index.
     pub fn read_var_int(&mut self) -> Result<i32, ()> {
@@ -81,17 +127,21 @@ impl<'a> ByteBuffer<'a> {
     }
 
     /// Try to read a variable-length unsigned 32-bit integer starting at the
-    /// current index.
+    /// current index. Fails on encodings that don't fit in 32 bits: a fifth
+    /// byte with its continuation bit set or with bits above the 32nd.
     pub fn read_var_uint(&mut self) -> Result<u32, ()> {
         let mut shift: u8 = 0;
         let mut result: u32 = 0;
 
         loop {
             let byte = self.read_byte()?;
+            if shift == 28 && byte > 15 {
+                return Err(());
+            }
             result |= ((byte & 127) as u32) << shift;
             shift += 7;
 
-            if (byte & 128) == 0 || shift >= 35 {
+            if (byte & 128) == 0 {
                 break;
             }
         }
@@ -119,14 +169,42 @@ impl<'a> ByteBuffer<'a> {
             self.index += 3;
 
             // Move the exponent back into place
-            bits = (bits << 23) | (bits >> 9);
+            bits = bits.rotate_left(23);
 
             Ok(f32::from_bits(bits))
         }
     }
 
+    /// Try to read a variable-length 64-bit floating-point number starting at
+    /// the current index. This is an extension to the Kiwi format.
+    pub fn read_var_float64(&mut self) -> Result<f64, ()> {
+        let first = self.read_byte()?;
+
+        // Optimization: use a single byte to store zero
+        if first == 0 {
+            Ok(0.0)
+        } else if self.index + 7 > self.data.len() {
+            Err(())
+        }
+        // Endian-independent 64-bit read
+        else {
+            let mut bits = first as u64;
+            for i in 0..7 {
+                bits |= (self.data[self.index + i] as u64) << (8 * (i + 1));
+            }
+            self.index += 7;
+
+            // Move the exponent back into place
+            bits = bits.rotate_left(55);
+
+            Ok(f64::from_bits(bits))
+        }
+    }
+
     /// Try to read a UTF-8 string starting at the current index. This string is
-    /// returned as a slice so it just aliases the underlying memory.
+    /// returned as a slice so it just aliases the underlying memory. Invalid
+    /// UTF-8 is replaced with U+FFFD; use [`read_str`](Self::read_str) to
+    /// reject it instead.
     pub fn read_string(&mut self) -> Result<Cow<'a, str>, ()> {
         let start = self.index;
 
@@ -142,6 +220,18 @@ impl<'a> ByteBuffer<'a> {
         Err(())
     }
 
+    /// Try to read a UTF-8 string starting at the current index, borrowing it
+    /// from the underlying memory. Unlike [`read_string`](Self::read_string),
+    /// invalid UTF-8 is an error rather than replaced, so this never
+    /// allocates. On error the current index is left unchanged.
+    pub fn read_str(&mut self) -> Result<&'a str, ()> {
+        let rest = &self.data[self.index..];
+        let len = rest.iter().position(|&b| b == 0).ok_or(())?;
+        let text = str::from_utf8(&rest[..len]).map_err(|_| ())?;
+        self.index += len + 1;
+        Ok(text)
+    }
+
     /// Try to read a variable-length signed 64-bit integer starting at the
     /// current index.
     pub fn read_var_int64(&mut self) -> Result<i64, ()> {
@@ -154,7 +244,8 @@ impl<'a> ByteBuffer<'a> {
     }
 
     /// Try to read a variable-length unsigned 64-bit integer starting at the
-    /// current index.
+    /// current index. The ninth byte carries eight data bits and no
+    /// continuation bit, so an encoding never runs past nine bytes.
     pub fn read_var_uint64(&mut self) -> Result<u64, ()> {
         let mut shift: u8 = 0;
         let mut result: u64 = 0;
@@ -271,6 +362,22 @@ fn read_var_float() {
     assert_eq!(read(&[255, 0, 0, 128]).map(|f| f.is_nan()), Ok(true));
 }
 
+#[test]
+fn read_var_float64() {
+    let read = |bytes| ByteBuffer::new(bytes).read_var_float64();
+    assert_eq!(read(&[]), Err(()));
+    assert_eq!(read(&[0]), Ok(0.0));
+    assert_eq!(read(&[128, 238, 124, 63]), Err(()));
+    assert_eq!(read(&[128, 238, 124, 63, 53, 94, 186, 189]), Ok(123.456));
+    assert_eq!(read(&[128, 239, 124, 63, 53, 94, 186, 189]), Ok(-123.456));
+    assert_eq!(read(&[255, 255, 255, 255, 255, 255, 255, 223]), Ok(f64::MIN));
+    assert_eq!(read(&[255, 254, 255, 255, 255, 255, 255, 223]), Ok(f64::MAX));
+    assert_eq!(read(&[3, 178, 230, 241, 133, 63, 220, 74]), Ok(1.0e-300));
+    assert_eq!(read(&[255, 1, 0, 0, 0, 0, 0, 224]), Ok(f64::NEG_INFINITY));
+    assert_eq!(read(&[255, 0, 0, 0, 0, 0, 0, 224]), Ok(f64::INFINITY));
+    assert_eq!(read(&[255, 0, 0, 0, 0, 0, 0, 240]).map(|f| f.is_nan()), Ok(true));
+}
+
 #[test]
 fn read_string() {
     let read = |bytes| ByteBuffer::new(bytes).read_string();
@@ -286,6 +393,22 @@ fn read_string() {
     );
 }
 
+#[test]
+fn read_str() {
+    let read = |bytes| ByteBuffer::new(bytes).read_str();
+    assert_eq!(read(&[]), Err(()));
+    assert_eq!(read(&[0]), Ok(""));
+    assert_eq!(read(&[97]), Err(()));
+    assert_eq!(read(&[97, 98, 99, 0]), Ok("abc"));
+    assert_eq!(read(&[240, 159, 141, 149, 0]), Ok("🍕"));
+    assert_eq!(read(&[97, 237, 160, 188, 99, 0]), Err(()));
+
+    let mut bb = ByteBuffer::new(&[97, 0, 255, 0]);
+    assert_eq!(bb.read_str(), Ok("a"));
+    assert_eq!(bb.read_str(), Err(()));
+    assert_eq!(bb.index(), 2);
+}
+
 #[test]
 fn read_var_int64() {
     let read = |bytes| ByteBuffer::new(bytes).read_var_int64();
@@ -426,17 +549,74 @@ impl ByteBufferMut {
         ByteBufferMut { data: vec![] }
     }
 
+    /// Creates an empty ByteBufferMut with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> ByteBufferMut {
+        ByteBufferMut { data: Vec::with_capacity(capacity) }
+    }
+
     /// Consumes this buffer and returns the underlying backing store. Use this
     /// to get the data out when you're done writing to the buffer.
     pub fn data(self) -> Vec<u8> {
         self.data
     }
 
+    /// Makes room for at least `additional` more bytes without writing them.
+    pub fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+
     /// Returns the number of bytes written so far.
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    /// Returns the bytes written so far without consuming the buffer.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Discards everything written so far but keeps the allocation, so one
+    /// buffer can be reused across many encodes.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Returns the bytes written so far, allowing earlier bytes to be
+    /// overwritten in place.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Overwrite a previously written byte. Panics if `index` is out of bounds.
+    pub fn set_byte(&mut self, index: usize, value: u8) {
+        self.data[index] = value;
+    }
+
+    /// Write a placeholder for a variable-length unsigned 32-bit integer and
+    /// return its offset. Fill it in later with `patch_var_uint`, e.g. to
+    /// backpatch a length prefix once the payload has been written.
+    ///
+    /// The placeholder always takes the maximum of five bytes so that any
+    /// `u32` fits; padded varints decode the same as their short form.
+    pub fn reserve_var_uint(&mut self) -> usize {
+        let offset = self.data.len();
+        self.data.extend_from_slice(&[128, 128, 128, 128, 0]);
+        offset
+    }
+
+    /// Fill in a placeholder written by `reserve_var_uint`. Panics if the
+    /// placeholder at `offset` is out of bounds.
+    pub fn patch_var_uint(&mut self, offset: usize, mut value: u32) {
+        let slot = &mut self.data[offset..offset + 5];
+        for (i, byte) in slot.iter_mut().enumerate() {
+            *byte = value as u8 & 127;
+            value >>= 7;
+            if i < 4 {
+                *byte |= 128;
+            }
+        }
+    }
+
     /// Write a boolean value to the end of the buffer.
     pub fn write_bool(&mut self, value: bool) {
         self.data.push(if value { 1 } else { 0 });
@@ -452,6 +632,22 @@ impl ByteBufferMut {
         self.data.extend_from_slice(value);
     }
 
+    /// Append an already-encoded fragment to the end of the buffer. Same as
+    /// `write_bytes`, for call sites that stitch encoded values together.
+    pub fn append(&mut self, other: &[u8]) {
+        self.write_bytes(other);
+    }
+
+    /// Consume another buffer and append its data to the end of this one. If
+    /// this buffer is still empty, the other buffer's storage is reused as-is.
+    pub fn append_buffer(&mut self, other: ByteBufferMut) {
+        if self.data.is_empty() {
+            self.data = other.data;
+        } else {
+            self.data.extend_from_slice(&other.data);
+        }
+    }
+
     /// Write a variable-length signed 32-bit integer to the end of the buffer.
     pub fn write_var_int(&mut self, value: i32) {
         self.write_var_uint(((value << 1) ^ (value >> 31)) as u32);
@@ -479,7 +675,7 @@ impl ByteBufferMut {
         let mut bits = value.to_bits();
 
         // Move the exponent to the first 8 bits
-        bits = (bits >> 23) | (bits << 9);
+        bits = bits.rotate_right(23);
 
         // Optimization: use a single byte to store zero and denormals (try for an exponent of 0)
         if (bits & 255) == 0 {
@@ -496,6 +692,28 @@ impl ByteBufferMut {
         ]);
     }
 
+    /// Write a variable-length 64-bit floating-point number to the end of the
+    /// buffer. This is an extension to the Kiwi format. Like
+    /// [write_var_float](#method.write_var_float) it flushes the smallest
+    /// values to zero: here, anything below `2^-1015` in magnitude.
+    pub fn write_var_float64(&mut self, value: f64) {
+        // Reinterpret as an integer
+        let mut bits = value.to_bits();
+
+        // Move the top 8 bits of the exponent to the first byte
+        bits = bits.rotate_right(55);
+
+        // Optimization: use a single byte to store zero, denormals and the
+        // seven smallest exponents (try for a first byte of 0)
+        if (bits & 255) == 0 {
+            self.data.push(0);
+            return;
+        }
+
+        // Endian-independent 64-bit write
+        self.data.extend_from_slice(&bits.to_le_bytes());
+    }
+
     /// Write a UTF-8 string to the end of the buffer.
     pub fn write_string(&mut self, value: &str) {
         self.data.extend_from_slice(value.as_bytes());
@@ -519,6 +737,57 @@ impl ByteBufferMut {
     }
 }
 
+/// The number of bytes [write_var_uint](struct.ByteBufferMut.html#method.write_var_uint)
+/// uses for `value`.
+pub(crate) fn var_uint_size(value: u32) -> usize {
+    var_uint64_size(value as u64)
+}
+
+/// The number of bytes [write_var_uint64](struct.ByteBufferMut.html#method.write_var_uint64)
+/// uses for `value`. The ninth byte holds a full 8 bits.
+pub(crate) fn var_uint64_size(value: u64) -> usize {
+    let mut size = 1;
+    let mut value = value;
+    while value > 127 && size < 9 {
+        value >>= 7;
+        size += 1;
+    }
+    size
+}
+
+/// The number of bytes [write_var_float](struct.ByteBufferMut.html#method.write_var_float)
+/// uses for `value`.
+pub(crate) fn var_float_size(value: f32) -> usize {
+    if value.to_bits().rotate_right(23) & 255 == 0 { 1 } else { 4 }
+}
+
+/// The number of bytes [write_var_float64](struct.ByteBufferMut.html#method.write_var_float64)
+/// uses for `value`.
+pub(crate) fn var_float64_size(value: f64) -> usize {
+    if value.to_bits().rotate_right(55) & 255 == 0 { 1 } else { 8 }
+}
+
+#[test]
+fn var_sizes_match_writes() {
+    let written = |write: &dyn Fn(&mut ByteBufferMut)| {
+        let mut bb = ByteBufferMut::new();
+        write(&mut bb);
+        bb.len()
+    };
+    for value in [0, 1, 127, 128, 16383, 16384, u32::MAX] {
+        assert_eq!(var_uint_size(value), written(&|bb| bb.write_var_uint(value)));
+    }
+    for value in [0, 127, 128, (1 << 56) - 1, 1 << 56, u64::MAX] {
+        assert_eq!(var_uint64_size(value), written(&|bb| bb.write_var_uint64(value)));
+    }
+    for value in [0.0, -0.0, 1.0e-40, 123.456, f32::MIN, f32::INFINITY] {
+        assert_eq!(var_float_size(value), written(&|bb| bb.write_var_float(value)));
+    }
+    for value in [0.0, -0.0, 1.0e-310, 123.456, f64::MIN, f64::INFINITY] {
+        assert_eq!(var_float64_size(value), written(&|bb| bb.write_var_float64(value)));
+    }
+}
+
 #[cfg(test)]
 fn write_once(cb: fn(&mut ByteBufferMut)) -> Vec<u8> {
     let mut bb = ByteBufferMut::new();
@@ -647,6 +916,56 @@ fn write_var_float() {
     assert_eq!(write_once(|bb| bb.write_var_float(1.0e-40)), [0]);
 }
 
+#[test]
+fn write_var_float64() {
+    assert_eq!(write_once(|bb| bb.write_var_float64(0.0)), [0]);
+    assert_eq!(write_once(|bb| bb.write_var_float64(-0.0)), [0]);
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(123.456)),
+        [128, 238, 124, 63, 53, 94, 186, 189]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(-123.456)),
+        [128, 239, 124, 63, 53, 94, 186, 189]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(f64::MIN)),
+        [255, 255, 255, 255, 255, 255, 255, 223]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(f64::MAX)),
+        [255, 254, 255, 255, 255, 255, 255, 223]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(1.0e-300)),
+        [3, 178, 230, 241, 133, 63, 220, 74]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(f64::NEG_INFINITY)),
+        [255, 1, 0, 0, 0, 0, 0, 224]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(f64::INFINITY)),
+        [255, 0, 0, 0, 0, 0, 0, 224]
+    );
+    assert_eq!(
+        write_once(|bb| bb.write_var_float64(f64::NAN)),
+        [255, 0, 0, 0, 0, 0, 0, 240]
+    );
+    assert_eq!(write_once(|bb| bb.write_var_float64(f64::MIN_POSITIVE)), [0]);
+    assert_eq!(write_once(|bb| bb.write_var_float64(1.0e-310)), [0]);
+}
+
+#[test]
+fn var_float64_round_trip() {
+    for value in [1.0, -1.0, 0.1, 1.0e-300, 6.02214076e23, f64::MIN, f64::MAX, f64::EPSILON] {
+        let mut bb = ByteBufferMut::new();
+        bb.write_var_float64(value);
+        let data = bb.data();
+        assert_eq!(ByteBuffer::new(&data).read_var_float64(), Ok(value));
+    }
+}
+
 #[test]
 fn write_string() {
     assert_eq!(write_once(|bb| bb.write_string("")), [0]);
@@ -777,3 +1096,149 @@ fn write_sequence() {
         [0, 133, 242, 210, 237, 240, 159, 141, 149, 0, 149, 154, 239, 58]
     );
 }
+
+#[test]
+fn append_fragments() {
+    let mut first = ByteBufferMut::new();
+    first.write_var_uint(300);
+
+    let mut second = ByteBufferMut::new();
+    second.write_string("hi");
+
+    let mut bb = ByteBufferMut::new();
+    bb.append_buffer(first);
+    bb.append(&[7]);
+    bb.append_buffer(second);
+    assert_eq!(bb.data(), [172, 2, 7, 104, 105, 0]);
+}
+
+#[test]
+fn clear_keeps_capacity() {
+    let mut bb = ByteBufferMut::with_capacity(64);
+    bb.write_string("first");
+    assert_eq!(bb.as_slice(), b"first\0");
+
+    let capacity = bb.data.capacity();
+    bb.clear();
+    assert_eq!(bb.len(), 0);
+    assert_eq!(bb.data.capacity(), capacity);
+
+    bb.write_var_uint(300);
+    assert_eq!(bb.as_slice(), [172, 2]);
+}
+
+#[test]
+fn patch_reserved_var_uint() {
+    let mut bb = ByteBufferMut::new();
+    let offset = bb.reserve_var_uint();
+    bb.write_string("hello");
+    let len = (bb.len() - offset - 5) as u32;
+    bb.patch_var_uint(offset, len);
+    bb.set_byte(5, b'j');
+    bb.as_mut_slice()[6] = b'E';
+
+    let data = bb.data();
+    assert_eq!(data, [134, 128, 128, 128, 0, 106, 69, 108, 108, 111, 0]);
+
+    let mut read = ByteBuffer::new(&data);
+    assert_eq!(read.read_var_uint(), Ok(6));
+    assert_eq!(read.read_string().as_deref(), Ok("jEllo"));
+
+    let mut bb = ByteBufferMut::new();
+    let offset = bb.reserve_var_uint();
+    bb.patch_var_uint(offset, u32::MAX);
+    assert_eq!(ByteBuffer::new(&bb.data()).read_var_uint(), Ok(u32::MAX));
+}
+
+#[test]
+fn read_var_uint_rejects_oversized() {
+    let read = |bytes| ByteBuffer::new(bytes).read_var_uint();
+    assert_eq!(read(&[255, 255, 255, 255, 255]), Err(()));
+    assert_eq!(read(&[255, 255, 255, 255, 255, 1]), Err(()));
+    assert_eq!(read(&[128, 128, 128, 128, 128, 0]), Err(()));
+    assert_eq!(read(&[255, 255, 255, 255, 16]), Err(()));
+    assert_eq!(read(&[255, 255, 255, 255, 15]), Ok(u32::MAX));
+    assert_eq!(ByteBuffer::new(&[255, 255, 255, 255, 255]).read_var_int(), Err(()));
+}
+
+#[test]
+fn read_var_uint64_stops_after_nine_bytes() {
+    let mut bb = ByteBuffer::new(&[255, 255, 255, 255, 255, 255, 255, 255, 255, 1]);
+    assert_eq!(bb.read_var_uint64(), Ok(u64::MAX));
+    assert_eq!(bb.index(), 9);
+}
+
+#[test]
+fn var_int64_round_trip() {
+    // The boundaries, every single-bit value and its neighbours (which cross
+    // each byte boundary, including the eight-bit ninth byte), then a fixed
+    // xorshift sequence.
+    let mut values = vec![0, 1, -1, i64::MIN, i64::MIN + 1, i64::MAX, i64::MAX - 1];
+    for bit in 0..64 {
+        let value = 1i64 << bit;
+        values.extend([value, value.wrapping_sub(1), value.wrapping_add(1), value.wrapping_neg(), !value]);
+    }
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    for _ in 0..10_000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        values.push(state as i64);
+    }
+
+    for value in values {
+        let mut bb = ByteBufferMut::new();
+        bb.write_var_int64(value);
+        let data = bb.data();
+        assert!(data.len() <= 9, "{} took {} bytes", value, data.len());
+        let mut read = ByteBuffer::new(&data);
+        assert_eq!(read.read_var_int64(), Ok(value));
+        assert_eq!(read.index(), data.len(), "{} left bytes unread", value);
+
+        let mut bb = ByteBufferMut::new();
+        bb.write_var_uint64(value as u64);
+        let data = bb.data();
+        assert_eq!(data.len(), var_uint64_size(value as u64));
+        assert_eq!(ByteBuffer::new(&data).read_var_uint64(), Ok(value as u64));
+    }
+}
+
+#[test]
+fn mark_peek_and_reset() {
+    let mut bb = ByteBuffer::new(&[3, 0]);
+    let mark = bb.mark();
+    assert_eq!(bb.peek_byte(), Some(3));
+    assert_eq!(bb.read_var_uint(), Ok(3));
+    assert_eq!(bb.peek_byte(), Some(0));
+    assert_eq!(bb.reset_to(mark), Ok(()));
+    assert_eq!(bb.index(), 0);
+    assert_eq!(bb.reset_to(2), Ok(()));
+    assert_eq!(bb.peek_byte(), None);
+    assert_eq!(bb.reset_to(3), Err(()));
+    assert_eq!(bb.index(), 2);
+}
+
+#[test]
+fn read_bytes_rejects_huge_lengths() {
+    let mut bb = ByteBuffer::new(&[1, 2, 3]);
+    assert_eq!(bb.read_byte(), Ok(1));
+    assert_eq!(bb.remaining(), 2);
+    assert_eq!(bb.read_bytes(usize::MAX), Err(()));
+    assert_eq!(bb.read_bytes(3), Err(()));
+    assert_eq!(bb.read_bytes(2), Ok(&[2, 3][..]));
+    assert_eq!(bb.remaining(), 0);
+}
+
+#[test]
+fn read_length_prefixed_frames() {
+    let mut bb = ByteBuffer::new(&[2, 1, 0, 0, 3, 7]);
+    let mut first = bb.read_length_prefixed().unwrap();
+    assert_eq!(first.data(), [1, 0]);
+    assert_eq!(first.read_var_uint(), Ok(1));
+    assert_eq!(bb.read_length_prefixed().map(|b| b.data().len()), Ok(0));
+
+    // The last frame claims three bytes but only one is left
+    let at = bb.index();
+    assert!(bb.read_length_prefixed().is_err());
+    assert_eq!(bb.index(), at);
+}
@@ -7,8 +7,8 @@
 //!
 //! let schema = Schema::new(vec![
 //!     Def::new("Point".to_owned(), DefKind::Struct, vec![
-//!         Field {name: "x".to_owned(), type_id: TYPE_FLOAT, is_array: false, value: 0},
-//!         Field {name: "y".to_owned(), type_id: TYPE_FLOAT, is_array: false, value: 0},
+//!         Field {name: "x".to_owned(), type_id: TYPE_FLOAT, array_depth: 0, key_type_id: None, value: 0},
+//!         Field {name: "y".to_owned(), type_id: TYPE_FLOAT, array_depth: 0, key_type_id: None, value: 0},
 //!     ]),
 //! ]);
 //!
@@ -17,6 +17,28 @@
 //! assert_eq!(value.encode(&schema), [126, 0, 0, 0, 126, 1, 0, 0]);
 //! ```
 
+// The `Result<_, ()>` read API and the `new()`/`len()` helpers mirror the
+// upstream kiwi crate and are kept for compatibility.
+#![allow(clippy::result_unit_err, clippy::new_without_default, clippy::len_without_is_empty)]
+// Without the default `std` feature only `core` and `alloc` are used, for
+// embedded and wasm targets without `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// The `std` prelude items the crate uses, which builds without `std` must
+/// import.
+#[cfg(not(feature = "std"))]
+mod prelude {
+    pub use alloc::{
+        borrow::ToOwned,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+}
+
 pub mod bb;
 pub mod schema;
 pub mod value;
@@ -25,6 +47,16 @@ pub use bb::*;
 pub use schema::*;
 pub use value::*;
 
+/// The map behind `Value::Object` and the `Schema` indexes. `HashMap` needs
+/// `std`, so without the `std` feature this is a `BTreeMap`; code meant to
+/// build either way should name `Map` rather than either type.
+#[cfg(feature = "std")]
+pub type Map<K, V> = std::collections::HashMap<K, V>;
+/// The map behind `Value::Object` and the `Schema` indexes: a `BTreeMap`,
+/// since `HashMap` needs `std`.
+#[cfg(not(feature = "std"))]
+pub type Map<K, V> = alloc::collections::BTreeMap<K, V>;
+
 pub const TYPE_BOOL: i32 = -1;
 pub const TYPE_BYTE: i32 = -2;
 pub const TYPE_INT: i32 = -3;
@@ -33,3 +65,4 @@ pub const TYPE_FLOAT: i32 = -5;
 pub const TYPE_STRING: i32 = -6;
 pub const TYPE_INT64: i32 = -7;
 pub const TYPE_UINT64: i32 = -8;
+pub const TYPE_FLOAT64: i32 = -9;
@@ -1,8 +1,11 @@
-use std::collections::HashMap;
-use std::str;
+use core::str;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
 use crate::{
-    TYPE_INT, TYPE_UINT, TYPE_FLOAT, TYPE_STRING, TYPE_INT64, TYPE_UINT64, TYPE_BOOL, TYPE_BYTE, 
+    Map,
+    TYPE_INT, TYPE_UINT, TYPE_FLOAT, TYPE_STRING, TYPE_INT64, TYPE_UINT64, TYPE_FLOAT64, TYPE_BOOL, TYPE_BYTE, 
     bb::{ ByteBuffer, ByteBufferMut}, 
 };
 
@@ -24,12 +27,20 @@ pub struct Field {
     /// * [TYPE_STRING](constant.TYPE_STRING.html)
     /// * [TYPE_INT64](constant.TYPE_INT64.html)
     /// * [TYPE_UINT64](constant.TYPE_UINT64.html)
+    /// * [TYPE_FLOAT64](constant.TYPE_FLOAT64.html)
     pub type_id: i32,
 
-    /// True if this field was declared as an array (e.g. `int[]` instead of
-    /// `int` in the textual Kiwi schema). Arrays are encoded using a length
-    /// prefix followed by that many items.
-    pub is_array: bool,
+    /// How many `[]` this field was declared with: 0 for a single value, 1
+    /// for an array (e.g. `int[]` instead of `int` in the textual Kiwi schema),
+    /// 2 for an array of arrays (`int[][]`) and so on. Arrays are encoded using
+    /// a length prefix followed by that many items.
+    pub array_depth: u32,
+
+    /// For a `map<K, V>` field, the type id of `K`; `type_id` and
+    /// `array_depth` then describe `V`. Maps are encoded as a length prefix
+    /// followed by that many key/value pairs. This is an extension to the
+    /// Kiwi format.
+    pub key_type_id: Option<i32>,
 
     /// The identifier corresponding to this field. This is the enum value for
     /// enum definitions and the field id for message definitions. This value has
@@ -37,6 +48,22 @@ pub struct Field {
     pub value: u32,
 }
 
+impl Field {
+    /// Returns true if this field is an array, of any depth.
+    pub fn is_array(&self) -> bool {
+        self.array_depth > 0
+    }
+
+    /// Returns true if this field is a `map<K, V>`.
+    pub fn is_map(&self) -> bool {
+        self.key_type_id.is_some()
+    }
+}
+
+/// Set in the `array_depth` var_uint of a map field, which is then followed
+/// by the key's type id. Still a single byte for the depths schemas use.
+pub const MAP_FIELD_FLAG: u32 = 1 << 6;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum DefKind {
     /// Enums are encoded as variable-length unsigned integers under the hood.
@@ -104,14 +131,14 @@ pub struct Def {
     /// the `fields` array to its index in that array. This is helpful when
     /// decoding and encoding a field to be able to quickly get to the field
     /// metadata.
-    pub field_value_to_index: HashMap<u32, usize>,
-    pub field_name_to_index: HashMap<String, usize>,
+    pub field_value_to_index: Map<u32, usize>,
+    pub field_name_to_index: Map<String, usize>,
 }
 
 impl Def {
     pub fn new(name: String, kind: DefKind, fields: Vec<Field>) -> Def {
-        let mut field_value_to_index = HashMap::new();
-        let mut field_name_to_index = HashMap::new();
+        let mut field_value_to_index = Map::new();
+        let mut field_name_to_index = Map::new();
         for (i, field) in fields.iter().enumerate() {
             field_value_to_index.insert(field.value, i);
             field_name_to_index.insert(field.name.clone(), i);
@@ -132,6 +159,42 @@ impl Def {
     }
 }
 
+/// What to do when a wire integer doesn't fit the (narrower) type the schema
+/// declares, e.g. an `int64` value read back through a field that is now an
+/// `int` after a schema change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericOverflowPolicy {
+    /// Fail the decode.
+    #[default]
+    Error,
+
+    /// Clamp to the nearest value the narrower type can hold.
+    Saturate,
+
+    /// Keep the low bits, like an `as` cast.
+    Wrap,
+}
+
+impl NumericOverflowPolicy {
+    /// Narrows a decoded 64-bit signed value to an `i32` according to this policy.
+    pub fn narrow_i32(self, value: i64) -> Result<i32, ()> {
+        match self {
+            NumericOverflowPolicy::Error => i32::try_from(value).map_err(|_| ()),
+            NumericOverflowPolicy::Saturate => Ok(value.clamp(i32::MIN as i64, i32::MAX as i64) as i32),
+            NumericOverflowPolicy::Wrap => Ok(value as i32),
+        }
+    }
+
+    /// Narrows a decoded 64-bit unsigned value to a `u32` according to this policy.
+    pub fn narrow_u32(self, value: u64) -> Result<u32, ()> {
+        match self {
+            NumericOverflowPolicy::Error => u32::try_from(value).map_err(|_| ()),
+            NumericOverflowPolicy::Saturate => Ok(value.min(u32::MAX as u64) as u32),
+            NumericOverflowPolicy::Wrap => Ok(value as u32),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct SchemaOptions {
     // The Rust implementation is the only one that validates enums while skipping fields.
@@ -139,6 +202,35 @@ pub struct SchemaOptions {
     // compatibility with other clients this can be used to allow enums to be decoded
     // even if they aren't valid.
     pub validate_enums: bool,
+
+    /// How [Value](enum.Value.html) decoding handles `int`/`uint` values that
+    /// were written with a wider type.
+    pub numeric_overflow: NumericOverflowPolicy,
+
+    /// How many structs, messages and arrays [Value](enum.Value.html)
+    /// decoding and [Schema::skip](struct.Schema.html#method.skip) may nest
+    /// before giving up, so that crafted input for a recursive schema can't
+    /// overflow the stack. Defaults to
+    /// [DEFAULT_MAX_DEPTH](constant.DEFAULT_MAX_DEPTH.html).
+    pub max_depth: usize,
+}
+
+/// The default [SchemaOptions::max_depth](struct.SchemaOptions.html#structfield.max_depth).
+pub const DEFAULT_MAX_DEPTH: usize = 500;
+
+/// The most elements an array of structs without fields may claim when
+/// decoding or skipping. Such elements take no bytes, so unlike other arrays
+/// their length can't be checked against the bytes that are left.
+pub const MAX_EMPTY_STRUCT_ARRAY_LEN: usize = 1 << 16;
+
+impl Default for SchemaOptions {
+    fn default() -> SchemaOptions {
+        SchemaOptions {
+            validate_enums: true,
+            numeric_overflow: NumericOverflowPolicy::Error,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
 }
 
 /// Holds the contents of a Kiwi schema.
@@ -159,7 +251,7 @@ pub struct SchemaOptions {
 ///
 /// let field = def.field("xyz").unwrap();
 /// assert_eq!(field.type_id, brine_kiwi_schema::TYPE_INT);
-/// assert_eq!(field.is_array, true);
+/// assert!(field.is_array());
 /// assert_eq!(field.value, 1);
 /// ```
 #[derive(Debug, PartialEq)]
@@ -169,12 +261,12 @@ pub struct Schema {
     /// Maps the `name` member of each [Def](struct.Def.html) in the `defs` array
     /// to its index in that array. This is helpful when decoding and encoding a
     /// field to be able to quickly get to the field metadata.
-    pub def_name_to_index: HashMap<String, usize>,
+    pub def_name_to_index: Map<String, usize>,
 }
 
 impl Schema {
     pub fn new(mut defs: Vec<Def>) -> Schema {
-        let mut def_name_to_index = HashMap::new();
+        let mut def_name_to_index = Map::new();
         for (i, def) in defs.iter_mut().enumerate() {
             def.index = i as i32;
             def_name_to_index.insert(def.name.clone(), i);
@@ -198,7 +290,7 @@ impl Schema {
         let definition_count = bb.read_var_uint()?;
 
         for _ in 0..definition_count {
-            let name = bb.read_string()?.into_owned();
+            let name = bb.read_str()?.to_owned();
             let kind = match bb.read_byte()? {
                 DEF_ENUM => DefKind::Enum,
                 DEF_STRUCT => DefKind::Struct,
@@ -209,17 +301,34 @@ impl Schema {
             let mut fields = Vec::new();
 
             for _ in 0..field_count {
-                let name = bb.read_string()?.into_owned();
+                let name = bb.read_str()?.to_owned();
                 let type_id = bb.read_var_int()?;
-                let is_array = bb.read_bool()?;
+                // Written as a var_uint, so depths 0 and 1 are the bytes an
+                // older `is_array` bool would have been.
+                let mut array_depth = bb.read_var_uint()?;
+                let key_type_id = if array_depth & MAP_FIELD_FLAG != 0 {
+                    array_depth &= !MAP_FIELD_FLAG;
+                    let key_type_id = bb.read_var_int()?;
+                    if key_type_id < TYPE_FLOAT64 || key_type_id >= definition_count as i32 {
+                        return Err(());
+                    }
+                    Some(key_type_id)
+                } else {
+                    None
+                };
+                // Bits above the map flag are no depth the compiler writes.
+                if array_depth >= MAP_FIELD_FLAG {
+                    return Err(());
+                }
                 let value = bb.read_var_uint()?;
-                if type_id < TYPE_UINT64 || type_id >= definition_count as i32 {
+                if type_id < TYPE_FLOAT64 || type_id >= definition_count as i32 {
                     return Err(());
                 }
                 fields.push(Field {
                     name,
                     type_id,
-                    is_array,
+                    array_depth,
+                    key_type_id,
                     value,
                 });
             }
@@ -248,7 +357,13 @@ impl Schema {
             for field in &def.fields {
                 bb.write_string(field.name.as_str());
                 bb.write_var_int(field.type_id);
-                bb.write_bool(field.is_array);
+                match field.key_type_id {
+                    Some(key_type_id) => {
+                        bb.write_var_uint(field.array_depth | MAP_FIELD_FLAG);
+                        bb.write_var_int(key_type_id);
+                    }
+                    None => bb.write_var_uint(field.array_depth),
+                }
                 bb.write_var_uint(field.value);
             }
         }
@@ -261,6 +376,25 @@ impl Schema {
         self.def_name_to_index.get(name).map(|i| &self.defs[*i])
     }
 
+    /// Returns the name of the type with the provided id: the native type name
+    /// (`"bool"`, `"int"`, …) for negative ids and the [Def](struct.Def.html)
+    /// name otherwise, or `None` if no such type exists.
+    pub fn type_name(&self, type_id: i32) -> Option<&str> {
+        match type_id {
+            TYPE_BOOL => Some("bool"),
+            TYPE_BYTE => Some("byte"),
+            TYPE_INT => Some("int"),
+            TYPE_UINT => Some("uint"),
+            TYPE_FLOAT => Some("float"),
+            TYPE_STRING => Some("string"),
+            TYPE_INT64 => Some("int64"),
+            TYPE_UINT64 => Some("uint64"),
+            TYPE_FLOAT64 => Some("float64"),
+            _ if type_id < 0 => None,
+            _ => self.defs.get(type_id as usize).map(|def| def.name.as_str()),
+        }
+    }
+
     /// Advances the current index of the provided [ByteBuffer](struct.ByteBuffer.html)
     /// by the size of a field with the provided type information. The Kiwi format
     /// doesn't support seeking around to arbitrary points (it must be read from
@@ -271,6 +405,18 @@ impl Schema {
         bb: &mut ByteBuffer,
         type_id: i32,
         options: &SchemaOptions,
+    ) -> Result<(), ()> {
+        self.skip_inner(bb, type_id, options, 0)
+    }
+
+    /// Shared by the skip methods. `depth` counts the structs, messages and
+    /// arrays being skipped around this value.
+    fn skip_inner(
+        &self,
+        bb: &mut ByteBuffer,
+        type_id: i32,
+        options: &SchemaOptions,
+        depth: usize,
     ) -> Result<(), ()> {
         match type_id {
             TYPE_BOOL => {
@@ -297,9 +443,12 @@ impl Schema {
             TYPE_UINT64 => {
                 bb.read_var_uint64()?;
             }
+            TYPE_FLOAT64 => {
+                bb.read_var_float64()?;
+            }
 
             _ => {
-                let def = &self.defs[type_id as usize];
+                let def = self.defs.get(type_id as usize).ok_or(())?;
 
                 match def.kind {
                     DefKind::Enum => {
@@ -310,9 +459,11 @@ impl Schema {
                         }
                     }
 
+                    DefKind::Struct | DefKind::Message if depth >= options.max_depth => return Err(()),
+
                     DefKind::Struct => {
                         for field in &def.fields {
-                            self.skip_field_with_options(bb, field, options)?;
+                            self.skip_field_inner(bb, field, options, depth + 1)?;
                         }
                     }
 
@@ -321,8 +472,8 @@ impl Schema {
                         if value == 0 {
                             break;
                         }
-                        if let Some(index) = def.field_value_to_index.get(&value) {
-                            self.skip_field_with_options(bb, &def.fields[*index], options)?;
+                        if let Some(field) = def.field_value_to_index.get(&value).and_then(|i| def.fields.get(*i)) {
+                            self.skip_field_inner(bb, field, options, depth + 1)?;
                         } else {
                             return Err(());
                         }
@@ -338,9 +489,7 @@ impl Schema {
         self.skip_with_options(
             bb,
             type_id,
-            &SchemaOptions {
-                validate_enums: true,
-            },
+            &SchemaOptions::default(),
         )
     }
 
@@ -353,13 +502,70 @@ impl Schema {
         field: &Field,
         options: &SchemaOptions,
     ) -> Result<(), ()> {
-        if field.is_array {
+        self.skip_field_inner(bb, field, options, 0)
+    }
+
+    fn skip_field_inner(
+        &self,
+        bb: &mut ByteBuffer,
+        field: &Field,
+        options: &SchemaOptions,
+        depth: usize,
+    ) -> Result<(), ()> {
+        if let Some(key_type_id) = field.key_type_id {
             let len = bb.read_var_uint()? as usize;
+            if len > bb.remaining() {
+                return Err(());
+            }
             for _ in 0..len {
-                self.skip_with_options(bb, field.type_id, options)?;
+                self.skip_inner(bb, key_type_id, options, depth)?;
+                self.skip_array_inner(bb, field.type_id, field.array_depth, options, depth)?;
             }
-        } else {
-            self.skip_with_options(bb, field.type_id, options)?;
+            return Ok(());
+        }
+        self.skip_array_inner(bb, field.type_id, field.array_depth, options, depth)
+    }
+
+    /// Skips a value of `type_id` nested `array_depth` arrays deep. Each
+    /// array counts towards `options.max_depth` like a struct does.
+    fn skip_array_inner(
+        &self,
+        bb: &mut ByteBuffer,
+        type_id: i32,
+        array_depth: u32,
+        options: &SchemaOptions,
+        depth: usize,
+    ) -> Result<(), ()> {
+        if array_depth == 0 {
+            return self.skip_inner(bb, type_id, options, depth);
+        }
+        if depth >= options.max_depth {
+            return Err(());
+        }
+        let len = bb.read_var_uint()? as usize;
+        self.check_array_len(type_id, array_depth, len, bb)?;
+        for _ in 0..len {
+            self.skip_array_inner(bb, type_id, array_depth - 1, options, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the length `len` just read from `bb` for an array of `type_id`
+    /// nested `array_depth - 1` more arrays deep, before anything is
+    /// allocated or looped over. Every element takes at least a byte (an inner
+    /// array its length) except a struct without fields, so other arrays
+    /// can't be longer than what's left; arrays of empty structs are capped at
+    /// [MAX_EMPTY_STRUCT_ARRAY_LEN](constant.MAX_EMPTY_STRUCT_ARRAY_LEN.html).
+    pub(crate) fn check_array_len(&self, type_id: i32, array_depth: u32, len: usize, bb: &ByteBuffer) -> Result<(), ()> {
+        let empty_struct = array_depth == 1
+            && type_id >= 0
+            && self
+                .defs
+                .get(type_id as usize)
+                .is_some_and(|def| def.kind == DefKind::Struct && def.fields.is_empty());
+        let limit = if empty_struct { MAX_EMPTY_STRUCT_ARRAY_LEN } else { bb.remaining() };
+        if len > limit {
+            return Err(());
         }
         Ok(())
     }
@@ -368,9 +574,7 @@ impl Schema {
         self.skip_field_with_options(
             bb,
             field,
-            &SchemaOptions {
-                validate_enums: true,
-            },
+            &SchemaOptions::default(),
         )
     }
 }
@@ -393,11 +597,53 @@ mod tests {
                 vec![Field {
                     name: "xyz".to_owned(),
                     type_id: TYPE_INT,
-                    is_array: true,
+                    array_depth: 1,
+                    key_type_id: None,
                     value: 1
                 },]
             ),])
         );
         assert_eq!(schema.encode(), schema_bytes);
+
+        // Names must be valid UTF-8
+        assert!(Schema::decode(&[1, 65, 0xFF, 0, 2, 0]).is_err());
+
+        // Array depths stop below the map flag
+        assert_eq!(Schema::decode(&[1, 65, 0, 2, 1, 120, 0, 5, 63, 1]).unwrap().defs[0].fields[0].array_depth, 63);
+        assert!(Schema::decode(&[1, 65, 0, 2, 1, 120, 0, 5, 0x80, 1, 1]).is_err());
+        assert!(Schema::decode(&[1, 65, 0, 2, 1, 120, 0, 5, 0x80, 0x84, 0x3D, 1]).is_err());
+    }
+
+    #[test]
+    fn schema_map_field() {
+        // "message ABC { map<string, int> xyz = 1; }"
+        let schema_bytes = [1, 65, 66, 67, 0, 2, 1, 120, 121, 122, 0, 5, 64, 11, 1];
+        let schema = Schema::decode(&schema_bytes).unwrap();
+        let field = &schema.defs[0].fields[0];
+        assert!(field.is_map());
+        assert_eq!(field.key_type_id, Some(crate::TYPE_STRING));
+        assert_eq!((field.type_id, field.array_depth), (TYPE_INT, 0));
+        assert_eq!(schema.encode(), schema_bytes);
+
+        // Skipping a map reads its count and every pair
+        let mut bb = ByteBuffer::new(&[1, 2, 97, 0, 4, 98, 0, 6, 0, 9]);
+        assert_eq!(bb.read_var_uint(), Ok(1));
+        schema.skip_field(&mut bb, field).unwrap();
+        assert_eq!(bb.read_byte(), Ok(0));
+        assert_eq!(bb.read_byte(), Ok(9));
+
+        // An out-of-range key type is rejected
+        assert!(Schema::decode(&[1, 65, 0, 2, 1, 120, 0, 5, 64, 2, 1]).is_err());
+    }
+
+    #[test]
+    fn schema_type_name() {
+        let schema = Schema::new(vec![Def::new("ABC".to_owned(), DefKind::Message, vec![])]);
+        assert_eq!(schema.type_name(TYPE_INT), Some("int"));
+        assert_eq!(schema.type_name(TYPE_UINT64), Some("uint64"));
+        assert_eq!(schema.type_name(0), Some("ABC"));
+        assert_eq!(schema.type_name(1), None);
+        assert_eq!(schema.type_name(TYPE_FLOAT64), Some("float64"));
+        assert_eq!(schema.type_name(-10), None);
     }
 }
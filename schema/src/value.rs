@@ -1,14 +1,16 @@
 use crate::{
-    TYPE_INT, TYPE_UINT, TYPE_FLOAT, TYPE_STRING, TYPE_INT64, TYPE_UINT64, TYPE_BOOL, TYPE_BYTE, 
-    bb::{ ByteBuffer, ByteBufferMut}, 
-    schema::{DefKind, Field, Schema},
+    TYPE_INT, TYPE_UINT, TYPE_FLOAT, TYPE_STRING, TYPE_INT64, TYPE_UINT64, TYPE_FLOAT64, TYPE_BOOL, TYPE_BYTE, 
+    bb::{ ByteBuffer, ByteBufferMut, var_float_size, var_float64_size, var_uint64_size, var_uint_size }, 
+    schema::{Def, DefKind, Field, Schema, SchemaOptions},
 };
 
-use std::collections::HashMap;
-use std::f32;
-use std::fmt;
-use std::ops::Index;
-use std::str;
+use crate::Map;
+use core::fmt;
+use core::ops::Index;
+use core::str;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
 /// This type holds dynamic Kiwi data.
 ///
@@ -27,9 +29,16 @@ pub enum Value<'a> {
     String(String),
     Int64(i64),
     UInt64(u64),
+    Float64(f64),
     Array(Vec<Value<'a>>),
+    /// The contents of a `byte[]`, kept as raw bytes rather than an
+    /// [Array](#variant.Array) of [Byte](#variant.Byte)s. Either encodes the
+    /// same way; decoding always produces this.
+    Bytes(Vec<u8>),
+    /// The key/value pairs of a `map<K, V>` field, in wire order.
+    Map(Vec<(Value<'a>, Value<'a>)>),
     Enum(&'a str, &'a str),
-    Object(&'a str, HashMap<&'a str, Value<'a>>),
+    Object(&'a str, Map<&'a str, Value<'a>>),
 }
 
 impl<'a> Value<'a> {
@@ -96,6 +105,15 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// A convenience method to extract the value out of a [Float64](#variant.Float64).
+    /// Returns `0.0` for other value kinds.
+    pub fn as_float64(&self) -> f64 {
+        match *self {
+            Value::Float64(value) => value,
+            _ => 0.0,
+        }
+    }
+
     /// A convenience method to extract the value out of a [String](#variant.String).
     /// Returns `""` for other value kinds.
     pub fn as_string(&self) -> &str {
@@ -107,7 +125,9 @@ impl<'a> Value<'a> {
     }
 
     /// A convenience method to get an array of values out of an [Array](#variant.Array).
-    /// Returns an empty array for other value kinds.
+    /// Returns an empty array for other value kinds, including
+    /// [Bytes](#variant.Bytes), which decoded `byte[]` fields are: use
+    /// [as_bytes](#method.as_bytes) for those.
     pub fn as_array(&self) -> &[Value<'a>] {
         match *self {
             Value::Array(ref values) => values.as_slice(),
@@ -115,6 +135,24 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// A convenience method to get the bytes out of a [Bytes](#variant.Bytes).
+    /// Returns an empty slice for other value kinds.
+    pub fn as_bytes(&self) -> &[u8] {
+        match *self {
+            Value::Bytes(ref bytes) => bytes.as_slice(),
+            _ => &[],
+        }
+    }
+
+    /// A convenience method to get the pairs out of a [Map](#variant.Map).
+    /// Returns an empty slice for other value kinds.
+    pub fn as_map(&self) -> &[(Value<'a>, Value<'a>)] {
+        match *self {
+            Value::Map(ref pairs) => pairs.as_slice(),
+            _ => &[],
+        }
+    }
+
     /// A convenience method to extract the value out of an [Enum](#variant.Enum).
     /// Returns `("", "")` for other value kinds.
     pub fn as_enum(&self) -> (&str, &str) {
@@ -124,20 +162,276 @@ impl<'a> Value<'a> {
         }
     }
 
-    /// A convenience method to extract the length out of an [Array](#variant.Array).
-    /// Returns `0` for other value kinds.
+    /// Like [as_bool](#method.as_bool), but returns `None` for other value kinds
+    /// instead of a default.
+    pub fn as_bool_opt(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_byte](#method.as_byte), but returns `None` for other value kinds
+    /// instead of a default.
+    pub fn as_byte_opt(&self) -> Option<u8> {
+        match *self {
+            Value::Byte(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_int](#method.as_int), but returns `None` for other value kinds
+    /// instead of a default.
+    pub fn as_int_opt(&self) -> Option<i32> {
+        match *self {
+            Value::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_uint](#method.as_uint), but returns `None` for other value kinds
+    /// instead of a default.
+    pub fn as_uint_opt(&self) -> Option<u32> {
+        match *self {
+            Value::UInt(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_int64](#method.as_int64), but returns `None` for other value
+    /// kinds instead of a default.
+    pub fn as_int64_opt(&self) -> Option<i64> {
+        match *self {
+            Value::Int64(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_uint64](#method.as_uint64), but returns `None` for other value
+    /// kinds instead of a default.
+    pub fn as_uint64_opt(&self) -> Option<u64> {
+        match *self {
+            Value::UInt64(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_float](#method.as_float), but returns `None` for other value
+    /// kinds instead of a default.
+    pub fn as_float_opt(&self) -> Option<f32> {
+        match *self {
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_float64](#method.as_float64), but returns `None` for other
+    /// value kinds instead of a default.
+    pub fn as_float64_opt(&self) -> Option<f64> {
+        match *self {
+            Value::Float64(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_string](#method.as_string), but returns `None` for other value
+    /// kinds instead of a default. As with `as_string`, an
+    /// [Enum](#variant.Enum) yields its variant name.
+    pub fn as_string_opt(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref value) => Some(value.as_str()),
+            Value::Enum(_, value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [as_array](#method.as_array), but returns `None` for other value
+    /// kinds instead of an empty array.
+    pub fn as_array_opt(&self) -> Option<&[Value<'a>]> {
+        match *self {
+            Value::Array(ref values) => Some(values.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Like [as_bytes](#method.as_bytes), but returns `None` for other value
+    /// kinds instead of an empty slice.
+    pub fn as_bytes_opt(&self) -> Option<&[u8]> {
+        match *self {
+            Value::Bytes(ref bytes) => Some(bytes.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Like [as_map](#method.as_map), but returns `None` for other value kinds
+    /// instead of an empty slice.
+    pub fn as_map_opt(&self) -> Option<&[(Value<'a>, Value<'a>)]> {
+        match *self {
+            Value::Map(ref pairs) => Some(pairs.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Like [as_enum](#method.as_enum), but returns `None` for other value kinds
+    /// instead of `("", "")`.
+    pub fn as_enum_opt(&self) -> Option<(&str, &str)> {
+        match *self {
+            Value::Enum(name, value) => Some((name, value)),
+            _ => None,
+        }
+    }
+
+    /// A convenience method to get the type name and fields out of an
+    /// [Object](#variant.Object). Returns `None` for other value kinds.
+    pub fn as_object(&self) -> Option<(&str, &Map<&'a str, Value<'a>>)> {
+        match *self {
+            Value::Object(name, ref fields) => Some((name, fields)),
+            _ => None,
+        }
+    }
+
+    /// Like [as_object](#method.as_object), but the fields can be modified.
+    pub fn as_object_mut(&mut self) -> Option<(&str, &mut Map<&'a str, Value<'a>>)> {
+        match *self {
+            Value::Object(name, ref mut fields) => Some((name, fields)),
+            _ => None,
+        }
+    }
+
+    /// Like [as_bool_opt](#method.as_bool_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_bool(&self) -> Result<bool, ()> {
+        self.as_bool_opt().ok_or(())
+    }
+
+    /// Like [as_byte_opt](#method.as_byte_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_byte(&self) -> Result<u8, ()> {
+        self.as_byte_opt().ok_or(())
+    }
+
+    /// Like [as_int_opt](#method.as_int_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_int(&self) -> Result<i32, ()> {
+        self.as_int_opt().ok_or(())
+    }
+
+    /// Like [as_uint_opt](#method.as_uint_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_uint(&self) -> Result<u32, ()> {
+        self.as_uint_opt().ok_or(())
+    }
+
+    /// Like [as_int64_opt](#method.as_int64_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_int64(&self) -> Result<i64, ()> {
+        self.as_int64_opt().ok_or(())
+    }
+
+    /// Like [as_uint64_opt](#method.as_uint64_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_uint64(&self) -> Result<u64, ()> {
+        self.as_uint64_opt().ok_or(())
+    }
+
+    /// Like [as_float_opt](#method.as_float_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_float(&self) -> Result<f32, ()> {
+        self.as_float_opt().ok_or(())
+    }
+
+    /// Like [as_float64_opt](#method.as_float64_opt), but returns `Err(())`
+    /// for other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_float64(&self) -> Result<f64, ()> {
+        self.as_float64_opt().ok_or(())
+    }
+
+    /// Like [as_string_opt](#method.as_string_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_string(&self) -> Result<&str, ()> {
+        self.as_string_opt().ok_or(())
+    }
+
+    /// Like [as_array_opt](#method.as_array_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_array(&self) -> Result<&[Value<'a>], ()> {
+        self.as_array_opt().ok_or(())
+    }
+
+    /// Like [as_bytes_opt](#method.as_bytes_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_bytes(&self) -> Result<&[u8], ()> {
+        self.as_bytes_opt().ok_or(())
+    }
+
+    /// Like [as_map_opt](#method.as_map_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_map(&self) -> Result<&[(Value<'a>, Value<'a>)], ()> {
+        self.as_map_opt().ok_or(())
+    }
+
+    /// Like [as_enum_opt](#method.as_enum_opt), but returns `Err(())` for
+    /// other value kinds, matching the error type of the `ByteBuffer` reads.
+    pub fn try_as_enum(&self) -> Result<(&str, &str), ()> {
+        self.as_enum_opt().ok_or(())
+    }
+
+    /// Converts this value into an [OwnedValue](enum.OwnedValue.html), which
+    /// owns its type, field and variant names and so doesn't borrow from
+    /// the schema.
+    pub fn into_owned(self) -> OwnedValue {
+        match self {
+            Value::Bool(value) => OwnedValue::Bool(value),
+            Value::Byte(value) => OwnedValue::Byte(value),
+            Value::Int(value) => OwnedValue::Int(value),
+            Value::UInt(value) => OwnedValue::UInt(value),
+            Value::Float(value) => OwnedValue::Float(value),
+            Value::String(value) => OwnedValue::String(value),
+            Value::Int64(value) => OwnedValue::Int64(value),
+            Value::UInt64(value) => OwnedValue::UInt64(value),
+            Value::Float64(value) => OwnedValue::Float64(value),
+            Value::Array(values) => OwnedValue::Array(values.into_iter().map(Value::into_owned).collect()),
+            Value::Bytes(bytes) => OwnedValue::Bytes(bytes),
+            Value::Map(pairs) => OwnedValue::Map(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            ),
+            Value::Enum(name, variant) => OwnedValue::Enum(name.to_owned(), variant.to_owned()),
+            Value::Object(name, fields) => OwnedValue::Object(
+                name.to_owned(),
+                fields
+                    .into_iter()
+                    .map(|(field, value)| (field.to_owned(), value.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Like [into_owned](#method.into_owned), but clones this value.
+    pub fn to_owned_value(&self) -> OwnedValue {
+        self.clone().into_owned()
+    }
+
+    /// A convenience method to extract the length out of an [Array](#variant.Array)
+    /// or [Bytes](#variant.Bytes). Returns `0` for other value kinds.
     pub fn len(&self) -> usize {
         match *self {
             Value::Array(ref values) => values.len(),
+            Value::Bytes(ref bytes) => bytes.len(),
             _ => 0,
         }
     }
 
-    /// A convenience method to append to an [Array](#variant.Array). Does
-    /// nothing for other value kinds.
+    /// A convenience method to append to an [Array](#variant.Array), or a
+    /// [Byte](#variant.Byte) to [Bytes](#variant.Bytes). Does nothing for
+    /// other value kinds.
     pub fn push(&mut self, value: Value<'a>) {
-        if let Value::Array(ref mut values) = *self {
-            values.push(value);
+        match (self, value) {
+            (Value::Array(values), value) => values.push(value),
+            (Value::Bytes(bytes), Value::Byte(byte)) => bytes.push(byte),
+            _ => {}
         }
     }
 
@@ -167,103 +461,752 @@ impl<'a> Value<'a> {
     }
 
     /// Decodes the type specified by `type_id` and `schema` from `bytes`.
+    /// Structs and messages may nest up to
+    /// [DEFAULT_MAX_DEPTH](constant.DEFAULT_MAX_DEPTH.html) deep.
     pub fn decode(schema: &'a Schema, type_id: i32, bytes: &[u8]) -> Result<Value<'a>, ()> {
         Value::decode_bb(schema, type_id, &mut ByteBuffer::new(bytes))
     }
 
+    /// Like [decode](#method.decode), but fails once structs and messages
+    /// nest more than `max_depth` deep instead of after the default
+    /// [DEFAULT_MAX_DEPTH](constant.DEFAULT_MAX_DEPTH.html).
+    pub fn decode_with_limit(
+        schema: &'a Schema,
+        type_id: i32,
+        bytes: &[u8],
+        max_depth: usize,
+    ) -> Result<Value<'a>, ()> {
+        let options = SchemaOptions {
+            max_depth,
+            ..SchemaOptions::default()
+        };
+        Value::decode_with_options(schema, type_id, bytes, &options)
+    }
+
+    /// Like [decode](#method.decode), but with explicit
+    /// [SchemaOptions](struct.SchemaOptions.html), e.g. to pick a
+    /// [NumericOverflowPolicy](enum.NumericOverflowPolicy.html) for data written
+    /// with an older schema.
+    pub fn decode_with_options(
+        schema: &'a Schema,
+        type_id: i32,
+        bytes: &[u8],
+        options: &SchemaOptions,
+    ) -> Result<Value<'a>, ()> {
+        Value::decode_bb_with_options(schema, type_id, &mut ByteBuffer::new(bytes), options)
+    }
+
+    /// Like [decode](#method.decode), but tolerates message field ids the
+    /// schema doesn't know, as in data written with a newer schema. Kiwi
+    /// can't skip a field whose type is unknown, so decoding stops there: the
+    /// partial value read so far is returned along with `true`. Every
+    /// enclosing struct, message or array is cut short at the same point.
+    /// Other malformed data is still an error.
+    pub fn decode_lenient(schema: &'a Schema, type_id: i32, bytes: &[u8]) -> Result<(Value<'a>, bool), ()> {
+        let mut stopped = false;
+        let value = Value::decode_bb_inner(
+            schema,
+            type_id,
+            &mut ByteBuffer::new(bytes),
+            &SchemaOptions::default(),
+            Some(&mut stopped),
+            0,
+        )?;
+        Ok((value, stopped))
+    }
+
     /// Encodes this value into an array of bytes using the provided `schema`.
+    /// The buffer is sized up front with [encoded_size](#method.encoded_size).
     pub fn encode(&self, schema: &Schema) -> Vec<u8> {
-        let mut bb = ByteBufferMut::new();
+        let mut bb = ByteBufferMut::with_capacity(self.encoded_size(schema));
         self.encode_bb(schema, &mut bb);
         bb.data()
     }
 
+    /// Like [encode](#method.encode), but appends to `bb` instead of
+    /// allocating a new buffer, reserving room for the whole value first.
+    /// Encoding many values into one reused buffer this way avoids an
+    /// allocation per value, e.g. when batching messages into one stream.
+    pub fn encode_into(&self, schema: &Schema, bb: &mut ByteBufferMut) {
+        bb.reserve(self.encoded_size(schema));
+        self.encode_bb(schema, bb);
+    }
+
+    /// Like [encode](#method.encode), but first checks that every definition,
+    /// field and enum variant named in this value exists in `schema` and that
+    /// structs have all their fields, returning an
+    /// [EncodeError](enum.EncodeError.html) where `encode` would panic. Field
+    /// names a message doesn't have are reported too, rather than dropped.
+    pub fn try_encode(&self, schema: &Schema) -> Result<Vec<u8>, EncodeError> {
+        self.check_encodable(schema)?;
+        Ok(self.encode(schema))
+    }
+
+    /// Like [encode_bb](#method.encode_bb), with the checks of
+    /// [try_encode](#method.try_encode). Nothing is written to `bb` on error.
+    pub fn try_encode_bb(&self, schema: &Schema, bb: &mut ByteBufferMut) -> Result<(), EncodeError> {
+        self.check_encodable(schema)?;
+        self.encode_bb(schema, bb);
+        Ok(())
+    }
+
+    fn check_encodable(&self, schema: &Schema) -> Result<(), EncodeError> {
+        let def_named = |name: &str| {
+            schema
+                .def(name)
+                .ok_or_else(|| EncodeError::UnknownDefinition(name.to_string()))
+        };
+
+        match *self {
+            Value::Array(ref values) => values.iter().try_for_each(|value| value.check_encodable(schema)),
+            Value::Bytes(_) => Ok(()),
+            Value::Map(ref pairs) => pairs
+                .iter()
+                .try_for_each(|(key, value)| key.check_encodable(schema).and_then(|_| value.check_encodable(schema))),
+
+            Value::Enum(name, variant) => {
+                let def = def_named(name)?;
+                if def.kind != DefKind::Enum {
+                    return Err(EncodeError::NotAnEnum(name.to_string()));
+                }
+                match def.field(variant) {
+                    Some(_) => Ok(()),
+                    None => Err(EncodeError::UnknownVariant {
+                        definition: name.to_string(),
+                        variant:    variant.to_string(),
+                    }),
+                }
+            }
+
+            Value::Object(name, ref fields) => {
+                let def = def_named(name)?;
+                if def.kind == DefKind::Enum {
+                    return Err(EncodeError::NotAnObject(name.to_string()));
+                }
+                for field in &def.fields {
+                    match fields.get(field.name.as_str()) {
+                        Some(value) => value.check_encodable(schema)?,
+                        None if def.kind == DefKind::Struct => {
+                            return Err(EncodeError::MissingField {
+                                definition: name.to_string(),
+                                field:      field.name.clone(),
+                            })
+                        }
+                        None => {}
+                    }
+                }
+                match fields.keys().filter(|key| def.field(key).is_none()).min() {
+                    Some(key) => Err(EncodeError::UnknownField {
+                        definition: name.to_string(),
+                        field:      key.to_string(),
+                    }),
+                    None => Ok(()),
+                }
+            }
+
+            _ => Ok(()),
+        }
+    }
+
     /// Decodes the type specified by `type_id` and `schema` from `bb` starting
     /// at the current index. After this function returns, the current index will
     /// be advanced by the amount of data that was successfully parsed. This is
     /// mainly useful as a helper routine for [decode](#method.decode), which you
     /// probably want to use instead.
+    ///
+    /// A message field id missing from the schema is an error, since the
+    /// field's bytes can't be skipped; see
+    /// [decode_lenient](#method.decode_lenient).
     pub fn decode_bb(
         schema: &'a Schema,
         type_id: i32,
         bb: &mut ByteBuffer,
     ) -> Result<Value<'a>, ()> {
+        Value::decode_bb_with_options(schema, type_id, bb, &SchemaOptions::default())
+    }
+
+    /// Like [decode_bb](#method.decode_bb), but with explicit
+    /// [SchemaOptions](struct.SchemaOptions.html). `int` and `uint` values are
+    /// read as 64-bit varints (the encodings agree for in-range values) and
+    /// then narrowed using `options.numeric_overflow`.
+    pub fn decode_bb_with_options(
+        schema: &'a Schema,
+        type_id: i32,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+    ) -> Result<Value<'a>, ()> {
+        Value::decode_bb_inner(schema, type_id, bb, options, None, 0)
+    }
+
+    /// Shared decoder. With `stopped` set, an unknown message field id ends
+    /// decoding (setting the flag) instead of failing, and every caller
+    /// returns what it has read so far once the flag is up. `depth` counts the
+    /// structs and messages being decoded around this value.
+    fn decode_bb_inner(
+        schema: &'a Schema,
+        type_id: i32,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        mut stopped: Option<&mut bool>,
+        depth: usize,
+    ) -> Result<Value<'a>, ()> {
+        let policy = options.numeric_overflow;
         match type_id {
             TYPE_BOOL => Ok(Value::Bool(bb.read_bool()?)),
             TYPE_BYTE => Ok(Value::Byte(bb.read_byte()?)),
-            TYPE_INT => Ok(Value::Int(bb.read_var_int()?)),
-            TYPE_UINT => Ok(Value::UInt(bb.read_var_uint()?)),
+            TYPE_INT => Ok(Value::Int(policy.narrow_i32(bb.read_var_int64()?)?)),
+            TYPE_UINT => Ok(Value::UInt(policy.narrow_u32(bb.read_var_uint64()?)?)),
             TYPE_FLOAT => Ok(Value::Float(bb.read_var_float()?)),
             TYPE_STRING => Ok(Value::String(bb.read_string()?.into_owned())),
             TYPE_INT64 => Ok(Value::Int64(bb.read_var_int64()?)),
             TYPE_UINT64 => Ok(Value::UInt64(bb.read_var_uint64()?)),
+            TYPE_FLOAT64 => Ok(Value::Float64(bb.read_var_float64()?)),
 
             _ => {
-                let def = &schema.defs[type_id as usize];
+                // Ids come from the data or the schema bytes, so an id past
+                // the end is an error rather than a panic.
+                let def = schema.defs.get(type_id as usize).ok_or(())?;
 
                 match def.kind {
                     DefKind::Enum => {
-                        if let Some(index) = def.field_value_to_index.get(&bb.read_var_uint()?) {
-                            Ok(Value::Enum(
-                                def.name.as_str(),
-                                def.fields[*index].name.as_str(),
-                            ))
-                        } else {
-                            Err(())
+                        match def.field_value_to_index.get(&bb.read_var_uint()?).and_then(|i| def.fields.get(*i)) {
+                            Some(field) => Ok(Value::Enum(def.name.as_str(), field.name.as_str())),
+                            None => Err(()),
                         }
                     }
 
+                    DefKind::Struct | DefKind::Message if depth >= options.max_depth => Err(()),
+
                     DefKind::Struct => {
-                        let mut fields = HashMap::new();
+                        let mut fields = Map::new();
                         for field in &def.fields {
                             fields.insert(
                                 field.name.as_str(),
-                                Value::decode_field_bb(schema, field, bb)?,
+                                Value::decode_field_bb_inner(schema, field, bb, options, stopped.as_deref_mut(), depth + 1)?,
                             );
+                            if stopped.as_deref() == Some(&true) {
+                                break;
+                            }
                         }
                         Ok(Value::Object(def.name.as_str(), fields))
                     }
 
                     DefKind::Message => {
-                        let mut fields = HashMap::new();
+                        let mut fields = Map::new();
                         loop {
                             let value = bb.read_var_uint()?;
                             if value == 0 {
                                 return Ok(Value::Object(def.name.as_str(), fields));
                             }
-                            if let Some(index) = def.field_value_to_index.get(&value) {
-                                let field = &def.fields[*index];
+                            if let Some(field) = def.field_value_to_index.get(&value).and_then(|i| def.fields.get(*i)) {
                                 fields.insert(
                                     field.name.as_str(),
-                                    Value::decode_field_bb(schema, field, bb)?,
+                                    Value::decode_field_bb_inner(schema, field, bb, options, stopped.as_deref_mut(), depth + 1)?,
                                 );
+                                if stopped.as_deref() == Some(&true) {
+                                    return Ok(Value::Object(def.name.as_str(), fields));
+                                }
+                            } else if let Some(stopped) = stopped {
+                                *stopped = true;
+                                return Ok(Value::Object(def.name.as_str(), fields));
                             } else {
                                 return Err(());
                             }
                         }
                     }
-                }
+                }
+            }
+        }
+    }
+
+    /// Decodes the field specified by `field` and `schema` from `bb` starting
+    /// at the current index. This is used by [decode_bb](#method.decode_bb) but
+    /// may also be useful by itself.
+    pub fn decode_field_bb(
+        schema: &'a Schema,
+        field: &Field,
+        bb: &mut ByteBuffer,
+    ) -> Result<Value<'a>, ()> {
+        Value::decode_field_bb_with_options(schema, field, bb, &SchemaOptions::default())
+    }
+
+    /// Like [decode_field_bb](#method.decode_field_bb), but with explicit
+    /// [SchemaOptions](struct.SchemaOptions.html).
+    pub fn decode_field_bb_with_options(
+        schema: &'a Schema,
+        field: &Field,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+    ) -> Result<Value<'a>, ()> {
+        Value::decode_field_bb_inner(schema, field, bb, options, None, 0)
+    }
+
+    fn decode_field_bb_inner(
+        schema: &'a Schema,
+        field: &Field,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        stopped: Option<&mut bool>,
+        depth: usize,
+    ) -> Result<Value<'a>, ()> {
+        match field.key_type_id {
+            Some(key_type_id) => Value::decode_map_bb_inner(schema, key_type_id, field, bb, options, stopped, depth),
+            None => Value::decode_array_bb_inner(schema, field.type_id, field.array_depth, bb, options, stopped, depth),
+        }
+    }
+
+    /// Decodes the pairs of a `map<K, V>` field whose key type is `key_type_id`.
+    // Kept out of `decode_field_bb_inner` so that frame stays small for
+    // deeply nested messages.
+    #[inline(never)]
+    fn decode_map_bb_inner(
+        schema: &'a Schema,
+        key_type_id: i32,
+        field: &Field,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        mut stopped: Option<&mut bool>,
+        depth: usize,
+    ) -> Result<Value<'a>, ()> {
+        // Keys are scalars, so every pair takes at least a byte.
+        let len = bb.read_var_uint()? as usize;
+        if len > bb.remaining() {
+            return Err(());
+        }
+        let mut pairs = Vec::with_capacity(len);
+        for _ in 0..len {
+            let key = Value::decode_bb_inner(schema, key_type_id, bb, options, None, depth)?;
+            let value = Value::decode_array_bb_inner(
+                schema,
+                field.type_id,
+                field.array_depth,
+                bb,
+                options,
+                stopped.as_deref_mut(),
+                depth,
+            )?;
+            pairs.push((key, value));
+            if stopped.as_deref() == Some(&true) {
+                break;
+            }
+        }
+        Ok(Value::Map(pairs))
+    }
+
+    /// Decodes a value of `type_id` nested `array_depth` arrays deep. Each
+    /// array counts towards `options.max_depth` like a struct does.
+    fn decode_array_bb_inner(
+        schema: &'a Schema,
+        type_id: i32,
+        array_depth: u32,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        mut stopped: Option<&mut bool>,
+        depth: usize,
+    ) -> Result<Value<'a>, ()> {
+        if array_depth == 0 {
+            return Value::decode_bb_inner(schema, type_id, bb, options, stopped, depth);
+        }
+        if array_depth == 1 && type_id == TYPE_BYTE {
+            let len = bb.read_var_uint()? as usize;
+            return Ok(Value::Bytes(bb.read_bytes(len)?.to_vec()));
+        }
+        if depth >= options.max_depth {
+            return Err(());
+        }
+        let len = bb.read_var_uint()? as usize;
+        schema.check_array_len(type_id, array_depth, len, bb)?;
+        let mut array = Vec::with_capacity(len.min(bb.remaining()));
+        for _ in 0..len {
+            array.push(Value::decode_array_bb_inner(
+                schema,
+                type_id,
+                array_depth - 1,
+                bb,
+                options,
+                stopped.as_deref_mut(),
+                depth + 1,
+            )?);
+            if stopped.as_deref() == Some(&true) {
+                break;
+            }
+        }
+        Ok(Value::Array(array))
+    }
+
+    /// Like [decode](#method.decode), but decodes into `self`, reusing its
+    /// allocations wherever the old value has the shape of the new one: an
+    /// object of the same type keeps its field map (and, recursively, its
+    /// field values), an array keeps its `Vec` and elements, and a string or
+    /// `byte[]` keeps its buffer. Decoding many records of one type into the same
+    /// `Value` then allocates little after the first. Values of any other
+    /// shape are replaced, so the result is always what `decode` would
+    /// return. On error, `self` holds a partially decoded value.
+    pub fn decode_into(&mut self, schema: &'a Schema, type_id: i32, bytes: &[u8]) -> Result<(), ()> {
+        self.decode_into_bb(schema, type_id, &mut ByteBuffer::new(bytes), &SchemaOptions::default(), 0)
+    }
+
+    /// Decodes a value of `type_id` into `self` for
+    /// [decode_into](#method.decode_into).
+    fn decode_into_bb(
+        &mut self,
+        schema: &'a Schema,
+        type_id: i32,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        depth: usize,
+    ) -> Result<(), ()> {
+        match *self {
+            Value::String(ref mut buffer) if type_id == TYPE_STRING => {
+                let text = bb.read_string()?;
+                buffer.clear();
+                buffer.push_str(&text);
+                return Ok(());
+            }
+            Value::Object(name, ref mut fields) if type_id >= 0 => {
+                let def = schema.defs.get(type_id as usize).ok_or(())?;
+                if def.kind != DefKind::Enum && def.name == name {
+                    if depth >= options.max_depth {
+                        return Err(());
+                    }
+                    return Value::decode_object_into_bb(schema, def, fields, bb, options, depth);
+                }
+            }
+            _ => {}
+        }
+        *self = Value::decode_bb_inner(schema, type_id, bb, options, None, depth)?;
+        Ok(())
+    }
+
+    /// Decodes the fields of the struct or message `def` into `fields`,
+    /// decoding into the values already there and dropping the ones the new
+    /// data doesn't hold.
+    fn decode_object_into_bb(
+        schema: &'a Schema,
+        def: &'a Def,
+        fields: &mut Map<&'a str, Value<'a>>,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        depth: usize,
+    ) -> Result<(), ()> {
+        let decode_field = |fields: &mut Map<&'a str, Value<'a>>, field: &'a Field, bb: &mut ByteBuffer| {
+            match fields.get_mut(field.name.as_str()) {
+                Some(value) => value.decode_field_into_bb(schema, field, bb, options, depth + 1),
+                None => {
+                    let value = Value::decode_field_bb_inner(schema, field, bb, options, None, depth + 1)?;
+                    fields.insert(field.name.as_str(), value);
+                    Ok(())
+                }
+            }
+        };
+
+        if def.kind == DefKind::Struct {
+            for field in &def.fields {
+                decode_field(fields, field, bb)?;
+            }
+            if fields.len() != def.fields.len() {
+                fields.retain(|name, _| def.field_name_to_index.contains_key(*name));
+            }
+            return Ok(());
+        }
+
+        // Fields are marked in a bitmask as they're read, so the ones this
+        // message leaves out can be dropped afterwards without allocating.
+        // Messages with more fields than bits start from an empty map.
+        let tracked = def.fields.len() <= 64;
+        if !tracked {
+            fields.clear();
+        }
+        let mut seen: u64 = 0;
+        loop {
+            let value = bb.read_var_uint()?;
+            if value == 0 {
+                break;
+            }
+            let index = *def.field_value_to_index.get(&value).ok_or(())?;
+            let field = def.fields.get(index).ok_or(())?;
+            decode_field(fields, field, bb)?;
+            if tracked {
+                seen |= 1 << index;
+            }
+        }
+        if tracked {
+            fields.retain(|name, _| {
+                def.field_name_to_index
+                    .get(*name)
+                    .is_some_and(|&index| seen & (1 << index) != 0)
+            });
+        }
+        Ok(())
+    }
+
+    /// Decodes the value of `field` into `self` for
+    /// [decode_into](#method.decode_into).
+    fn decode_field_into_bb(
+        &mut self,
+        schema: &'a Schema,
+        field: &Field,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        depth: usize,
+    ) -> Result<(), ()> {
+        if field.key_type_id.is_some() {
+            *self = Value::decode_field_bb_inner(schema, field, bb, options, None, depth)?;
+            return Ok(());
+        }
+        self.decode_array_into_bb(schema, field.type_id, field.array_depth, bb, options, depth)
+    }
+
+    /// Decodes a value of `type_id` nested `array_depth` arrays deep into
+    /// `self`, reusing the elements of an existing array.
+    fn decode_array_into_bb(
+        &mut self,
+        schema: &'a Schema,
+        type_id: i32,
+        array_depth: u32,
+        bb: &mut ByteBuffer,
+        options: &SchemaOptions,
+        depth: usize,
+    ) -> Result<(), ()> {
+        if array_depth == 0 {
+            return self.decode_into_bb(schema, type_id, bb, options, depth);
+        }
+        if array_depth == 1 && type_id == TYPE_BYTE {
+            let len = bb.read_var_uint()? as usize;
+            let bytes = bb.read_bytes(len)?;
+            match *self {
+                Value::Bytes(ref mut buffer) => {
+                    buffer.clear();
+                    buffer.extend_from_slice(bytes);
+                }
+                _ => *self = Value::Bytes(bytes.to_vec()),
+            }
+            return Ok(());
+        }
+        if depth >= options.max_depth {
+            return Err(());
+        }
+        let len = bb.read_var_uint()? as usize;
+        schema.check_array_len(type_id, array_depth, len, bb)?;
+        if !matches!(*self, Value::Array(_)) {
+            *self = Value::Array(Vec::with_capacity(len.min(bb.remaining())));
+        }
+        if let Value::Array(ref mut array) = *self {
+            array.truncate(len);
+            for i in 0..len {
+                match array.get_mut(i) {
+                    Some(item) => item.decode_array_into_bb(schema, type_id, array_depth - 1, bb, options, depth + 1)?,
+                    None => array.push(Value::decode_array_bb_inner(
+                        schema,
+                        type_id,
+                        array_depth - 1,
+                        bb,
+                        options,
+                        None,
+                        depth + 1,
+                    )?),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that this value has the shape of the type `type_id` in `schema`,
+    /// returning the first mismatch found as a message prefixed with its
+    /// dotted path, e.g. `colors.1.alpha: expected Byte, found String`. Array
+    /// items are addressed by index. Unlike [try_encode](#method.try_encode),
+    /// this also compares every scalar with the kind its field's type expects.
+    pub fn check_against(&self, schema: &Schema, type_id: i32) -> Result<(), String> {
+        self.check_type(schema, type_id, "")
+    }
+
+    /// Same as [check_against](#method.check_against).
+    #[deprecated(note = "use `check_against`")]
+    pub fn validate(&self, schema: &Schema, type_id: i32) -> Result<(), String> {
+        self.check_against(schema, type_id)
+    }
+
+    /// Like [check_against](#method.check_against), but checks this value as
+    /// the contents of `field`, so an array field expects an
+    /// [Array](#variant.Array) of its type. Messages are prefixed with the
+    /// field name, e.g. `colors.1.alpha: expected Byte, found String`.
+    pub fn check_field_against(&self, schema: &Schema, field: &Field) -> Result<(), String> {
+        self.check_field(schema, field, &field.name)
+    }
+
+    fn check_field(&self, schema: &Schema, field: &Field, path: &str) -> Result<(), String> {
+        let key_type_id = match field.key_type_id {
+            Some(key_type_id) => key_type_id,
+            None => return self.check_array(schema, field.type_id, field.array_depth, path),
+        };
+        match *self {
+            Value::Map(ref pairs) => {
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    let path = format!("{}.{}", path, i);
+                    key.check_type(schema, key_type_id, &format!("{}.key", path))?;
+                    value.check_array(schema, field.type_id, field.array_depth, &format!("{}.value", path))?;
+                }
+                Ok(())
+            }
+            _ => Err(format!("{}: expected Map, found {}", path, self.kind_name())),
+        }
+    }
+
+    fn check_array(&self, schema: &Schema, type_id: i32, array_depth: u32, path: &str) -> Result<(), String> {
+        if array_depth == 0 {
+            return self.check_type(schema, type_id, path);
+        }
+        match *self {
+            Value::Bytes(_) if array_depth == 1 && type_id == TYPE_BYTE => Ok(()),
+            Value::Array(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    item.check_array(schema, type_id, array_depth - 1, &format!("{}.{}", path, i))?;
+                }
+                Ok(())
+            }
+            _ => Err(format!("{}: expected Array, found {}", path, self.kind_name())),
+        }
+    }
+
+    fn check_type(&self, schema: &Schema, type_id: i32, path: &str) -> Result<(), String> {
+        let fail = |message: String| {
+            if path.is_empty() { Err(message) } else { Err(format!("{}: {}", path, message)) }
+        };
+        let mismatch = |expected: &str| fail(format!("expected {}, found {}", expected, self.kind_name()));
+        let at = |name: &str| {
+            if path.is_empty() { name.to_string() } else { format!("{}.{}", path, name) }
+        };
+
+        let expected = match type_id {
+            TYPE_BOOL => "Bool",
+            TYPE_BYTE => "Byte",
+            TYPE_INT => "Int",
+            TYPE_UINT => "UInt",
+            TYPE_FLOAT => "Float",
+            TYPE_STRING => "String",
+            TYPE_INT64 => "Int64",
+            TYPE_UINT64 => "UInt64",
+            TYPE_FLOAT64 => "Float64",
+            _ => {
+                let def = match schema.defs.get(type_id as usize) {
+                    Some(def) => def,
+                    None => return fail(format!("unknown type id {}", type_id)),
+                };
+                return match (&def.kind, self) {
+                    (DefKind::Enum, Value::Enum(name, variant)) if *name == def.name => {
+                        if def.field(variant).is_some() {
+                            Ok(())
+                        } else {
+                            mismatch(&format!("a variant of {}", def.name))
+                        }
+                    }
+                    (DefKind::Struct, Value::Object(name, fields))
+                    | (DefKind::Message, Value::Object(name, fields))
+                        if *name == def.name =>
+                    {
+                        for field in &def.fields {
+                            let path = at(&field.name);
+                            match fields.get(field.name.as_str()) {
+                                Some(value) => value.check_field(schema, field, &path)?,
+                                None if def.kind == DefKind::Message => continue,
+                                None => return Err(format!("{}: missing from {}", path, def.name)),
+                            }
+                        }
+                        match fields.keys().find(|key| def.field(key).is_none()) {
+                            Some(key) => Err(format!("{}: not a field of {}", at(key), def.name)),
+                            None => Ok(()),
+                        }
+                    }
+                    _ => mismatch(&def.name),
+                };
             }
+        };
+
+        if self.kind_name() == expected {
+            Ok(())
+        } else {
+            mismatch(expected)
         }
     }
 
-    /// Decodes the field specified by `field` and `schema` from `bb` starting
-    /// at the current index. This is used by [decode_bb](#method.decode_bb) but
-    /// may also be useful by itself.
-    pub fn decode_field_bb(
-        schema: &'a Schema,
-        field: &Field,
-        bb: &mut ByteBuffer,
-    ) -> Result<Value<'a>, ()> {
-        if field.is_array {
-            let len = bb.read_var_uint()? as usize;
-            let mut array = Vec::with_capacity(len);
-            for _ in 0..len {
-                array.push(Value::decode_bb(schema, field.type_id, bb)?);
+    /// The name of this value's variant, as used in `check_against` messages.
+    fn kind_name(&self) -> &'static str {
+        match *self {
+            Value::Bool(_) => "Bool",
+            Value::Byte(_) => "Byte",
+            Value::Int(_) => "Int",
+            Value::UInt(_) => "UInt",
+            Value::Float(_) => "Float",
+            Value::String(_) => "String",
+            Value::Int64(_) => "Int64",
+            Value::UInt64(_) => "UInt64",
+            Value::Float64(_) => "Float64",
+            Value::Array(_) => "Array",
+            Value::Bytes(_) => "Bytes",
+            Value::Map(_) => "Map",
+            Value::Enum(..) => "Enum",
+            Value::Object(..) => "Object",
+        }
+    }
+
+    /// Returns the exact number of bytes [encode](#method.encode) produces for
+    /// this value, without allocating. Mirrors [encode_bb](#method.encode_bb).
+    pub fn encoded_size(&self, schema: &Schema) -> usize {
+        match *self {
+            Value::Bool(_) | Value::Byte(_) => 1,
+            Value::Int(value) => var_uint_size(((value << 1) ^ (value >> 31)) as u32),
+            Value::UInt(value) => var_uint_size(value),
+            Value::Float(value) => var_float_size(value),
+            Value::String(ref value) => value.len() + 1,
+            Value::Int64(value) => var_uint64_size(((value << 1) ^ (value >> 63)) as u64),
+            Value::UInt64(value) => var_uint64_size(value),
+            Value::Float64(value) => var_float64_size(value),
+
+            Value::Array(ref values) => {
+                var_uint_size(values.len() as u32)
+                    + values.iter().map(|value| value.encoded_size(schema)).sum::<usize>()
+            }
+
+            Value::Bytes(ref bytes) => var_uint_size(bytes.len() as u32) + bytes.len(),
+
+            Value::Map(ref pairs) => {
+                var_uint_size(pairs.len() as u32)
+                    + pairs
+                        .iter()
+                        .map(|(key, value)| key.encoded_size(schema) + value.encoded_size(schema))
+                        .sum::<usize>()
+            }
+
+            Value::Enum(name, value) => {
+                let def = &schema.defs[*schema.def_name_to_index.get(name).unwrap()];
+                let index = *def.field_name_to_index.get(value).unwrap();
+                var_uint_size(def.fields[index].value)
+            }
+
+            Value::Object(name, ref fields) => {
+                let def = &schema.defs[*schema.def_name_to_index.get(name).unwrap()];
+                match def.kind {
+                    DefKind::Enum => panic!(),
+                    DefKind::Struct => def
+                        .fields
+                        .iter()
+                        .map(|field| fields.get(field.name.as_str()).unwrap().encoded_size(schema))
+                        .sum(),
+                    DefKind::Message => {
+                        let present: usize = def
+                            .fields
+                            .iter()
+                            .filter_map(|field| {
+                                fields
+                                    .get(field.name.as_str())
+                                    .map(|value| var_uint_size(field.value) + value.encoded_size(schema))
+                            })
+                            .sum();
+                        // The trailing zero byte ends the message.
+                        present + 1
+                    }
+                }
             }
-            Ok(Value::Array(array))
-        } else {
-            Value::decode_bb(schema, field.type_id, bb)
         }
     }
 
@@ -280,13 +1223,26 @@ impl<'a> Value<'a> {
             Value::String(ref value) => bb.write_string(value.as_str()),
             Value::Int64(value) => bb.write_var_int64(value),
             Value::UInt64(value) => bb.write_var_uint64(value),
+            Value::Float64(value) => bb.write_var_float64(value),
 
             Value::Array(ref values) => {
                 bb.write_var_uint(values.len() as u32);
                 for value in values {
                     value.encode_bb(schema, bb);
                 }
-                return;
+            }
+
+            Value::Bytes(ref bytes) => {
+                bb.write_var_uint(bytes.len() as u32);
+                bb.write_bytes(bytes);
+            }
+
+            Value::Map(ref pairs) => {
+                bb.write_var_uint(pairs.len() as u32);
+                for (key, value) in pairs {
+                    key.encode_bb(schema, bb);
+                    value.encode_bb(schema, bb);
+                }
             }
 
             Value::Enum(name, value) => {
@@ -323,15 +1279,157 @@ impl<'a> Value<'a> {
     }
 }
 
+/// Why [Value::try_encode](enum.Value.html#method.try_encode) refused to
+/// encode a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodeError {
+    /// An enum or object names a definition the schema doesn't have.
+    UnknownDefinition(String),
+    /// An object has a field its struct or message doesn't define.
+    UnknownField { definition: String, field: String },
+    /// An enum value names a variant its enum doesn't define.
+    UnknownVariant { definition: String, variant: String },
+    /// A struct object lacks one of its fields; structs have no optional ones.
+    MissingField { definition: String, field: String },
+    /// An [Enum](enum.Value.html#variant.Enum) names a struct or message.
+    NotAnEnum(String),
+    /// An [Object](enum.Value.html#variant.Object) names an enum.
+    NotAnObject(String),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EncodeError::UnknownDefinition(ref name) => write!(f, "unknown definition {:?}", name),
+            EncodeError::UnknownField { ref definition, ref field } => {
+                write!(f, "{} has no field {:?}", definition, field)
+            }
+            EncodeError::UnknownVariant { ref definition, ref variant } => {
+                write!(f, "{} has no variant {:?}", definition, variant)
+            }
+            EncodeError::MissingField { ref definition, ref field } => {
+                write!(f, "struct {} is missing field {:?}", definition, field)
+            }
+            EncodeError::NotAnEnum(ref name) => write!(f, "{} is not an enum", name),
+            EncodeError::NotAnObject(ref name) => write!(f, "{} is an enum, not a struct or message", name),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// A [Value](enum.Value.html) that owns its type, field and variant names, so
+/// it can be stored without a lifetime tied to the schema it was decoded
+/// with. Use [as_value](#method.as_value) to encode it again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedValue {
+    Bool(bool),
+    Byte(u8),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+    String(String),
+    Int64(i64),
+    UInt64(u64),
+    Float64(f64),
+    Array(Vec<OwnedValue>),
+    Bytes(Vec<u8>),
+    Map(Vec<(OwnedValue, OwnedValue)>),
+    Enum(String, String),
+    Object(String, Map<String, OwnedValue>),
+}
+
+impl OwnedValue {
+    /// Returns a [Value](enum.Value.html) borrowing its names from `self`.
+    /// Scalars, strings included, are copied.
+    pub fn as_value(&self) -> Value<'_> {
+        match *self {
+            OwnedValue::Bool(value) => Value::Bool(value),
+            OwnedValue::Byte(value) => Value::Byte(value),
+            OwnedValue::Int(value) => Value::Int(value),
+            OwnedValue::UInt(value) => Value::UInt(value),
+            OwnedValue::Float(value) => Value::Float(value),
+            OwnedValue::String(ref value) => Value::String(value.clone()),
+            OwnedValue::Int64(value) => Value::Int64(value),
+            OwnedValue::UInt64(value) => Value::UInt64(value),
+            OwnedValue::Float64(value) => Value::Float64(value),
+            OwnedValue::Array(ref values) => Value::Array(values.iter().map(OwnedValue::as_value).collect()),
+            OwnedValue::Bytes(ref bytes) => Value::Bytes(bytes.clone()),
+            OwnedValue::Map(ref pairs) => Value::Map(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.as_value(), value.as_value()))
+                    .collect(),
+            ),
+            OwnedValue::Enum(ref name, ref variant) => Value::Enum(name, variant),
+            OwnedValue::Object(ref name, ref fields) => Value::Object(
+                name,
+                fields
+                    .iter()
+                    .map(|(field, value)| (field.as_str(), value.as_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Serializes objects as maps, arrays as sequences, enums as their variant
+/// name and scalars as their natural JSON-style types (bytes are small
+/// integers, floats are numbers). [Bytes](enum.Value.html#variant.Bytes) go
+/// through `serialize_bytes`, which JSON writes as an array of integers.
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for Value<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        match *self {
+            Value::Bool(value) => serializer.serialize_bool(value),
+            Value::Byte(value) => serializer.serialize_u8(value),
+            Value::Int(value) => serializer.serialize_i32(value),
+            Value::UInt(value) => serializer.serialize_u32(value),
+            Value::Float(value) => serializer.serialize_f32(value),
+            Value::String(ref value) => serializer.serialize_str(value),
+            Value::Int64(value) => serializer.serialize_i64(value),
+            Value::UInt64(value) => serializer.serialize_u64(value),
+            Value::Float64(value) => serializer.serialize_f64(value),
+            Value::Array(ref values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::Bytes(ref bytes) => serializer.serialize_bytes(bytes),
+            Value::Map(ref pairs) => {
+                let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                for (key, value) in pairs {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::Enum(_, variant) => serializer.serialize_str(variant),
+            Value::Object(_, ref fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (name, value) in fields {
+                    map.serialize_entry(name, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
 impl<'a> Index<usize> for Value<'a> {
     type Output = Value<'a>;
 
     /// A convenience method that adds support for `self[index]` expressions.
     /// It will panic if this value isn't an [Array](#variant.Array) or if the
-    /// provided index is out of bounds.
+    /// provided index is out of bounds. Decoded `byte[]` fields are
+    /// [Bytes](#variant.Bytes), so index [as_bytes](#method.as_bytes) instead.
     fn index(&self, index: usize) -> &Value<'a> {
         match *self {
             Value::Array(ref values) => &values[index],
+            Value::Bytes(_) => panic!("cannot index Value::Bytes as an array, use as_bytes()"),
             _ => panic!(),
         }
     }
@@ -348,7 +1446,10 @@ impl<'a> fmt::Debug for Value<'a> {
             Value::String(ref value) => value.fmt(f),
             Value::Int64(value) => value.fmt(f),
             Value::UInt64(value) => value.fmt(f),
+            Value::Float64(value) => value.fmt(f),
             Value::Array(ref values) => values.fmt(f),
+            Value::Bytes(ref bytes) => bytes.fmt(f),
+            Value::Map(ref pairs) => f.debug_map().entries(pairs.iter().map(|(key, value)| (key, value))).finish(),
             Value::Enum(name, ref value) => write!(f, "{}::{}", name, value),
 
             Value::Object(name, ref fields) => {
@@ -375,7 +1476,7 @@ impl<'a> fmt::Debug for Value<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::{Def, Field};
+    use crate::schema::{Def, Field, DEFAULT_MAX_DEPTH, MAX_EMPTY_STRUCT_ARRAY_LEN};
 
     #[test]
     fn value_basic() {
@@ -388,7 +1489,7 @@ mod tests {
             Value::String("abc".to_owned()),
             Value::Enum("Foo", "FOO"),
             Value::Object("Obj", {
-                let mut map = HashMap::new();
+                let mut map = Map::new();
                 map.insert("key1", Value::String("value1".to_owned()));
                 map.insert("key2", Value::String("value2".to_owned()));
                 map
@@ -407,14 +1508,14 @@ mod tests {
         assert_eq!(
             value[7],
             Value::Object("Obj", {
-                let mut map = HashMap::new();
+                let mut map = Map::new();
                 map.insert("key1", Value::String("value1".to_owned()));
                 map.insert("key2", Value::String("value2".to_owned()));
                 map
             })
         );
 
-        assert_eq!(value[0].as_bool(), true);
+        assert!(value[0].as_bool());
         assert_eq!(value[1].as_byte(), 255);
         assert_eq!(value[2].as_int(), -1);
         assert_eq!(value[3].as_uint(), 1);
@@ -432,6 +1533,587 @@ mod tests {
         );
     }
 
+    #[test]
+    fn value_as_opt() {
+        assert_eq!(Value::Bool(false).as_bool_opt(), Some(false));
+        assert_eq!(Value::Byte(0).as_byte_opt(), Some(0));
+        assert_eq!(Value::Int(-1).as_int_opt(), Some(-1));
+        assert_eq!(Value::UInt(0).as_uint_opt(), Some(0));
+        assert_eq!(Value::Int64(-2).as_int64_opt(), Some(-2));
+        assert_eq!(Value::UInt64(2).as_uint64_opt(), Some(2));
+        assert_eq!(Value::Float64(0.25).as_float64_opt(), Some(0.25));
+        assert_eq!(Value::Float(0.5).as_float_opt(), Some(0.5));
+        assert_eq!(Value::String("abc".to_owned()).as_string_opt(), Some("abc"));
+        assert_eq!(Value::Enum("Foo", "FOO").as_enum_opt(), Some(("Foo", "FOO")));
+        assert_eq!(Value::Array(vec![]).as_array_opt(), Some(&[][..]));
+
+        // A kind mismatch is distinguishable from a real zero value.
+        assert_eq!(Value::Int(0).as_uint(), 0);
+        assert_eq!(Value::Int(0).as_uint_opt(), None);
+        assert_eq!(Value::UInt(1).as_bool_opt(), None);
+        assert_eq!(Value::Bool(true).as_string_opt(), None);
+        assert_eq!(Value::String("abc".to_owned()).as_array_opt(), None);
+        assert_eq!(Value::String("abc".to_owned()).as_enum_opt(), None);
+    }
+
+    #[test]
+    fn value_try_as() {
+        assert_eq!(Value::Bool(true).try_as_bool(), Ok(true));
+        assert_eq!(Value::Byte(7).try_as_byte(), Ok(7));
+        assert_eq!(Value::Int(-1).try_as_int(), Ok(-1));
+        assert_eq!(Value::UInt(1).try_as_uint(), Ok(1));
+        assert_eq!(Value::Int64(-2).try_as_int64(), Ok(-2));
+        assert_eq!(Value::UInt64(2).try_as_uint64(), Ok(2));
+        assert_eq!(Value::Float64(0.25).try_as_float64(), Ok(0.25));
+        assert_eq!(Value::Float(0.5).try_as_float(), Ok(0.5));
+        assert_eq!(Value::String("abc".to_owned()).try_as_string(), Ok("abc"));
+        assert_eq!(Value::Enum("Foo", "FOO").try_as_enum(), Ok(("Foo", "FOO")));
+        assert_eq!(Value::Array(vec![]).try_as_array(), Ok(&[][..]));
+
+        assert_eq!(Value::Int(0).try_as_uint(), Err(()));
+        assert_eq!(Value::UInt(0).try_as_float(), Err(()));
+        assert_eq!(Value::Bool(true).try_as_string(), Err(()));
+        assert_eq!(Value::String("abc".to_owned()).try_as_array(), Err(()));
+    }
+
+    #[test]
+    fn value_try_encode_reports_bad_names() {
+        let schema = Schema::new(vec![
+            Def::new(
+                "Color".to_owned(),
+                DefKind::Struct,
+                vec![
+                    Field { name: "red".to_owned(), type_id: TYPE_BYTE, array_depth: 0, key_type_id: None, value: 1 },
+                    Field { name: "alpha".to_owned(), type_id: TYPE_BYTE, array_depth: 0, key_type_id: None, value: 2 },
+                ],
+            ),
+            Def::new(
+                "Type".to_owned(),
+                DefKind::Enum,
+                vec![Field { name: "FLAT".to_owned(), type_id: 0, array_depth: 0, key_type_id: None, value: 0 }],
+            ),
+            Def::new(
+                "Example".to_owned(),
+                DefKind::Message,
+                vec![
+                    Field { name: "type".to_owned(), type_id: 1, array_depth: 0, key_type_id: None, value: 1 },
+                    Field { name: "colors".to_owned(), type_id: 0, array_depth: 1, key_type_id: None, value: 2 },
+                ],
+            ),
+        ]);
+        let color = |fields: &[(&'static str, u8)]| {
+            Value::Object("Color", fields.iter().map(|&(name, v)| (name, Value::Byte(v))).collect())
+        };
+        let example = |fields: Vec<(&'static str, Value<'static>)>| {
+            Value::Object("Example", fields.into_iter().collect())
+        };
+
+        let good = example(vec![
+            ("type", Value::Enum("Type", "FLAT")),
+            ("colors", Value::Array(vec![color(&[("red", 1), ("alpha", 2)])])),
+        ]);
+        assert_eq!(good.try_encode(&schema), Ok(good.encode(&schema)));
+
+        let error = |value: Value| value.try_encode(&schema).unwrap_err().to_string();
+        assert_eq!(
+            error(example(vec![("type", Value::Enum("Type", "ROUND"))])),
+            "Type has no variant \"ROUND\""
+        );
+        assert_eq!(error(example(vec![("typ", Value::Enum("Type", "FLAT"))])), "Example has no field \"typ\"");
+        assert_eq!(
+            error(example(vec![("colors", Value::Array(vec![color(&[("red", 1)])]))])),
+            "struct Color is missing field \"alpha\""
+        );
+        assert_eq!(error(Value::Object("Colour", Map::new())), "unknown definition \"Colour\"");
+        assert_eq!(error(Value::Enum("Color", "red")), "Color is not an enum");
+        assert_eq!(error(Value::Object("Type", Map::new())), "Type is an enum, not a struct or message");
+
+        // `try_encode_bb` appends on success and writes nothing on failure
+        let mut bb = ByteBufferMut::new();
+        bb.write_byte(7);
+        assert_eq!(good.try_encode_bb(&schema, &mut bb), Ok(()));
+        let bad = example(vec![("typ", Value::Enum("Type", "FLAT"))]);
+        assert!(bad.try_encode_bb(&schema, &mut bb).is_err());
+        assert_eq!(bb.data(), [&[7][..], &good.encode(&schema)].concat());
+    }
+
+    #[test]
+    fn value_decode_rejects_oversized_array_length() {
+        let schema = Schema::new(vec![
+            Def::new(
+                "List".to_owned(),
+                DefKind::Message,
+                vec![
+                    Field { name: "items".to_owned(), type_id: TYPE_UINT, array_depth: 1, key_type_id: None, value: 1 },
+                    Field { name: "units".to_owned(), type_id: 1, array_depth: 1, key_type_id: None, value: 2 },
+                    Field { name: "points".to_owned(), type_id: 2, array_depth: 1, key_type_id: None, value: 3 },
+                ],
+            ),
+            Def::new("Unit".to_owned(), DefKind::Struct, vec![]),
+            Def::new("Point".to_owned(), DefKind::Struct, vec![
+                Field { name: "x".to_owned(), type_id: TYPE_INT, array_depth: 0, key_type_id: None, value: 1 },
+            ]),
+        ]);
+        let rejects = |bytes: &[u8]| {
+            assert_eq!(Value::decode(&schema, 0, bytes), Err(()));
+            let mut value = Value::Bool(false);
+            assert_eq!(value.decode_into(&schema, 0, bytes), Err(()));
+            assert_eq!(schema.skip(&mut ByteBuffer::new(bytes), 0), Err(()));
+        };
+
+        // Four billion `uint`s, empty structs or structs with fields claimed
+        rejects(&[1, 255, 255, 255, 255, 15, 0]);
+        rejects(&[2, 255, 255, 255, 255, 15, 0]);
+        rejects(&[3, 255, 255, 255, 255, 15, 0]);
+
+        // Empty structs take no bytes, so their count can exceed what's left,
+        // up to a fixed cap
+        let units = Value::decode(&schema, 0, &[2, 3, 0]).unwrap();
+        assert_eq!(units.get("units").map(|units| units.len()), Some(3));
+        let mut at_cap = vec![2];
+        let mut bb = ByteBufferMut::new();
+        bb.write_var_uint(MAX_EMPTY_STRUCT_ARRAY_LEN as u32);
+        at_cap.extend(bb.data());
+        at_cap.push(0);
+        assert!(Value::decode(&schema, 0, &at_cap).is_ok());
+        let mut bb = ByteBufferMut::new();
+        bb.write_var_uint(MAX_EMPTY_STRUCT_ARRAY_LEN as u32 + 1);
+        rejects(&[&[2], bb.as_slice(), &[0]].concat());
+    }
+
+    #[test]
+    fn value_decode_bogus_type_id() {
+        let schema = Schema::new(vec![Def::new(
+            "Foo".to_owned(),
+            DefKind::Message,
+            vec![Field { name: "bar".to_owned(), type_id: 1_000, array_depth: 0, key_type_id: None, value: 1 }],
+        )]);
+        assert_eq!(Value::decode(&schema, 1_000_000, &[0]), Err(()));
+        assert_eq!(Value::decode(&schema, -100, &[0]), Err(()));
+        // A field whose type id points past the schema
+        assert_eq!(Value::decode(&schema, 0, &[1, 0, 0]), Err(()));
+        assert_eq!(schema.skip(&mut ByteBuffer::new(&[1, 0, 0]), 0), Err(()));
+    }
+
+    #[test]
+    fn value_decode_nested_arrays() {
+        let schema = Schema::new(vec![Def::new(
+            "Grid".to_owned(),
+            DefKind::Struct,
+            vec![Field { name: "cells".to_owned(), type_id: TYPE_BYTE, array_depth: 2, key_type_id: None, value: 1 }],
+        )]);
+        let bytes = [2, 2, 1, 2, 0];
+        let value = Value::decode(&schema, 0, &bytes).unwrap();
+        let cells = value.get("cells").unwrap();
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0], Value::Bytes(vec![1, 2]));
+        assert!(cells[1].as_bytes().is_empty());
+        assert_eq!(value.encode(&schema), bytes);
+        assert_eq!(schema.skip(&mut ByteBuffer::new(&bytes), 0), Ok(()));
+        assert!(value.check_against(&schema, 0).is_ok());
+
+        // An inner array can't claim more items than there are bytes left
+        assert_eq!(Value::decode(&schema, 0, &[1, 9, 1]), Err(()));
+        let flat = Value::Object("Grid", [("cells", Value::Array(vec![Value::Byte(1)]))].into_iter().collect());
+        assert_eq!(flat.check_against(&schema, 0), Err("cells.0: expected Array, found Byte".to_string()));
+    }
+
+    #[test]
+    fn value_decode_map() {
+        let schema = Schema::new(vec![Def::new(
+            "Attrs".to_owned(),
+            DefKind::Message,
+            vec![Field { name: "attrs".to_owned(), type_id: TYPE_INT, array_depth: 0, key_type_id: Some(TYPE_STRING), value: 1 }],
+        )]);
+        let bytes = [1, 2, 97, 0, 4, 98, 0, 6, 0];
+        let value = Value::decode(&schema, 0, &bytes).unwrap();
+        let attrs = value.get("attrs").unwrap();
+        assert_eq!(
+            attrs.as_map(),
+            [
+                (Value::String("a".to_owned()), Value::Int(2)),
+                (Value::String("b".to_owned()), Value::Int(3)),
+            ]
+        );
+        assert_eq!(value.encode(&schema), bytes);
+        assert_eq!(value.encoded_size(&schema), bytes.len());
+        assert_eq!(value.clone().into_owned().as_value(), value);
+        assert!(value.check_against(&schema, 0).is_ok());
+        assert_eq!(format!("{:?}", attrs), r#"{"a": 2, "b": 3}"#);
+
+        // A count past the end fails before anything is allocated
+        assert_eq!(Value::decode(&schema, 0, &[1, 100, 0]), Err(()));
+        let wrong = Value::Object(
+            "Attrs",
+            [("attrs", Value::Map(vec![(Value::Int(1), Value::Int(2))]))].into_iter().collect(),
+        );
+        assert_eq!(wrong.check_against(&schema, 0), Err("attrs.0.key: expected String, found Int".to_string()));
+    }
+
+    #[test]
+    fn value_decode_into_reuses_value() {
+        let schema = Schema::new(vec![
+            Def::new("Point".to_owned(), DefKind::Struct, vec![
+                Field { name: "x".to_owned(), type_id: TYPE_INT, array_depth: 0, key_type_id: None, value: 1 },
+                Field { name: "name".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 2 },
+            ]),
+            Def::new("Record".to_owned(), DefKind::Message, vec![
+                Field { name: "id".to_owned(), type_id: TYPE_UINT, array_depth: 0, key_type_id: None, value: 1 },
+                Field { name: "points".to_owned(), type_id: 0, array_depth: 1, key_type_id: None, value: 2 },
+                Field { name: "tag".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 3 },
+                Field { name: "child".to_owned(), type_id: 1, array_depth: 0, key_type_id: None, value: 4 },
+            ]),
+        ]);
+        let point = |x, name: &str| {
+            Value::Object("Point", [("x", Value::Int(x)), ("name", Value::String(name.to_owned()))].into_iter().collect())
+        };
+        let first = Value::Object("Record", [
+            ("id", Value::UInt(1)),
+            ("points", Value::Array(vec![point(1, "a"), point(2, "b")])),
+            ("tag", Value::String("x".to_owned())),
+            ("child", Value::Object("Record", [("id", Value::UInt(9))].into_iter().collect())),
+        ].into_iter().collect()).encode(&schema);
+        let second = Value::Object("Record", [
+            ("id", Value::UInt(2)),
+            ("points", Value::Array(vec![point(3, "c")])),
+        ].into_iter().collect()).encode(&schema);
+
+        let mut value = Value::Bool(false);
+        value.decode_into(&schema, 1, &first).unwrap();
+        assert_eq!(value, Value::decode(&schema, 1, &first).unwrap());
+        let points = value.get("points").unwrap().as_array().as_ptr();
+
+        // Fields the second record leaves out are dropped; the array is reused.
+        value.decode_into(&schema, 1, &second).unwrap();
+        assert_eq!(value, Value::decode(&schema, 1, &second).unwrap());
+        assert_eq!(value.get("points").unwrap().as_array().as_ptr(), points);
+
+        value.decode_into(&schema, 1, &first).unwrap();
+        assert_eq!(value, Value::decode(&schema, 1, &first).unwrap());
+
+        // A value of another type is replaced. The second record's point
+        // starts after its id and the array length.
+        value.decode_into(&schema, 0, &second[4..]).unwrap();
+        assert_eq!(value, point(3, "c"));
+
+        assert_eq!(value.decode_into(&schema, 1, &first[..first.len() - 1]), Err(()));
+        assert_eq!(value.decode_into(&schema, 1, &[9, 0]), Err(()));
+    }
+
+    #[test]
+    fn value_encode_into_appends() {
+        let schema = Schema::new(vec![Def::new("Ping".to_owned(), DefKind::Message, vec![
+            Field { name: "seq".to_owned(), type_id: TYPE_UINT, array_depth: 0, key_type_id: None, value: 1 },
+            Field { name: "note".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 2 },
+        ])]);
+        let ping = |seq, note: &str| {
+            Value::Object("Ping", [("seq", Value::UInt(seq)), ("note", Value::String(note.to_owned()))].into_iter().collect())
+        };
+
+        let mut bb = ByteBufferMut::new();
+        bb.write_byte(7);
+        ping(1, "a").encode_into(&schema, &mut bb);
+        ping(300, "bc").encode_into(&schema, &mut bb);
+        let mut expected = vec![7];
+        expected.extend(ping(1, "a").encode(&schema));
+        expected.extend(ping(300, "bc").encode(&schema));
+        assert_eq!(bb.as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn value_byte_arrays_are_bytes() {
+        let schema = Schema::new(vec![Def::new("Blob".to_owned(), DefKind::Message, vec![
+            Field { name: "data".to_owned(), type_id: TYPE_BYTE, array_depth: 1, key_type_id: None, value: 1 },
+        ])]);
+        let bytes = [1, 3, 10, 20, 30, 0];
+        let mut value = Value::decode(&schema, 0, &bytes).unwrap();
+        let data = value.get("data").unwrap();
+        assert_eq!(*data, Value::Bytes(vec![10, 20, 30]));
+        assert_eq!(data.as_bytes(), [10, 20, 30]);
+        assert_eq!(data.len(), 3);
+        assert!(data.as_array().is_empty());
+        assert_eq!(value.encoded_size(&schema), bytes.len());
+        assert_eq!(value.encode(&schema), bytes);
+        assert!(value.check_against(&schema, 0).is_ok());
+
+        // An array of bytes still encodes the same way.
+        let array = Value::Array(vec![Value::Byte(10), Value::Byte(20), Value::Byte(30)]);
+        let object = Value::Object("Blob", [("data", array)].into_iter().collect());
+        assert_eq!(object.encode(&schema), bytes);
+        assert!(object.check_against(&schema, 0).is_ok());
+
+        // Decoding into a value reuses its buffer.
+        let buffer = value.get("data").unwrap().as_bytes().as_ptr();
+        value.decode_into(&schema, 0, &[1, 2, 5, 6, 0]).unwrap();
+        assert_eq!(value.get("data").unwrap().as_bytes(), [5, 6]);
+        assert_eq!(value.get("data").unwrap().as_bytes().as_ptr(), buffer);
+
+        // A length beyond the data fails rather than reading past it.
+        assert_eq!(Value::decode(&schema, 0, &[1, 9, 1, 0]), Err(()));
+    }
+
+    #[test]
+    fn value_decode_depth_limit() {
+        let schema = Schema::new(vec![Def::new(
+            "Node".to_owned(),
+            DefKind::Message,
+            vec![Field { name: "child".to_owned(), type_id: 0, array_depth: 0, key_type_id: None, value: 1 }],
+        )]);
+        // `nodes` nested messages: a `child` field id per parent, then one
+        // terminating zero per message.
+        let nested = |nodes: usize| {
+            let mut bytes = vec![1; nodes - 1];
+            bytes.extend(vec![0; nodes]);
+            bytes
+        };
+
+        assert!(Value::decode_with_limit(&schema, 0, &nested(3), 3).is_ok());
+        assert_eq!(Value::decode_with_limit(&schema, 0, &nested(4), 3), Err(()));
+        assert!(Value::decode(&schema, 0, &nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(Value::decode(&schema, 0, &nested(100_000)), Err(()));
+        assert!(schema.skip(&mut ByteBuffer::new(&nested(DEFAULT_MAX_DEPTH)), 0).is_ok());
+        assert_eq!(schema.skip(&mut ByteBuffer::new(&nested(100_000)), 0), Err(()));
+
+        // Array levels count too: each byte opens another one-element array.
+        let deep = Schema::new(vec![Def::new(
+            "Deep".to_owned(),
+            DefKind::Message,
+            vec![Field { name: "cells".to_owned(), type_id: TYPE_INT, array_depth: 1_000_000, key_type_id: None, value: 1 }],
+        )]);
+        let bytes = vec![1; 500_000];
+        assert_eq!(Value::decode(&deep, 0, &bytes), Err(()));
+        assert_eq!(Value::Bool(false).decode_into(&deep, 0, &bytes), Err(()));
+        assert_eq!(deep.skip(&mut ByteBuffer::new(&bytes), 0), Err(()));
+    }
+
+    #[test]
+    fn value_decode_lenient_stops_at_unknown_field() {
+        let item = |extra: bool| {
+            let mut fields = vec![Field { name: "id".to_owned(), type_id: TYPE_UINT, array_depth: 0, key_type_id: None, value: 1 }];
+            if extra {
+                fields.push(Field { name: "note".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 2 });
+            }
+            Def::new("Item".to_owned(), DefKind::Message, fields)
+        };
+        let list = || {
+            Def::new(
+                "List".to_owned(),
+                DefKind::Message,
+                vec![
+                    Field { name: "items".to_owned(), type_id: 0, array_depth: 1, key_type_id: None, value: 1 },
+                    Field { name: "count".to_owned(), type_id: TYPE_UINT, array_depth: 0, key_type_id: None, value: 2 },
+                ],
+            )
+        };
+        let old = Schema::new(vec![item(false), list()]);
+        let new = Schema::new(vec![item(true), list()]);
+
+        let item_value = |id: u32, note: Option<&str>| {
+            let mut fields = Map::new();
+            fields.insert("id", Value::UInt(id));
+            if let Some(note) = note {
+                fields.insert("note", Value::String(note.to_owned()));
+            }
+            Value::Object("Item", fields)
+        };
+        let mut fields = Map::new();
+        fields.insert("items", Value::Array(vec![item_value(1, None), item_value(2, Some("new")), item_value(3, None)]));
+        fields.insert("count", Value::UInt(3));
+        let bytes = Value::Object("List", fields).encode(&new);
+
+        // The old schema can't skip the unknown "note" field...
+        assert_eq!(Value::decode(&old, 1, &bytes), Err(()));
+
+        // ...but a lenient decode keeps everything read before it.
+        let (partial, stopped) = Value::decode_lenient(&old, 1, &bytes).unwrap();
+        assert!(stopped);
+        let mut expected = Map::new();
+        let mut second = Map::new();
+        second.insert("id", Value::UInt(2));
+        expected.insert("items", Value::Array(vec![item_value(1, None), Value::Object("Item", second)]));
+        assert_eq!(partial, Value::Object("List", expected));
+
+        // Data the schema fully understands decodes as usual.
+        let (full, stopped) = Value::decode_lenient(&new, 1, &bytes).unwrap();
+        assert!(!stopped);
+        assert_eq!(Some(full), Value::decode(&new, 1, &bytes).ok());
+
+        // Malformed data is still an error.
+        assert_eq!(Value::decode_lenient(&old, 1, &[1, 5]), Err(()));
+    }
+
+    #[test]
+    fn value_encoded_size_matches_encode() {
+        let schema = Schema::new(vec![
+            Def::new(
+                "Kind".to_owned(),
+                DefKind::Enum,
+                vec![Field { name: "BIG".to_owned(), type_id: 0, array_depth: 0, key_type_id: None, value: 300 }],
+            ),
+            Def::new(
+                "Pair".to_owned(),
+                DefKind::Struct,
+                vec![
+                    Field { name: "a".to_owned(), type_id: TYPE_INT, array_depth: 0, key_type_id: None, value: 0 },
+                    Field { name: "b".to_owned(), type_id: TYPE_FLOAT, array_depth: 0, key_type_id: None, value: 0 },
+                ],
+            ),
+            Def::new(
+                "Msg".to_owned(),
+                DefKind::Message,
+                vec![
+                    Field { name: "name".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 1 },
+                    Field { name: "pairs".to_owned(), type_id: 1, array_depth: 1, key_type_id: None, value: 200 },
+                    Field { name: "kind".to_owned(), type_id: 0, array_depth: 0, key_type_id: None, value: 3 },
+                    Field { name: "big".to_owned(), type_id: TYPE_UINT64, array_depth: 0, key_type_id: None, value: 4 },
+                    Field { name: "small".to_owned(), type_id: TYPE_INT64, array_depth: 0, key_type_id: None, value: 5 },
+                    Field { name: "flag".to_owned(), type_id: TYPE_BOOL, array_depth: 0, key_type_id: None, value: 6 },
+                ],
+            ),
+        ]);
+        let pair = |a: i32, b: f32| {
+            let mut fields = Map::new();
+            fields.insert("a", Value::Int(a));
+            fields.insert("b", Value::Float(b));
+            Value::Object("Pair", fields)
+        };
+
+        let mut fields = Map::new();
+        fields.insert("name", Value::String("🍕 pizza".to_owned()));
+        fields.insert("pairs", Value::Array((0..200).map(|i| pair(i * -1000, i as f32 * 0.5)).collect()));
+        fields.insert("kind", Value::Enum("Kind", "BIG"));
+        fields.insert("big", Value::UInt64(u64::MAX));
+        fields.insert("small", Value::Int64(i64::MIN));
+        fields.insert("flag", Value::Bool(true));
+        let value = Value::Object("Msg", fields);
+        assert_eq!(value.encoded_size(&schema), value.encode(&schema).len());
+
+        let empty = Value::Object("Msg", Map::new());
+        assert_eq!(empty.encoded_size(&schema), 1);
+    }
+
+    #[test]
+    fn value_into_owned_outlives_schema() {
+        let bytes = {
+            let schema = Schema::new(vec![Def::new(
+                "Tag".to_owned(),
+                DefKind::Message,
+                vec![Field { name: "label".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 1 }],
+            )]);
+            let mut fields = Map::new();
+            fields.insert("label", Value::String("hot".to_owned()));
+            Value::Object("Tag", fields).encode(&schema)
+        };
+
+        let owned = {
+            let schema = Schema::new(vec![Def::new(
+                "Tag".to_owned(),
+                DefKind::Message,
+                vec![Field { name: "label".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 1 }],
+            )]);
+            let value = Value::decode(&schema, 0, &bytes).unwrap();
+            assert_eq!(value.to_owned_value(), value.clone().into_owned());
+            value.into_owned()
+            // `schema` is dropped here; `owned` doesn't borrow from it.
+        };
+
+        let mut fields = Map::new();
+        fields.insert("label".to_owned(), OwnedValue::String("hot".to_owned()));
+        assert_eq!(owned, OwnedValue::Object("Tag".to_owned(), fields));
+
+        let mut fields = Map::new();
+        fields.insert("label", Value::String("hot".to_owned()));
+        assert_eq!(owned.as_value(), Value::Object("Tag", fields));
+        assert_eq!(
+            OwnedValue::Array(vec![OwnedValue::Enum("Kind".to_owned(), "A".to_owned())]).as_value(),
+            Value::Array(vec![Value::Enum("Kind", "A")])
+        );
+    }
+
+    #[test]
+    fn value_check_against() {
+        let schema = Schema::new(vec![
+            Def::new(
+                "Color".to_owned(),
+                DefKind::Struct,
+                vec![
+                    Field { name: "red".to_owned(), type_id: TYPE_BYTE, array_depth: 0, key_type_id: None, value: 0 },
+                    Field { name: "alpha".to_owned(), type_id: TYPE_BYTE, array_depth: 0, key_type_id: None, value: 0 },
+                ],
+            ),
+            Def::new(
+                "Palette".to_owned(),
+                DefKind::Message,
+                vec![
+                    Field { name: "name".to_owned(), type_id: TYPE_STRING, array_depth: 0, key_type_id: None, value: 1 },
+                    Field { name: "colors".to_owned(), type_id: 0, array_depth: 1, key_type_id: None, value: 2 },
+                ],
+            ),
+        ]);
+        let color = |alpha: Value<'static>| {
+            let mut fields = Map::new();
+            fields.insert("red", Value::Byte(1));
+            fields.insert("alpha", alpha);
+            Value::Object("Color", fields)
+        };
+        let palette = |colors: Vec<Value<'static>>| {
+            let mut fields = Map::new();
+            fields.insert("colors", Value::Array(colors));
+            Value::Object("Palette", fields)
+        };
+
+        let valid = palette(vec![color(Value::Byte(0)), color(Value::Byte(255))]);
+        assert_eq!(valid.check_against(&schema, 1), Ok(()));
+
+        let wrong = palette(vec![color(Value::Byte(0)), color(Value::String("opaque".to_owned()))]);
+        assert_eq!(
+            wrong.check_against(&schema, 1),
+            Err("colors.1.alpha: expected Byte, found String".to_owned())
+        );
+        assert_eq!(
+            Value::Int(0).check_against(&schema, 1),
+            Err("expected Palette, found Int".to_owned())
+        );
+        assert_eq!(
+            Value::Object("Color", Map::new()).check_against(&schema, 0),
+            Err("red: missing from Color".to_owned())
+        );
+
+        // The deprecated alias still gives the same answers.
+        #[allow(deprecated)]
+        let results = (valid.validate(&schema, 1), wrong.validate(&schema, 1));
+        assert_eq!(results, (Ok(()), wrong.check_against(&schema, 1)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn value_serialize() {
+        let mut fields = Map::new();
+        fields.insert("flag", Value::Bool(true));
+        fields.insert("byte", Value::Byte(255));
+        fields.insert("int", Value::Int(-1));
+        fields.insert("float", Value::Float(0.5));
+        fields.insert("name", Value::String("abc".to_owned()));
+        fields.insert("big", Value::UInt64(u64::MAX));
+        fields.insert("kind", Value::Enum("Kind", "ROUND"));
+        fields.insert("list", Value::Array(vec![Value::UInt(1), Value::UInt(2)]));
+        let value = Value::Object("Obj", fields);
+
+        assert_eq!(
+            serde_json::to_value(&value).unwrap(),
+            serde_json::json!({
+                "flag": true,
+                "byte": 255,
+                "int": -1,
+                "float": 0.5,
+                "name": "abc",
+                "big": u64::MAX,
+                "kind": "ROUND",
+                "list": [1, 2]
+            })
+        );
+    }
+
     #[test]
     fn value_push() {
         let mut value = Value::Array(vec![]);
@@ -449,7 +2131,7 @@ mod tests {
 
     #[test]
     fn value_set() {
-        let mut value = Value::Object("Foo", HashMap::new());
+        let mut value = Value::Object("Foo", Map::new());
         assert_eq!(value.get("x"), None);
 
         value.set("x", Value::Int(123));
@@ -466,7 +2148,7 @@ mod tests {
 
     #[test]
     fn value_remove() {
-        let mut value = Value::Object("Foo", HashMap::new());
+        let mut value = Value::Object("Foo", Map::new());
         assert_eq!(value.get("x"), None);
 
         value.set("x", Value::Int(123));
@@ -485,6 +2167,24 @@ mod tests {
         assert_eq!(value.get("y"), None);
     }
 
+    #[test]
+    fn value_as_object() {
+        let mut value = Value::Object("Foo", Map::new());
+        value.set("x", Value::Int(1));
+
+        let (name, fields) = value.as_object().unwrap();
+        assert_eq!(name, "Foo");
+        assert_eq!(fields.get("x"), Some(&Value::Int(1)));
+
+        if let Some((_, fields)) = value.as_object_mut() {
+            fields.insert("y", Value::Int(2));
+        }
+        assert_eq!(value.get("y"), Some(&Value::Int(2)));
+
+        assert_eq!(Value::Int(1).as_object(), None);
+        assert_eq!(Value::Array(vec![]).as_object_mut(), None);
+    }
+
     #[test]
     fn value_encode_and_decode() {
         let schema = Schema::new(vec![
@@ -495,13 +2195,15 @@ mod tests {
                     Field {
                         name: "FOO".to_owned(),
                         type_id: 0,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 100,
                     },
                     Field {
                         name: "BAR".to_owned(),
                         type_id: 0,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 200,
                     },
                 ],
@@ -513,13 +2215,15 @@ mod tests {
                     Field {
                         name: "v_enum".to_owned(),
                         type_id: 0,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 0,
                     },
                     Field {
                         name: "v_message".to_owned(),
                         type_id: 2,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 0,
                     },
                 ],
@@ -531,133 +2235,155 @@ mod tests {
                     Field {
                         name: "v_bool".to_owned(),
                         type_id: TYPE_BOOL,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 1,
                     },
                     Field {
                         name: "v_byte".to_owned(),
                         type_id: TYPE_BYTE,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 2,
                     },
                     Field {
                         name: "v_int".to_owned(),
                         type_id: TYPE_INT,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 3,
                     },
                     Field {
                         name: "v_uint".to_owned(),
                         type_id: TYPE_UINT,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 4,
                     },
                     Field {
                         name: "v_float".to_owned(),
                         type_id: TYPE_FLOAT,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 5,
                     },
                     Field {
                         name: "v_string".to_owned(),
                         type_id: TYPE_STRING,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 6,
                     },
                     Field {
                         name: "v_int64".to_owned(),
                         type_id: TYPE_INT64,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 7,
                     },
                     Field {
                         name: "v_uint64".to_owned(),
                         type_id: TYPE_UINT64,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 8,
                     },
                     Field {
                         name: "v_enum".to_owned(),
                         type_id: 0,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 9,
                     },
                     Field {
                         name: "v_struct".to_owned(),
                         type_id: 1,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 10,
                     },
                     Field {
                         name: "v_message".to_owned(),
                         type_id: 2,
-                        is_array: false,
+                        array_depth: 0,
+                        key_type_id: None,
                         value: 11,
                     },
                     Field {
                         name: "a_bool".to_owned(),
                         type_id: TYPE_BOOL,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 12,
                     },
                     Field {
                         name: "a_byte".to_owned(),
                         type_id: TYPE_BYTE,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 13,
                     },
                     Field {
                         name: "a_int".to_owned(),
                         type_id: TYPE_INT,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 14,
                     },
                     Field {
                         name: "a_uint".to_owned(),
                         type_id: TYPE_UINT,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 15,
                     },
                     Field {
                         name: "a_float".to_owned(),
                         type_id: TYPE_FLOAT,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 16,
                     },
                     Field {
                         name: "a_string".to_owned(),
                         type_id: TYPE_STRING,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 17,
                     },
                     Field {
                         name: "a_int64".to_owned(),
                         type_id: TYPE_INT64,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 18,
                     },
                     Field {
                         name: "a_uint64".to_owned(),
                         type_id: TYPE_UINT64,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 19,
                     },
                     Field {
                         name: "a_enum".to_owned(),
                         type_id: 0,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 20,
                     },
                     Field {
                         name: "a_struct".to_owned(),
                         type_id: 1,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 21,
                     },
                     Field {
                         name: "a_message".to_owned(),
                         type_id: 2,
-                        is_array: true,
+                        array_depth: 1,
+                        key_type_id: None,
                         value: 22,
                     },
                 ],
@@ -697,6 +2423,10 @@ mod tests {
             Value::decode(&schema, TYPE_UINT64, &[1]),
             Ok(Value::UInt64(1))
         );
+        assert_eq!(
+            Value::decode(&schema, TYPE_FLOAT64, &[127, 0, 0, 0, 0, 0, 0, 192]),
+            Ok(Value::Float64(0.5))
+        );
         assert_eq!(Value::decode(&schema, 0, &[0]), Err(()));
         assert_eq!(
             Value::decode(&schema, 0, &[100]),
@@ -719,14 +2449,15 @@ mod tests {
         );
         assert_eq!(Value::Int64(-1).encode(&schema), [1]);
         assert_eq!(Value::UInt64(1).encode(&schema), [1]);
+        assert_eq!(Value::Float64(0.5).encode(&schema), [127, 0, 0, 0, 0, 0, 0, 192]);
         assert_eq!(Value::Enum("Enum", "FOO").encode(&schema), [100]);
         assert_eq!(Value::Enum("Enum", "BAR").encode(&schema), [200, 1]);
 
         fn insert<'a>(
-            mut map: HashMap<&'a str, Value<'a>>,
+            mut map: Map<&'a str, Value<'a>>,
             key: &'a str,
             value: Value<'a>,
-        ) -> HashMap<&'a str, Value<'a>> {
+        ) -> Map<&'a str, Value<'a>> {
             map.insert(key, value);
             map
         }
@@ -734,9 +2465,9 @@ mod tests {
         let empty_struct = Value::Object(
             "Struct",
             insert(
-                insert(HashMap::new(), "v_enum", Value::Array(vec![])),
+                insert(Map::new(), "v_enum", Value::Array(vec![])),
                 "v_message",
-                Value::Object("Message", HashMap::new()),
+                Value::Object("Message", Map::new()),
             ),
         );
 
@@ -747,14 +2478,14 @@ mod tests {
             "Struct",
             insert(
                 insert(
-                    HashMap::new(),
+                    Map::new(),
                     "v_enum",
                     Value::Array(vec![Value::Enum("Enum", "FOO"), Value::Enum("Enum", "BAR")]),
                 ),
                 "v_message",
                 Value::Object(
                     "Message",
-                    insert(HashMap::new(), "v_string", Value::String("🍕".to_owned())),
+                    insert(Map::new(), "v_string", Value::String("🍕".to_owned())),
                 ),
             ),
         );
@@ -771,7 +2502,7 @@ mod tests {
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_bool", Value::Bool(false))
+                insert(Map::new(), "v_bool", Value::Bool(false))
             )
             .encode(&schema),
             [1, 0, 0]
@@ -779,7 +2510,7 @@ mod tests {
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_bool", Value::Bool(true))
+                insert(Map::new(), "v_bool", Value::Bool(true))
             )
             .encode(&schema),
             [1, 1, 0]
@@ -787,23 +2518,23 @@ mod tests {
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_byte", Value::Byte(255))
+                insert(Map::new(), "v_byte", Value::Byte(255))
             )
             .encode(&schema),
             [2, 255, 0]
         );
         assert_eq!(
-            Value::Object("Message", insert(HashMap::new(), "v_int", Value::Int(-1))).encode(&schema),
+            Value::Object("Message", insert(Map::new(), "v_int", Value::Int(-1))).encode(&schema),
             [3, 1, 0]
         );
         assert_eq!(
-            Value::Object("Message", insert(HashMap::new(), "v_uint", Value::UInt(1))).encode(&schema),
+            Value::Object("Message", insert(Map::new(), "v_uint", Value::UInt(1))).encode(&schema),
             [4, 1, 0]
         );
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_float", Value::Float(0.0))
+                insert(Map::new(), "v_float", Value::Float(0.0))
             )
             .encode(&schema),
             [5, 0, 0]
@@ -811,19 +2542,19 @@ mod tests {
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_string", Value::String("".to_owned()))
+                insert(Map::new(), "v_string", Value::String("".to_owned()))
             )
             .encode(&schema),
             [6, 0, 0]
         );
         assert_eq!(
-            Value::Object("Message", insert(HashMap::new(), "v_int64", Value::Int(-1))).encode(&schema),
+            Value::Object("Message", insert(Map::new(), "v_int64", Value::Int(-1))).encode(&schema),
             [7, 1, 0]
         );
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_uint64", Value::UInt(1))
+                insert(Map::new(), "v_uint64", Value::UInt(1))
             )
             .encode(&schema),
             [8, 1, 0]
@@ -831,7 +2562,7 @@ mod tests {
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_enum", Value::Enum("Enum", "FOO"))
+                insert(Map::new(), "v_enum", Value::Enum("Enum", "FOO"))
             )
             .encode(&schema),
             [9, 100, 0]
@@ -839,7 +2570,7 @@ mod tests {
         assert_eq!(
             Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_struct", empty_struct.clone())
+                insert(Map::new(), "v_struct", empty_struct.clone())
             )
             .encode(&schema),
             [10, 0, 0, 0]
@@ -848,9 +2579,9 @@ mod tests {
             Value::Object(
                 "Message",
                 insert(
-                    HashMap::new(),
+                    Map::new(),
                     "v_message",
-                    Value::Object("Message", HashMap::new())
+                    Value::Object("Message", Map::new())
                 )
             )
             .encode(&schema),
@@ -861,77 +2592,77 @@ mod tests {
             Value::decode(&schema, 2, &[1, 0, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_bool", Value::Bool(false))
+                insert(Map::new(), "v_bool", Value::Bool(false))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[1, 1, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_bool", Value::Bool(true))
+                insert(Map::new(), "v_bool", Value::Bool(true))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[2, 255, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_byte", Value::Byte(255))
+                insert(Map::new(), "v_byte", Value::Byte(255))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[3, 1, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_int", Value::Int(-1))
+                insert(Map::new(), "v_int", Value::Int(-1))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[4, 1, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_uint", Value::UInt(1))
+                insert(Map::new(), "v_uint", Value::UInt(1))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[5, 0, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_float", Value::Float(0.0))
+                insert(Map::new(), "v_float", Value::Float(0.0))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[6, 0, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_string", Value::String("".to_owned()))
+                insert(Map::new(), "v_string", Value::String("".to_owned()))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[7, 1, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_int64", Value::Int64(-1))
+                insert(Map::new(), "v_int64", Value::Int64(-1))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[8, 1, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_uint64", Value::UInt64(1))
+                insert(Map::new(), "v_uint64", Value::UInt64(1))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[9, 100, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_enum", Value::Enum("Enum", "FOO"))
+                insert(Map::new(), "v_enum", Value::Enum("Enum", "FOO"))
             ))
         );
         assert_eq!(
             Value::decode(&schema, 2, &[10, 0, 0, 0]),
             Ok(Value::Object(
                 "Message",
-                insert(HashMap::new(), "v_struct", empty_struct.clone())
+                insert(Map::new(), "v_struct", empty_struct.clone())
             ))
         );
         assert_eq!(
@@ -939,14 +2670,68 @@ mod tests {
             Ok(Value::Object(
                 "Message",
                 insert(
-                    HashMap::new(),
+                    Map::new(),
                     "v_message",
-                    Value::Object("Message", HashMap::new())
+                    Value::Object("Message", Map::new())
                 )
             ))
         );
     }
 
+    #[test]
+    fn value_decode_numeric_overflow_policy() {
+        use crate::schema::NumericOverflowPolicy;
+
+        let schema = Schema::new(vec![]);
+        let options = |numeric_overflow| SchemaOptions {
+            numeric_overflow,
+            ..SchemaOptions::default()
+        };
+        let error = options(NumericOverflowPolicy::Error);
+        let saturate = options(NumericOverflowPolicy::Saturate);
+        let wrap = options(NumericOverflowPolicy::Wrap);
+
+        // An `int64` value that no longer fits after the field became an `int`
+        let big = Value::Int64(5_000_000_000).encode(&schema);
+        assert_eq!(Value::decode(&schema, TYPE_INT, &big), Err(()));
+        assert_eq!(Value::decode_with_options(&schema, TYPE_INT, &big, &error), Err(()));
+        assert_eq!(
+            Value::decode_with_options(&schema, TYPE_INT, &big, &saturate),
+            Ok(Value::Int(i32::MAX))
+        );
+        assert_eq!(
+            Value::decode_with_options(&schema, TYPE_INT, &big, &wrap),
+            Ok(Value::Int(5_000_000_000i64 as i32))
+        );
+
+        let small = Value::Int64(-5_000_000_000).encode(&schema);
+        assert_eq!(
+            Value::decode_with_options(&schema, TYPE_INT, &small, &saturate),
+            Ok(Value::Int(i32::MIN))
+        );
+
+        // A `uint64` value read back through a `uint` field
+        let big = Value::UInt64(1 << 40 | 7).encode(&schema);
+        assert_eq!(Value::decode_with_options(&schema, TYPE_UINT, &big, &error), Err(()));
+        assert_eq!(
+            Value::decode_with_options(&schema, TYPE_UINT, &big, &saturate),
+            Ok(Value::UInt(u32::MAX))
+        );
+        assert_eq!(
+            Value::decode_with_options(&schema, TYPE_UINT, &big, &wrap),
+            Ok(Value::UInt(7))
+        );
+
+        // In-range values decode the same way under every policy
+        let fits = Value::Int64(-123).encode(&schema);
+        for options in [&error, &saturate, &wrap] {
+            assert_eq!(
+                Value::decode_with_options(&schema, TYPE_INT, &fits, options),
+                Ok(Value::Int(-123))
+            );
+        }
+    }
+
     // This test case is for a bug where rustc was silently inferring an incorrect
     // lifetime. This is the specific error:
     //
@@ -970,6 +2755,7 @@ mod tests {
     //   pub fn get(&self, name: &str) -> Option<&Value<'a>> {
     //
     #[test]
+    #[allow(clippy::needless_lifetimes)]
     fn value_get_bad_lifetime_inference_in_rustc() {
         fn use_item<'a>(_: &'a Value<'static>) {}
 
@@ -0,0 +1,69 @@
+use brine_kiwi_schema::{Def, DefKind, Map, Schema, Value};
+
+use crate::KiwiError;
+
+/// Assembles a `Value::Object` for one struct or message of a [`Schema`],
+/// checking every field as it is set so a mistake is reported where it is
+/// made rather than when the value is encoded.
+///
+/// ```
+/// # use brine_kiwi::{KiwiError, Schema, SchemaValueBuilder, Value};
+/// # fn demo(schema: &Schema) -> Result<(), KiwiError> {
+/// let value = SchemaValueBuilder::new(schema, "Example")?
+///     .set("clientID", Value::UInt(123))?
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SchemaValueBuilder<'a> {
+    schema: &'a Schema,
+    def:    &'a Def,
+    fields: Map<&'a str, Value<'a>>,
+}
+
+impl<'a> SchemaValueBuilder<'a> {
+    /// Starts an empty value of the struct or message named `type_name`.
+    pub fn new(schema: &'a Schema, type_name: &str) -> Result<Self, KiwiError> {
+        let def = schema
+            .def(type_name)
+            .ok_or_else(|| KiwiError::EncodeError(format!("Unknown type \"{}\"", type_name)))?;
+        if def.kind == DefKind::Enum {
+            return Err(KiwiError::EncodeError(format!(
+                "\"{}\" is an enum, not a struct or message",
+                type_name
+            )));
+        }
+        Ok(SchemaValueBuilder {
+            schema,
+            def,
+            fields: Map::new(),
+        })
+    }
+
+    /// Sets `field` to `value`, failing if the definition has no such field
+    /// or `value` does not have the field's declared type (arrays included).
+    pub fn set(mut self, field: &str, value: Value<'a>) -> Result<Self, KiwiError> {
+        let declared = self.def.field(field).ok_or_else(|| {
+            KiwiError::EncodeError(format!("{} has no field \"{}\"", self.def.name, field))
+        })?;
+
+        value
+            .check_field_against(self.schema, declared)
+            .map_err(|err| KiwiError::EncodeError(format!("{}.{}", self.def.name, err)))?;
+
+        self.fields.insert(declared.name.as_str(), value);
+        Ok(self)
+    }
+
+    /// Finishes the value. Every field of a struct must have been set;
+    /// message fields are optional.
+    pub fn build(self) -> Result<Value<'a>, KiwiError> {
+        if self.def.kind == DefKind::Struct {
+            if let Some(missing) = self.def.fields.iter().find(|f| !self.fields.contains_key(f.name.as_str())) {
+                return Err(KiwiError::MissingField(format!("{}.{}", self.def.name, missing.name)));
+            }
+        }
+        Ok(Value::Object(self.def.name.as_str(), self.fields))
+    }
+}
@@ -2,27 +2,218 @@
 //!
 //! This crate provides runtime support for working with Kiwi-encoded data.
 //! 
-//! - `FromKiwi` trait (re-exported from compiler)  
+//! - `FromKiwi` / `ToKiwi` traits and the `ValueExt` helpers (re-exported from compiler)  
+//! - `SchemaValueBuilder`, which checks a `Value` against the schema as it is built
+//! - JSON ↔ `Value` conversion guided by a schema
+//! - `MessageReader` and `MessageWriter`, for length-delimited messages on a stream
 //! - Helpers for reading/writing flat binary streams, etc.
 
-pub use brine_kiwi_compiler::traits::FromKiwi;
+pub use brine_kiwi_compiler::traits::{FromKiwi, ToKiwi, ValueExt};
 pub use brine_kiwi_compiler::error::KiwiError;
-pub use brine_kiwi_schema::{ Schema, Field, Value };
+pub use brine_kiwi_schema::{ Schema, Field, Value, OwnedValue, ByteBufferMut, EncodeError, Map };
 
-/// Decode a Kiwi buffer into a pretty‐printed JSON string.
-pub fn decode_to_json(buffer: &[u8]) -> Result<String, KiwiError> {
+mod builder;
+pub use builder::SchemaValueBuilder;
+
+mod reader;
+pub use reader::MessageReader;
+
+mod writer;
+pub use writer::MessageWriter;
+
+use brine_kiwi_schema::{
+    DefKind, TYPE_BOOL, TYPE_BYTE, TYPE_FLOAT, TYPE_FLOAT64, TYPE_INT, TYPE_INT64, TYPE_STRING, TYPE_UINT,
+    TYPE_UINT64,
+};
+
+/// Decode a binary *schema* (`.kiwi.bin`) into a pretty‐printed JSON
+/// description of its definitions. To decode message data, use
+/// [`decode_message_to_json`].
+pub fn decode_schema_to_json(buffer: &[u8]) -> Result<String, KiwiError> {
     let schema = brine_kiwi_compiler::decode_binary_schema(buffer)?;
-    Ok(serde_json::to_string_pretty(&schema).unwrap())
+    serde_json::to_string_pretty(&schema).map_err(json_error)
+}
+
+#[deprecated(note = "this decodes a schema, not data; use `decode_schema_to_json`")]
+pub fn decode_to_json(buffer: &[u8]) -> Result<String, KiwiError> {
+    decode_schema_to_json(buffer)
+}
+
+/// Decode `bytes` holding a value of the type at `type_id` in `schema` into a
+/// pretty‐printed JSON string.
+pub fn decode_message_to_json(schema: &Schema, type_id: i32, bytes: &[u8]) -> Result<String, KiwiError> {
+    let value = Value::decode(schema, type_id, bytes)
+        .map_err(|_| KiwiError::DecodeError(format!("Failed to decode a value of type id {}", type_id)))?;
+    serde_json::to_string_pretty(&value).map_err(json_error)
+}
+
+/// Convert a decoded `Value` into a `serde_json::Value`. Objects become maps,
+/// enums become their variant name, and non-finite floats become `null`.
+/// Fails if a map key has no JSON string form, e.g. an array or a NaN.
+pub fn value_to_json(value: &Value) -> Result<serde_json::Value, KiwiError> {
+    serde_json::to_value(value).map_err(json_error)
+}
+
+fn json_error(err: serde_json::Error) -> KiwiError {
+    KiwiError::EncodeError(format!("Failed to convert to JSON: {}", err))
 }
 
+/// Build a `Value` of the type at `type_id` in `schema` from JSON, the
+/// inverse of [`value_to_json`]. Numbers are coerced to the scalar kind the
+/// schema declares (and must fit it), enums are given by variant name, and
+/// message fields may be left out. Mismatches are reported with the dotted
+/// path of the offending value, e.g. `colors.0.red: expected byte, found "x"`.
+pub fn value_from_json<'a>(
+    schema: &'a Schema,
+    type_id: i32,
+    json: &serde_json::Value,
+) -> Result<Value<'a>, KiwiError> {
+    json_to_value(schema, type_id, json, "")
+}
+
+fn json_to_value<'a>(
+    schema: &'a Schema,
+    type_id: i32,
+    json: &serde_json::Value,
+    path: &str,
+) -> Result<Value<'a>, KiwiError> {
+    let mismatch = |expected: &str| mismatch_at(path, expected, json);
+    let at = |name: &str| if path.is_empty() { name.to_string() } else { format!("{}.{}", path, name) };
+
+    let value = match type_id {
+        TYPE_BOOL => Value::Bool(json.as_bool().ok_or_else(|| mismatch("bool"))?),
+        TYPE_BYTE => Value::Byte(
+            json.as_u64().and_then(|n| u8::try_from(n).ok()).ok_or_else(|| mismatch("byte"))?,
+        ),
+        TYPE_INT => Value::Int(
+            json.as_i64().and_then(|n| i32::try_from(n).ok()).ok_or_else(|| mismatch("int"))?,
+        ),
+        TYPE_UINT => Value::UInt(
+            json.as_u64().and_then(|n| u32::try_from(n).ok()).ok_or_else(|| mismatch("uint"))?,
+        ),
+        TYPE_FLOAT => Value::Float(json.as_f64().ok_or_else(|| mismatch("float"))? as f32),
+        TYPE_STRING => Value::String(json.as_str().ok_or_else(|| mismatch("string"))?.to_string()),
+        TYPE_INT64 => Value::Int64(json.as_i64().ok_or_else(|| mismatch("int64"))?),
+        TYPE_UINT64 => Value::UInt64(json.as_u64().ok_or_else(|| mismatch("uint64"))?),
+        TYPE_FLOAT64 => Value::Float64(json.as_f64().ok_or_else(|| mismatch("float64"))?),
+        _ => {
+            let def = schema
+                .defs
+                .get(type_id as usize)
+                .ok_or_else(|| KiwiError::DecodeError(format!("Invalid type id {}", type_id)))?;
+            match def.kind {
+                DefKind::Enum => {
+                    let name = json.as_str().ok_or_else(|| mismatch(&def.name))?;
+                    let variant = def
+                        .field(name)
+                        .ok_or_else(|| KiwiError::InvalidEnumVariant(at(name)))?;
+                    Value::Enum(def.name.as_str(), variant.name.as_str())
+                }
+                DefKind::Struct | DefKind::Message => {
+                    let object = json.as_object().ok_or_else(|| mismatch(&def.name))?;
+                    if let Some(key) = object.keys().find(|key| def.field(key).is_none()) {
+                        return Err(KiwiError::DecodeError(format!(
+                            "{}: unknown field of {}",
+                            at(key),
+                            def.name
+                        )));
+                    }
+
+                    let mut fields = Map::new();
+                    for field in &def.fields {
+                        let field_path = at(&field.name);
+                        let item = match object.get(&field.name) {
+                            Some(item) => item,
+                            None if def.kind == DefKind::Message => continue,
+                            None => return Err(KiwiError::MissingField(field_path)),
+                        };
+                        let value = match field.key_type_id {
+                            Some(key_type_id) => json_to_map_value(schema, key_type_id, field.type_id, item, &field_path)?,
+                            None => json_to_array_value(schema, field.type_id, field.array_depth, item, &field_path)?,
+                        };
+                        fields.insert(field.name.as_str(), value);
+                    }
+                    Value::Object(def.name.as_str(), fields)
+                }
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Like [`json_to_value`], for a value nested `array_depth` arrays deep.
+fn json_to_array_value<'a>(
+    schema: &'a Schema,
+    type_id: i32,
+    array_depth: u32,
+    json: &serde_json::Value,
+    path: &str,
+) -> Result<Value<'a>, KiwiError> {
+    if array_depth == 0 {
+        return json_to_value(schema, type_id, json, path);
+    }
+    let items = json.as_array().ok_or_else(|| mismatch_at(path, "array", json))?;
+    let values: Vec<Value> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| json_to_array_value(schema, type_id, array_depth - 1, item, &format!("{}.{}", path, i)))
+        .collect::<Result<_, _>>()?;
+    // Match what decoding produces for a `byte[]`.
+    if array_depth == 1 && type_id == TYPE_BYTE {
+        return Ok(Value::Bytes(values.iter().map(Value::as_byte).collect()));
+    }
+    Ok(Value::Array(values))
+}
+
+/// Like [`json_to_value`], for a map given as a JSON object. Object keys are
+/// always strings, so keys of other types are parsed from them first.
+fn json_to_map_value<'a>(
+    schema: &'a Schema,
+    key_type_id: i32,
+    type_id: i32,
+    json: &serde_json::Value,
+    path: &str,
+) -> Result<Value<'a>, KiwiError> {
+    let object = json.as_object().ok_or_else(|| mismatch_at(path, "map", json))?;
+    let pairs = object
+        .iter()
+        .map(|(key, item)| {
+            let item_path = format!("{}.{}", path, key);
+            // Enums and strings are keyed by name; anything else is a number
+            // or bool written as a string.
+            let key_json = if key_type_id == TYPE_STRING || key_type_id >= 0 {
+                serde_json::Value::String(key.clone())
+            } else {
+                key.parse().unwrap_or_else(|_| serde_json::Value::String(key.clone()))
+            };
+            Ok((
+                json_to_value(schema, key_type_id, &key_json, &item_path)?,
+                json_to_value(schema, type_id, item, &item_path)?,
+            ))
+        })
+        .collect::<Result<_, KiwiError>>()?;
+    Ok(Value::Map(pairs))
+}
+
+fn mismatch_at(path: &str, expected: &str, json: &serde_json::Value) -> KiwiError {
+    let prefix = if path.is_empty() { String::new() } else { format!("{}: ", path) };
+    KiwiError::DecodeError(format!("{}expected {}, found {}", prefix, expected, json))
+}
+
+// The module paths of the original SDK, which re-exported from here before
+// everything moved to the crate root.
+
+#[deprecated(note = "use the re-export at the crate root, `brine_kiwi::FromKiwi`")]
 pub mod traits {
     pub use brine_kiwi_compiler::traits::FromKiwi;
 }
 
+#[deprecated(note = "use the re-export at the crate root, `brine_kiwi::KiwiError`")]
 pub mod error {
     pub use brine_kiwi_compiler::error::KiwiError;
 }
 
+#[deprecated(note = "use the re-exports at the crate root, e.g. `brine_kiwi::Schema`")]
 pub mod schema {
     pub use brine_kiwi_schema::{Schema, Field, Value};
 }
@@ -0,0 +1,109 @@
+use std::io::{self, Read};
+
+use brine_kiwi_schema::{Schema, Value};
+
+use crate::KiwiError;
+
+/// Reads length-delimited messages from a stream, such as a socket: each is a
+/// `var_uint` byte count followed by that many bytes holding one value of
+/// `type_id`. Short reads are retried until a whole message has arrived.
+///
+/// The iterator ends when the stream ends between messages. An I/O error,
+/// including the stream ending partway through a message, is returned as
+/// `KiwiError::Io` and ends the iteration, since the framing is lost. A
+/// message that doesn't decode is a `KiwiError::DecodeError`; the next one
+/// can still be read.
+///
+/// ```
+/// use brine_kiwi::{MessageReader, Schema, Value};
+/// use brine_kiwi_compiler::compile_schema;
+///
+/// let (_, bin) = compile_schema("message Ping { uint seq = 1; }").unwrap();
+/// let schema = Schema::decode(&bin).unwrap();
+///
+/// // Two `Ping`s, each prefixed with its length.
+/// let stream: &[u8] = &[3, 1, 5, 0, 3, 1, 6, 0];
+/// let seqs: Vec<u32> = MessageReader::new(stream, &schema, 0)
+///     .map(|value| value.unwrap().get("seq").map(Value::as_uint).unwrap())
+///     .collect();
+/// assert_eq!(seqs, [5, 6]);
+/// ```
+pub struct MessageReader<'a, R> {
+    reader:  R,
+    schema:  &'a Schema,
+    type_id: i32,
+    buffer:  Vec<u8>,
+    done:    bool,
+}
+
+impl<'a, R: Read> MessageReader<'a, R> {
+    /// Reads messages holding values of `type_id` in `schema` from `reader`.
+    pub fn new(reader: R, schema: &'a Schema, type_id: i32) -> Self {
+        MessageReader { reader, schema, type_id, buffer: Vec::new(), done: false }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the next length prefix, or `None` if the stream ended cleanly
+    /// before it.
+    fn read_length(&mut self) -> Result<Option<u32>, KiwiError> {
+        let mut length: u32 = 0;
+        for shift in (0..35).step_by(7) {
+            let mut byte = [0u8];
+            if let Err(err) = self.reader.read_exact(&mut byte) {
+                if shift == 0 && err.kind() == io::ErrorKind::UnexpectedEof {
+                    return Ok(None);
+                }
+                return Err(err.into());
+            }
+            length |= ((byte[0] & 127) as u32) << shift;
+            if byte[0] & 128 == 0 {
+                return Ok(Some(length));
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "message length does not fit in a var_uint").into())
+    }
+
+    fn read_message(&mut self) -> Result<Option<Value<'a>>, KiwiError> {
+        let length = match self.read_length()? {
+            Some(length) => length as u64,
+            None => return Ok(None),
+        };
+        // Filled as bytes arrive rather than allocated up front, so a bogus
+        // length can't reserve gigabytes.
+        self.buffer.clear();
+        let read = (&mut self.reader).take(length).read_to_end(&mut self.buffer)?;
+        if (read as u64) < length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Value::decode(self.schema, self.type_id, &self.buffer)
+            .map(Some)
+            .map_err(|_| KiwiError::DecodeError(format!("Failed to decode a value of type id {}", self.type_id)))
+    }
+}
+
+impl<'a, R: Read> Iterator for MessageReader<'a, R> {
+    type Item = Result<Value<'a>, KiwiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_message() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                // Only a message that failed to decode leaves the stream at
+                // the start of the next one.
+                self.done = !matches!(err, KiwiError::DecodeError(_));
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::Write;
+
+use brine_kiwi_schema::{ByteBufferMut, Schema, Value};
+
+use crate::KiwiError;
+
+/// Writes length-delimited messages to a stream, in the framing
+/// [`MessageReader`](crate::MessageReader) reads: a `var_uint` byte count,
+/// then the encoded value. Each message is handed to the writer as soon as
+/// it is encoded, so wrap unbuffered writers such as a `TcpStream` in a
+/// `BufWriter` and call [`flush`](Self::flush) when a batch is done.
+///
+/// ```
+/// use brine_kiwi::{MessageReader, MessageWriter, Schema, Value};
+/// use brine_kiwi_compiler::compile_schema;
+///
+/// let (_, bin) = compile_schema("message Ping { uint seq = 1; }").unwrap();
+/// let schema = Schema::decode(&bin).unwrap();
+/// let ping = Value::Object("Ping", [("seq", Value::UInt(5))].into_iter().collect());
+///
+/// let mut writer = MessageWriter::new(Vec::new());
+/// writer.write_message(&ping, &schema).unwrap();
+/// let stream = writer.into_inner();
+/// assert_eq!(stream, [3, 1, 5, 0]);
+///
+/// let read: Vec<Value> = MessageReader::new(stream.as_slice(), &schema, 0).map(Result::unwrap).collect();
+/// assert_eq!(read, [ping]);
+/// ```
+pub struct MessageWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> MessageWriter<W> {
+    /// Writes messages to `writer`.
+    pub fn new(writer: W) -> Self {
+        MessageWriter { writer }
+    }
+
+    /// Returns the underlying writer, without flushing it.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Encodes `value` with `schema` and writes it as one message. A value
+    /// that doesn't match the schema is a `KiwiError::EncodeError`, and
+    /// nothing is written; I/O errors are `KiwiError::Io`.
+    pub fn write_message(&mut self, value: &Value, schema: &Schema) -> Result<(), KiwiError> {
+        let body = value.try_encode(schema)?;
+        let length = u32::try_from(body.len())
+            .map_err(|_| KiwiError::EncodeError(format!("Message of {} bytes is too long to frame", body.len())))?;
+        let mut prefix = ByteBufferMut::new();
+        prefix.write_var_uint(length);
+        self.writer.write_all(&prefix.data())?;
+        self.writer.write_all(&body)?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<(), KiwiError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+#![cfg(test)]
+
+use std::collections::HashMap;
+
+use brine_kiwi::{KiwiError, Schema, SchemaValueBuilder, Value};
+use brine_kiwi_compiler::compile_schema;
+
+const SCHEMA: &str = r#"
+    enum Type { FLAT = 0; ROUND = 1; }
+    struct Color { byte red; byte alpha; }
+    message Example {
+      uint clientID = 1;
+      Type type = 2;
+      Color[] colors = 3;
+    }
+"#;
+
+fn schema() -> Schema {
+    let (_, bin) = compile_schema(SCHEMA).expect("compile_schema failed");
+    Schema::decode(&bin).expect("Schema::decode failed")
+}
+
+fn color(red: Value<'static>) -> Value<'static> {
+    let mut fields = HashMap::new();
+    fields.insert("red", red);
+    fields.insert("alpha", Value::Byte(255));
+    Value::Object("Color", fields)
+}
+
+#[test]
+fn test_builder_builds_encodable_value() {
+    let schema = schema();
+    let value = SchemaValueBuilder::new(&schema, "Example")
+        .and_then(|b| b.set("clientID", Value::UInt(7)))
+        .and_then(|b| b.set("type", Value::Enum("Type", "ROUND")))
+        .and_then(|b| b.set("colors", Value::Array(vec![color(Value::Byte(1))])))
+        .and_then(|b| b.build())
+        .expect("builder failed");
+
+    let bytes = value.encode(&schema);
+    assert_eq!(Value::decode(&schema, 2, &bytes), Ok(value));
+}
+
+#[test]
+fn test_builder_fails_at_the_mistake() {
+    let schema = schema();
+    let err = |result: Result<SchemaValueBuilder, KiwiError>| result.unwrap_err().to_string();
+
+    assert_eq!(
+        err(SchemaValueBuilder::new(&schema, "Example").and_then(|b| b.set("clientId", Value::UInt(7)))),
+        "Schema encode error: Example has no field \"clientId\""
+    );
+    assert_eq!(
+        err(SchemaValueBuilder::new(&schema, "Example").and_then(|b| b.set("clientID", Value::Int(7)))),
+        "Schema encode error: Example.clientID: expected UInt, found Int"
+    );
+    assert_eq!(
+        err(SchemaValueBuilder::new(&schema, "Example")
+            .and_then(|b| b.set("colors", Value::Array(vec![color(Value::Byte(1)), color(Value::Int(2))])))),
+        "Schema encode error: Example.colors.1.red: expected Byte, found Int"
+    );
+    assert!(SchemaValueBuilder::new(&schema, "Type").is_err());
+    assert!(SchemaValueBuilder::new(&schema, "Missing").is_err());
+}
+
+#[test]
+fn test_builder_requires_struct_fields() {
+    let schema = schema();
+    let result = SchemaValueBuilder::new(&schema, "Color")
+        .and_then(|b| b.set("red", Value::Byte(1)))
+        .and_then(|b| b.build());
+    assert!(matches!(result, Err(KiwiError::MissingField(ref name)) if name == "Color.alpha"));
+
+    // Message fields are all optional.
+    assert!(SchemaValueBuilder::new(&schema, "Example").and_then(|b| b.build()).is_ok());
+}
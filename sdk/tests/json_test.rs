@@ -0,0 +1,168 @@
+#![cfg(test)]
+
+use std::collections::HashMap;
+
+use brine_kiwi::{
+    decode_message_to_json, decode_schema_to_json, value_from_json, value_to_json, KiwiError, Schema, Value,
+};
+use brine_kiwi_compiler::compile_schema;
+
+const SCHEMA: &str = r#"
+    enum Type { FLAT = 0; ROUND = 1; }
+    struct Color { byte red; float alpha; }
+    message Example {
+      uint clientID = 1;
+      Type type = 2;
+      Color[] colors = 3;
+      string name = 4;
+    }
+"#;
+
+#[test]
+fn test_decode_message_to_json() {
+    let (_, bin) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let schema = Schema::decode(&bin).expect("Schema::decode failed");
+
+    let mut color = HashMap::new();
+    color.insert("red", Value::Byte(255));
+    color.insert("alpha", Value::Float(0.5));
+    let mut fields = HashMap::new();
+    fields.insert("clientID", Value::UInt(7));
+    fields.insert("type", Value::Enum("Type", "ROUND"));
+    fields.insert("colors", Value::Array(vec![Value::Object("Color", color)]));
+    let bytes = Value::Object("Example", fields).encode(&schema);
+
+    let json = decode_message_to_json(&schema, 2, &bytes).expect("decode_message_to_json failed");
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        parsed,
+        serde_json::json!({
+            "clientID": 7,
+            "type": "ROUND",
+            "colors": [{ "red": 255, "alpha": 0.5 }]
+        })
+    );
+
+    assert!(decode_message_to_json(&schema, 2, &[0xFF]).is_err());
+}
+
+#[test]
+fn test_decode_schema_to_json_describes_definitions() {
+    let (_, bin) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let json = decode_schema_to_json(&bin).expect("decode_schema_to_json failed");
+    assert!(json.contains("\"name\": \"Example\""));
+}
+
+#[test]
+fn test_value_to_json_rejects_keys_json_cannot_represent() {
+    let value = Value::Map(vec![(Value::String("a".to_string()), Value::UInt(1))]);
+    assert_eq!(value_to_json(&value).unwrap(), serde_json::json!({ "a": 1 }));
+
+    let value = Value::Map(vec![(Value::Array(vec![Value::UInt(1)]), Value::UInt(1))]);
+    assert!(matches!(value_to_json(&value), Err(KiwiError::EncodeError(_))));
+
+    let value = Value::Map(vec![(Value::Float(f32::NAN), Value::UInt(1))]);
+    assert!(matches!(value_to_json(&value), Err(KiwiError::EncodeError(_))));
+}
+
+#[test]
+fn test_value_from_json_round_trips_to_binary() {
+    let (_, bin) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let schema = Schema::decode(&bin).expect("Schema::decode failed");
+
+    let json = serde_json::json!({
+        "clientID": 7,
+        "type": "ROUND",
+        "colors": [{ "red": 255, "alpha": 0.5 }]
+    });
+    let value = value_from_json(&schema, 2, &json).expect("value_from_json failed");
+    assert_eq!(value.get("colors").unwrap()[0].get("red"), Some(&Value::Byte(255)));
+    assert_eq!(value.get("type"), Some(&Value::Enum("Type", "ROUND")));
+
+    let bytes = value.encode(&schema);
+    let decoded = decode_message_to_json(&schema, 2, &bytes).expect("decode_message_to_json failed");
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&decoded).unwrap(), json);
+}
+
+#[test]
+fn test_value_from_json_reports_mismatches() {
+    let (_, bin) = compile_schema(SCHEMA).expect("compile_schema failed");
+    let schema = Schema::decode(&bin).expect("Schema::decode failed");
+
+    let error = |json: serde_json::Value| value_from_json(&schema, 2, &json).unwrap_err().to_string();
+    assert_eq!(
+        error(serde_json::json!({ "colors": [{ "red": 256, "alpha": 1.0 }] })),
+        "Schema decode error: colors.0.red: expected byte, found 256"
+    );
+    assert_eq!(
+        error(serde_json::json!({ "colors": [{ "alpha": 1.0 }] })),
+        "Missing required field \"colors.0.red\""
+    );
+    assert_eq!(
+        error(serde_json::json!({ "clientId": 7 })),
+        "Schema decode error: clientId: unknown field of Example"
+    );
+    assert!(matches!(
+        value_from_json(&schema, 2, &serde_json::json!({ "type": "SQUARE" })),
+        Err(KiwiError::InvalidEnumVariant(_))
+    ));
+}
+
+#[test]
+fn test_value_from_json_maps_round_trip() {
+    let (_, bin) = compile_schema(
+        "enum Type { FLAT = 0; ROUND = 1; }
+         message Counts { map<int, string> names = 1; map<Type, uint> shapes = 2; }",
+    )
+    .expect("compile_schema failed");
+    let schema = Schema::decode(&bin).expect("Schema::decode failed");
+
+    let json = serde_json::json!({
+        "names": { "-1": "minus one", "2": "two" },
+        "shapes": { "ROUND": 3 }
+    });
+    let value = value_from_json(&schema, 1, &json).expect("value_from_json failed");
+    assert_eq!(
+        value.get("shapes"),
+        Some(&Value::Map(vec![(Value::Enum("Type", "ROUND"), Value::UInt(3))]))
+    );
+
+    let bytes = value.encode(&schema);
+    let decoded = decode_message_to_json(&schema, 1, &bytes).expect("decode_message_to_json failed");
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&decoded).unwrap(), json);
+
+    let error = value_from_json(&schema, 1, &serde_json::json!({ "names": { "x": "y" } })).unwrap_err();
+    assert_eq!(error.to_string(), "Schema decode error: names.x: expected int, found \"x\"");
+}
+
+#[test]
+fn test_value_from_json_byte_arrays() {
+    let (_, bin) = compile_schema("message Blob { byte[] data = 1; byte[][] rows = 2; }").expect("compile_schema failed");
+    let schema = Schema::decode(&bin).expect("Schema::decode failed");
+
+    let json = serde_json::json!({ "data": [1, 2, 255], "rows": [[7], []] });
+    let value = value_from_json(&schema, 0, &json).expect("value_from_json failed");
+    assert_eq!(value.get("data"), Some(&Value::Bytes(vec![1, 2, 255])));
+    assert_eq!(value.get("rows"), Some(&Value::Array(vec![Value::Bytes(vec![7]), Value::Bytes(vec![])])));
+
+    let bytes = value.encode(&schema);
+    assert_eq!(Value::decode(&schema, 0, &bytes), Ok(value));
+    let decoded = decode_message_to_json(&schema, 0, &bytes).expect("decode_message_to_json failed");
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&decoded).unwrap(), json);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_module_paths() {
+    use brine_kiwi::{error, schema, traits::FromKiwi};
+
+    struct Id(u32);
+    impl FromKiwi for Id {
+        fn from_kiwi(value: &schema::Value) -> Result<Self, error::KiwiError> {
+            value.as_uint_opt().map(Id).ok_or_else(|| error::KiwiError::MissingField("id".into()))
+        }
+    }
+    assert_eq!(Id::from_kiwi(&Value::UInt(7)).unwrap().0, 7);
+    let _: Option<&schema::Field> = None;
+    let _: Option<&schema::Schema> = None;
+}
@@ -0,0 +1,125 @@
+#![cfg(test)]
+
+use std::io::{self, Read};
+
+use brine_kiwi::{KiwiError, MessageReader, MessageWriter, Schema, Value};
+use brine_kiwi_compiler::compile_schema;
+
+fn schema() -> Schema {
+    let (_, bin) = compile_schema("message Ping { uint seq = 1; string note = 2; }").expect("compile_schema failed");
+    Schema::decode(&bin).expect("Schema::decode failed")
+}
+
+/// Frames each encoded value with its var_uint length.
+fn framed(schema: &Schema, seqs: &[u32]) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for &seq in seqs {
+        let mut fields = brine_kiwi::Map::new();
+        fields.insert("seq", Value::UInt(seq));
+        fields.insert("note", Value::String("x".repeat(seq as usize)));
+        let bytes = Value::Object("Ping", fields).encode(schema);
+        let mut length = bytes.len();
+        while length >= 128 {
+            stream.push((length as u8) | 128);
+            length >>= 7;
+        }
+        stream.push(length as u8);
+        stream.extend(bytes);
+    }
+    stream
+}
+
+/// Hands out at most one byte per `read`, like a slow socket.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((&byte, rest)), Some(slot)) => {
+                *slot = byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+fn seqs<'a>(values: impl Iterator<Item = Result<Value<'a>, KiwiError>>) -> Vec<u32> {
+    values.map(|value| value.unwrap().get("seq").unwrap().as_uint()).collect()
+}
+
+#[test]
+fn test_message_reader_handles_partial_reads() {
+    let schema = schema();
+    let stream = framed(&schema, &[1, 200, 3]);
+
+    assert_eq!(seqs(MessageReader::new(stream.as_slice(), &schema, 0)), [1, 200, 3]);
+    assert_eq!(seqs(MessageReader::new(Trickle(&stream), &schema, 0)), [1, 200, 3]);
+    assert_eq!(MessageReader::new(&[][..], &schema, 0).count(), 0);
+}
+
+#[test]
+fn test_message_reader_reports_errors() {
+    let schema = schema();
+    let stream = framed(&schema, &[1, 2]);
+
+    // Cut off inside the second message: an I/O error, then nothing.
+    let mut reader = MessageReader::new(&stream[..stream.len() - 1], &schema, 0);
+    assert!(reader.next().unwrap().is_ok());
+    assert!(matches!(
+        reader.next(),
+        Some(Err(KiwiError::Io(ref err))) if err.kind() == io::ErrorKind::UnexpectedEof
+    ));
+    assert!(reader.next().is_none());
+
+    // A message that doesn't decode is skipped over.
+    let mut bad = vec![2, 9, 0];
+    bad.extend(&stream);
+    let results: Vec<_> = MessageReader::new(bad.as_slice(), &schema, 0).collect();
+    assert_eq!(results.len(), 3);
+    assert!(matches!(results[0], Err(KiwiError::DecodeError(_))));
+    assert!(results[1..].iter().all(Result::is_ok));
+
+    // Errors from the stream itself come through as `KiwiError::Io`.
+    struct Broken;
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+    let mut reader = MessageReader::new(Broken, &schema, 0);
+    assert!(matches!(
+        reader.next(),
+        Some(Err(KiwiError::Io(ref err))) if err.kind() == io::ErrorKind::ConnectionReset
+    ));
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_message_writer_matches_reader_framing() {
+    let schema = schema();
+    let expected = framed(&schema, &[1, 200, 3]);
+
+    let values: Vec<Value> = MessageReader::new(expected.as_slice(), &schema, 0).map(Result::unwrap).collect();
+    let mut writer = MessageWriter::new(Vec::new());
+    for value in &values {
+        writer.write_message(value, &schema).unwrap();
+    }
+    writer.flush().unwrap();
+    assert_eq!(writer.into_inner(), expected);
+
+    // A value the schema can't encode writes nothing.
+    let mut writer = MessageWriter::new(Vec::new());
+    let bad = Value::Object("Pong", brine_kiwi::Map::new());
+    assert!(matches!(writer.write_message(&bad, &schema), Err(KiwiError::EncodeError(_))));
+    assert!(writer.into_inner().is_empty());
+
+    // A full buffer fails the write.
+    let mut small = [0u8; 2];
+    let mut writer = MessageWriter::new(&mut small[..]);
+    assert!(matches!(
+        writer.write_message(&values[1], &schema),
+        Err(KiwiError::Io(ref err)) if err.kind() == io::ErrorKind::WriteZero
+    ));
+}